@@ -190,7 +190,7 @@ fn main() -> Result<(), Box<dyn Error>> {
             WindowOptions {
                 ..Default::default()
             },
-            |_, cx| cx.new(|cx| Workspace::new(cx)),
+            |_, cx| cx.new(Workspace::new),
         )
         .unwrap();
     });
@@ -16,6 +16,8 @@ impl Workspace {
 }
 
 impl Render for Workspace {
+    // `number` is a plain local that is reset on every render, unlike use_state
+    #[allow(unused_assignments)]
     fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         // use_state creates state that persists as long as this render method
         // is called in consecutive frames. It automatically generates a key
@@ -146,7 +148,7 @@ fn main() -> Result<(), Box<dyn Error>> {
             WindowOptions {
                 ..Default::default()
             },
-            |_, cx| cx.new(|cx| Workspace::new(cx)),
+            |_, cx| cx.new(Workspace::new),
         )
         .unwrap();
     });
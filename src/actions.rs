@@ -1,4 +1,5 @@
 use gpui::actions;
 
 actions!(app, [Quit]);
-actions!(menu, [OpenFile]);
+actions!(menu, [OpenFile, OpenSettings]);
+actions!(tabs, [NextTab, PreviousTab, CloseTab]);
@@ -0,0 +1,581 @@
+use std::collections::{BinaryHeap, HashSet};
+use std::path::PathBuf;
+
+#[cfg(any(test, feature = "test-support"))]
+use std::collections::{BTreeMap, HashMap};
+#[cfg(any(test, feature = "test-support"))]
+use std::sync::{Arc, Mutex};
+
+#[cfg(any(test, feature = "test-support"))]
+use git2::ObjectType;
+use git2::{Oid, Repository};
+
+use crate::error::GpigError;
+use crate::garph::{ChangedFile, Garph};
+use crate::refs::{BranchInfo, RemoteInfo, RepoRefs};
+use crate::settings::{DiffLimits, SortOrder};
+
+/// What HEAD resolves to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Head {
+    Commit(Oid),
+    /// A branch without commits yet.
+    Unborn(String),
+    /// HEAD does not lead to a readable commit.
+    Broken(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct CommitInfo {
+    pub oid: Oid,
+    pub message: String,
+    pub author: String,
+    pub time: git2::Time,
+    pub parents: Vec<Oid>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StashEntry {
+    pub index: usize,
+    pub oid: Oid,
+    /// The commit the stash was made on.
+    pub base: Oid,
+    pub message: String,
+}
+
+/// Read access to a repository for the graph and the diff views.
+pub trait Backend: Send {
+    fn resolve_head(&self) -> Head;
+
+    /// Up to `limit` commits reachable from `from`, children before parents, newest first.
+    /// Parents that cannot be read end the walk along that line instead of failing it;
+    /// such walks order by date whatever `sort` asks for.
+    fn walk(&self, from: Oid, limit: usize, sort: SortOrder) -> Result<Vec<Oid>, GpigError>;
+
+    fn commit_info(&self, oid: Oid) -> Result<CommitInfo, GpigError>;
+
+    /// Whether the object is present, parents may be missing after a shallow clone.
+    fn contains(&self, oid: Oid) -> bool;
+
+    /// Commits whose parents a shallow clone left out.
+    fn shallow_boundary(&self) -> HashSet<Oid>;
+
+    /// Local branches with their upstreams, sorted by name.
+    fn branches(&self) -> Result<Vec<BranchInfo>, GpigError>;
+
+    /// Remotes with their remote-tracking branches.
+    fn remotes(&self) -> Result<Vec<RemoteInfo>, GpigError>;
+
+    /// Tag names and the commits they peel to.
+    fn tags(&self) -> Vec<(String, Oid)>;
+
+    /// Newest first.
+    fn stashes(&self) -> Vec<StashEntry>;
+
+    /// Files the commit changed compared to its first parent.
+    fn changed_files(&self, oid: Oid) -> Result<Vec<ChangedFile>, GpigError>;
+
+    /// Text diff of one file in a commit, cut at the diff limits.
+    fn file_diff(&self, oid: Oid, path: &str, limits: &DiffLimits) -> Result<String, GpigError>;
+
+    fn blob_content(&self, oid: Oid) -> Result<Vec<u8>, GpigError>;
+}
+
+impl Backend for Repository {
+    fn resolve_head(&self) -> Head {
+        match self.head() {
+            Ok(head) => match head.peel_to_commit() {
+                Ok(commit) => Head::Commit(commit.id()),
+                Err(e) => Head::Broken(e.message().to_string()),
+            },
+            Err(e) if e.code() == git2::ErrorCode::UnbornBranch => {
+                let branch = self
+                    .find_reference("HEAD")
+                    .ok()
+                    .and_then(|head| head.symbolic_target().map(String::from))
+                    .unwrap_or_default();
+                Head::Unborn(
+                    branch
+                        .strip_prefix("refs/heads/")
+                        .unwrap_or(&branch)
+                        .to_string(),
+                )
+            }
+            Err(e) => Head::Broken(e.message().to_string()),
+        }
+    }
+
+    fn walk(&self, from: Oid, limit: usize, sort: SortOrder) -> Result<Vec<Oid>, GpigError> {
+        let mut revwalk = self.revwalk()?;
+        revwalk.set_sorting(sort.git_sort())?;
+        revwalk.push(from)?;
+        // the revwalk gives up on missing parent objects, which the lenient walk skips
+        match revwalk.take(limit).collect::<Result<Vec<Oid>, _>>() {
+            Ok(oids) => Ok(oids),
+            Err(_) => Ok(lenient_walk(from, limit, |oid| {
+                let commit = self.find_commit(oid).ok()?;
+                Some((commit.time().seconds(), commit.parent_ids().collect()))
+            })),
+        }
+    }
+
+    fn commit_info(&self, oid: Oid) -> Result<CommitInfo, GpigError> {
+        let commit = self.find_commit(oid)?;
+        Ok(CommitInfo {
+            oid,
+            message: commit.message().unwrap_or_default().to_string(),
+            author: commit.author().email().unwrap_or_default().to_string(),
+            time: commit.time(),
+            parents: commit.parent_ids().collect(),
+        })
+    }
+
+    fn contains(&self, oid: Oid) -> bool {
+        self.odb().is_ok_and(|odb| odb.exists(oid))
+    }
+
+    fn shallow_boundary(&self) -> HashSet<Oid> {
+        std::fs::read_to_string(self.path().join("shallow"))
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| Oid::from_str(line.trim()).ok())
+            .collect()
+    }
+
+    fn branches(&self) -> Result<Vec<BranchInfo>, GpigError> {
+        Ok(RepoRefs::load_local_branches(self)?)
+    }
+
+    fn remotes(&self) -> Result<Vec<RemoteInfo>, GpigError> {
+        Ok(RepoRefs::load_remotes(self)?)
+    }
+
+    fn tags(&self) -> Vec<(String, Oid)> {
+        let Ok(references) = self.references_glob("refs/tags/*") else {
+            return Vec::new();
+        };
+        references
+            .flatten()
+            .filter_map(|reference| {
+                let commit = reference.peel_to_commit().ok()?;
+                Some((reference.shorthand()?.to_string(), commit.id()))
+            })
+            .collect()
+    }
+
+    fn stashes(&self) -> Vec<StashEntry> {
+        let Ok(reflog) = self.reflog("refs/stash") else {
+            return Vec::new();
+        };
+        reflog
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| {
+                let stash = self.find_commit(entry.id_new()).ok()?;
+                Some(StashEntry {
+                    index,
+                    oid: stash.id(),
+                    base: stash.parent_id(0).ok()?,
+                    message: entry.message().unwrap_or_default().to_string(),
+                })
+            })
+            .collect()
+    }
+
+    fn changed_files(&self, oid: Oid) -> Result<Vec<ChangedFile>, GpigError> {
+        Garph::changed_files(self, &oid)
+    }
+
+    fn file_diff(&self, oid: Oid, path: &str, limits: &DiffLimits) -> Result<String, GpigError> {
+        Garph::file_diff(self, &oid, path, limits)
+    }
+
+    fn blob_content(&self, oid: Oid) -> Result<Vec<u8>, GpigError> {
+        Ok(self.find_blob(oid)?.content().to_vec())
+    }
+}
+
+/// Newest-first walk from `head` that stops at parents `read` cannot find. `read` gives a
+/// commit's time and parents.
+fn lenient_walk(
+    head: Oid,
+    limit: usize,
+    read: impl Fn(Oid) -> Option<(i64, Vec<Oid>)>,
+) -> Vec<Oid> {
+    let mut queue = BinaryHeap::new();
+    let mut seen = HashSet::from([head]);
+    let mut oids = Vec::new();
+    if let Some((time, _)) = read(head) {
+        queue.push((time, head));
+    }
+    while let Some((_, oid)) = queue.pop() {
+        if oids.len() == limit {
+            break;
+        }
+        oids.push(oid);
+        let Some((_, parents)) = read(oid) else {
+            continue;
+        };
+        for parent in parents {
+            if seen.insert(parent)
+                && let Some((time, _)) = read(parent)
+            {
+                queue.push((time, parent));
+            }
+        }
+    }
+    oids
+}
+
+/// Where a background task gets its own backend from; libgit2 handles must not be shared
+/// between threads, so each task opens the repository again.
+#[derive(Clone, Debug)]
+pub enum RepoHandle {
+    /// Path of the `.git` directory.
+    Git(PathBuf),
+    #[cfg(any(test, feature = "test-support"))]
+    Memory(MemoryBackend),
+}
+
+impl RepoHandle {
+    pub fn open(&self) -> Result<Box<dyn Backend>, GpigError> {
+        match self {
+            RepoHandle::Git(git_dir) => Ok(Box::new(Repository::open(git_dir)?)),
+            #[cfg(any(test, feature = "test-support"))]
+            RepoHandle::Memory(backend) => Ok(Box::new(backend.clone())),
+        }
+    }
+}
+
+#[cfg(any(test, feature = "test-support"))]
+#[derive(Debug)]
+struct MemoryCommit {
+    info: CommitInfo,
+    /// Path to blob id, the whole tree of the commit.
+    files: BTreeMap<String, Oid>,
+}
+
+#[cfg(any(test, feature = "test-support"))]
+#[derive(Debug, Default)]
+struct MemoryRepo {
+    branch: String,
+    /// Set once HEAD is detached or the branch has commits.
+    head: Option<Oid>,
+    /// Branches other than the one HEAD is on.
+    branches: BTreeMap<String, Oid>,
+    commits: HashMap<Oid, MemoryCommit>,
+    blobs: HashMap<Oid, Vec<u8>>,
+    tags: Vec<(String, Oid)>,
+    shallow: HashSet<Oid>,
+}
+
+#[cfg(any(test, feature = "test-support"))]
+/// An in-memory repository for tests, built up commit by commit. Clones share the same
+/// history.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryBackend {
+    repo: Arc<Mutex<MemoryRepo>>,
+}
+
+#[cfg(any(test, feature = "test-support"))]
+impl MemoryBackend {
+    /// An empty repository with HEAD on the unborn `branch`.
+    pub fn new(branch: &str) -> Self {
+        Self {
+            repo: Arc::new(Mutex::new(MemoryRepo {
+                branch: branch.to_string(),
+                ..MemoryRepo::default()
+            })),
+        }
+    }
+
+    /// Commit on top of HEAD, writing each `(path, content)`.
+    pub fn commit(&self, message: &str, files: &[(&str, &str)]) -> Oid {
+        let parents = self.lock().head.into_iter().collect();
+        self.commit_with_parents(message, parents, files, &[])
+    }
+
+    /// Commit on top of HEAD with `removed` paths deleted.
+    pub fn remove(&self, message: &str, removed: &[&str]) -> Oid {
+        let parents = self.lock().head.into_iter().collect();
+        self.commit_with_parents(message, parents, &[], removed)
+    }
+
+    /// A merge of HEAD and `other`, keeping the tree of HEAD.
+    pub fn merge(&self, message: &str, other: Oid) -> Oid {
+        let parents = self.lock().head.into_iter().chain([other]).collect();
+        self.commit_with_parents(message, parents, &[], &[])
+    }
+
+    /// Move HEAD to `oid`, for building side branches.
+    pub fn checkout(&self, oid: Oid) {
+        self.lock().head = Some(oid);
+    }
+
+    /// A branch at `oid` next to the one HEAD is on.
+    pub fn branch(&self, name: &str, oid: Oid) {
+        self.lock().branches.insert(name.to_string(), oid);
+    }
+
+    pub fn tag(&self, name: &str, oid: Oid) {
+        self.lock().tags.push((name.to_string(), oid));
+    }
+
+    /// Drop a commit object, as if it was never fetched.
+    pub fn forget(&self, oid: Oid) {
+        self.lock().commits.remove(&oid);
+    }
+
+    /// Mark `oid` as the edge of a shallow clone.
+    pub fn set_shallow(&self, oid: Oid) {
+        self.lock().shallow.insert(oid);
+    }
+
+    fn commit_with_parents(
+        &self,
+        message: &str,
+        parents: Vec<Oid>,
+        files: &[(&str, &str)],
+        removed: &[&str],
+    ) -> Oid {
+        let mut repo = self.lock();
+        let mut tree = parents
+            .first()
+            .and_then(|parent| repo.commits.get(parent))
+            .map(|parent| parent.files.clone())
+            .unwrap_or_default();
+        for (path, content) in files {
+            let blob = Oid::hash_object(ObjectType::Blob, content.as_bytes())
+                .expect("hashing in memory cannot fail");
+            repo.blobs.insert(blob, content.as_bytes().to_vec());
+            tree.insert(path.to_string(), blob);
+        }
+        for path in removed {
+            tree.remove(*path);
+        }
+
+        // one minute apart, so time order matches commit order
+        let seconds = 1_700_000_000 + repo.commits.len() as i64 * 60;
+        let oid = Oid::hash_object(
+            ObjectType::Commit,
+            format!("{:?}\n{:?}\n{}\n{}", tree, parents, seconds, message).as_bytes(),
+        )
+        .expect("hashing in memory cannot fail");
+        repo.commits.insert(
+            oid,
+            MemoryCommit {
+                info: CommitInfo {
+                    oid,
+                    message: message.to_string(),
+                    author: "gpig@example.com".to_string(),
+                    time: git2::Time::new(seconds, 0),
+                    parents,
+                },
+                files: tree,
+            },
+        );
+        repo.head = Some(oid);
+        oid
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MemoryRepo> {
+        self.repo
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn not_found(what: &str, oid: Oid) -> GpigError {
+        GpigError::Git {
+            class: git2::ErrorClass::Odb,
+            code: git2::ErrorCode::NotFound,
+            message: format!("{} {} not found", what, oid),
+        }
+    }
+}
+
+#[cfg(any(test, feature = "test-support"))]
+impl Backend for MemoryBackend {
+    fn resolve_head(&self) -> Head {
+        let repo = self.lock();
+        match repo.head {
+            None => Head::Unborn(repo.branch.clone()),
+            Some(oid) if repo.commits.contains_key(&oid) => Head::Commit(oid),
+            Some(oid) => Head::Broken(format!("object not found - no match for id ({})", oid)),
+        }
+    }
+
+    fn walk(&self, from: Oid, limit: usize, _sort: SortOrder) -> Result<Vec<Oid>, GpigError> {
+        let repo = self.lock();
+        Ok(lenient_walk(from, limit, |oid| {
+            let commit = repo.commits.get(&oid)?;
+            Some((commit.info.time.seconds(), commit.info.parents.clone()))
+        }))
+    }
+
+    fn commit_info(&self, oid: Oid) -> Result<CommitInfo, GpigError> {
+        self.lock()
+            .commits
+            .get(&oid)
+            .map(|commit| commit.info.clone())
+            .ok_or_else(|| Self::not_found("commit", oid))
+    }
+
+    fn contains(&self, oid: Oid) -> bool {
+        let repo = self.lock();
+        repo.commits.contains_key(&oid) || repo.blobs.contains_key(&oid)
+    }
+
+    fn shallow_boundary(&self) -> HashSet<Oid> {
+        self.lock().shallow.clone()
+    }
+
+    fn branches(&self) -> Result<Vec<BranchInfo>, GpigError> {
+        let repo = self.lock();
+        let head = repo.head.map(|oid| (&repo.branch, oid, true));
+        let others = repo.branches.iter().map(|(name, oid)| (name, *oid, false));
+        let mut branches: Vec<BranchInfo> = head
+            .into_iter()
+            .chain(others)
+            .filter_map(|(name, oid, is_head)| {
+                Some(BranchInfo {
+                    name: name.clone(),
+                    oid,
+                    is_head,
+                    upstream: None,
+                    ahead: 0,
+                    behind: 0,
+                    last_commit: repo.commits.get(&oid)?.info.time,
+                })
+            })
+            .collect();
+        branches.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(branches)
+    }
+
+    fn remotes(&self) -> Result<Vec<RemoteInfo>, GpigError> {
+        Ok(Vec::new())
+    }
+
+    fn tags(&self) -> Vec<(String, Oid)> {
+        self.lock().tags.clone()
+    }
+
+    fn stashes(&self) -> Vec<StashEntry> {
+        Vec::new()
+    }
+
+    fn changed_files(&self, oid: Oid) -> Result<Vec<ChangedFile>, GpigError> {
+        let repo = self.lock();
+        let commit = repo
+            .commits
+            .get(&oid)
+            .ok_or_else(|| Self::not_found("commit", oid))?;
+        let empty = BTreeMap::new();
+        let parent = match commit.info.parents.first() {
+            Some(parent) => {
+                &repo
+                    .commits
+                    .get(parent)
+                    .ok_or_else(|| Self::not_found("commit", *parent))?
+                    .files
+            }
+            None => &empty,
+        };
+
+        let paths: BTreeMap<&String, ()> = parent
+            .keys()
+            .chain(commit.files.keys())
+            .map(|path| (path, ()))
+            .collect();
+        Ok(paths
+            .into_keys()
+            .filter_map(|path| {
+                let old_oid = parent.get(path).copied();
+                let new_oid = commit.files.get(path).copied();
+                let status = match (old_oid, new_oid) {
+                    (None, Some(_)) => git2::Delta::Added,
+                    (Some(_), None) => git2::Delta::Deleted,
+                    (Some(old), Some(new)) if old != new => git2::Delta::Modified,
+                    _ => return None,
+                };
+                Some(ChangedFile {
+                    path: path.clone(),
+                    status,
+                    old_oid,
+                    new_oid,
+                })
+            })
+            .collect())
+    }
+
+    fn file_diff(&self, oid: Oid, path: &str, limits: &DiffLimits) -> Result<String, GpigError> {
+        let file = self
+            .changed_files(oid)?
+            .into_iter()
+            .find(|file| file.path == path)
+            .ok_or_else(|| GpigError::PathNotInDiff(path.to_string()))?;
+        let read = |blob: Option<Oid>| -> Result<Vec<u8>, GpigError> {
+            blob.map_or(Ok(Vec::new()), |blob| self.blob_content(blob))
+        };
+        let (old, new) = (read(file.old_oid)?, read(file.new_oid)?);
+        if new.contains(&0) || new.len() > limits.max_file_size_bytes {
+            return Err(GpigError::BinaryFile {
+                path: path.to_string(),
+                size: new.len(),
+            });
+        }
+
+        let old = String::from_utf8_lossy(&old);
+        let new = String::from_utf8_lossy(&new);
+        let old: Vec<&str> = old.lines().collect();
+        let new: Vec<&str> = new.lines().collect();
+        // one hunk: the shared lines around a single changed block
+        let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        let mut lines = match file.status {
+            git2::Delta::Added => vec![format!("+++ a/{}", path)],
+            git2::Delta::Deleted => vec![format!("--- a/{}", path)],
+            _ => vec![format!("--- a/{}", path), format!("+++ b/{}", path)],
+        };
+        lines.push(format!("@@ -1,{} +1,{} @@", old.len(), new.len()));
+        lines.extend(old[..prefix].iter().map(|line| format!(" {}", line)));
+        lines.extend(
+            old[prefix..old.len() - suffix]
+                .iter()
+                .map(|line| format!("-{}", line)),
+        );
+        lines.extend(
+            new[prefix..new.len() - suffix]
+                .iter()
+                .map(|line| format!("+{}", line)),
+        );
+        lines.extend(
+            old[old.len() - suffix..]
+                .iter()
+                .map(|line| format!(" {}", line)),
+        );
+        if lines.len() > limits.max_file_diff_lines {
+            lines.truncate(limits.max_file_diff_lines);
+            lines.push(String::new());
+            lines.push(format!(
+                "... (showing first {} lines, diff truncated)",
+                limits.max_file_diff_lines
+            ));
+        }
+        Ok(lines.join("\n"))
+    }
+
+    fn blob_content(&self, oid: Oid) -> Result<Vec<u8>, GpigError> {
+        self.lock()
+            .blobs
+            .get(&oid)
+            .cloned()
+            .ok_or_else(|| Self::not_found("blob", oid))
+    }
+}
@@ -0,0 +1,49 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use git2::Repository;
+
+/// Environment variable naming the repository to open, also read from `.env`.
+pub const REPO_ENV: &str = "GPIG_REPO";
+
+pub const USAGE: &str = "usage: gpig [PATH]\n\nOpens the git repository containing PATH, or $GPIG_REPO, or the current directory.";
+
+/// What the command line asked for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Launch {
+    Help,
+    /// Open the repository enclosing `path`. `explicit` is false when it is just the
+    /// current directory, which need not be inside a repository.
+    Open {
+        path: PathBuf,
+        explicit: bool,
+    },
+}
+
+/// The path argument wins over `GPIG_REPO`, which wins over the current directory.
+/// `args` excludes the program name.
+pub fn parse(args: impl IntoIterator<Item = OsString>, env_repo: Option<OsString>) -> Launch {
+    let mut args = args.into_iter();
+    match args.next() {
+        Some(arg) if arg == "-h" || arg == "--help" => Launch::Help,
+        Some(arg) => Launch::Open {
+            path: PathBuf::from(arg),
+            explicit: true,
+        },
+        None => match env_repo.filter(|path| !path.is_empty()) {
+            Some(path) => Launch::Open {
+                path: PathBuf::from(path),
+                explicit: true,
+            },
+            None => Launch::Open {
+                path: PathBuf::from("."),
+                explicit: false,
+            },
+        },
+    }
+}
+
+/// The repository `path` is in, searching parent directories the way `git` does.
+pub fn discover(path: &Path) -> Result<Repository, git2::Error> {
+    Repository::discover(path)
+}
@@ -9,6 +9,10 @@ pub struct CommitNode {
     pub parents: Vec<Oid>,
     pub position: Point<Pixels>,
     pub color: usize,
+    /// Index in the stash list when this node is a stash rather than a commit.
+    pub stash: Option<usize>,
+    /// Some parents are not in the repository, e.g. beyond a shallow clone's depth.
+    pub truncated: bool,
 }
 
 impl CommitNode {
@@ -29,6 +33,8 @@ impl CommitNode {
             parents,
             position,
             color,
+            stash: None,
+            truncated: false,
         }
     }
 }
@@ -0,0 +1,271 @@
+use std::path::Path;
+
+use git2::{IndexEntry, MergeFileOptions, Repository};
+
+const OURS_LABEL: &str = "ours";
+const BASE_LABEL: &str = "base";
+const THEIRS_LABEL: &str = "theirs";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Resolution {
+    Ours,
+    Theirs,
+    /// Ours followed by theirs.
+    Both,
+    Base,
+    /// Lines typed in by the user.
+    Custom(Vec<String>),
+}
+
+/// A run of lines in the merged file. Lines keep their trailing newline.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConflictChunk {
+    /// Merged without conflict.
+    Clean(Vec<String>),
+    Conflict {
+        base: Vec<String>,
+        ours: Vec<String>,
+        theirs: Vec<String>,
+        resolution: Option<Resolution>,
+    },
+}
+
+impl ConflictChunk {
+    /// The lines this chunk contributes to the result, if it is decided.
+    pub fn result_lines(&self) -> Option<Vec<String>> {
+        match self {
+            ConflictChunk::Clean(lines) => Some(lines.clone()),
+            ConflictChunk::Conflict {
+                base,
+                ours,
+                theirs,
+                resolution,
+            } => match resolution.as_ref()? {
+                Resolution::Ours => Some(ours.clone()),
+                Resolution::Theirs => Some(theirs.clone()),
+                Resolution::Both => Some(ours.iter().chain(theirs).cloned().collect()),
+                Resolution::Base => Some(base.clone()),
+                Resolution::Custom(lines) => Some(lines.clone()),
+            },
+        }
+    }
+}
+
+/// One conflicted path, split into clean and conflicting chunks.
+#[derive(Clone, Debug)]
+pub struct ConflictFile {
+    pub path: String,
+    pub chunks: Vec<ConflictChunk>,
+    /// One side deleted the file; an empty result removes it.
+    pub deleted_on_one_side: bool,
+}
+
+impl ConflictFile {
+    /// Indices into `chunks` of every conflicting chunk.
+    pub fn conflict_indices(&self) -> Vec<usize> {
+        self.chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| matches!(chunk, ConflictChunk::Conflict { .. }))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    pub fn resolve(&mut self, chunk_index: usize, choice: Resolution) {
+        if let Some(ConflictChunk::Conflict { resolution, .. }) = self.chunks.get_mut(chunk_index) {
+            *resolution = Some(choice);
+        }
+    }
+
+    pub fn unresolve(&mut self, chunk_index: usize) {
+        if let Some(ConflictChunk::Conflict { resolution, .. }) = self.chunks.get_mut(chunk_index) {
+            *resolution = None;
+        }
+    }
+
+    pub fn unresolved_count(&self) -> usize {
+        self.chunks
+            .iter()
+            .filter(|chunk| chunk.result_lines().is_none())
+            .count()
+    }
+
+    /// The merged file, or `None` while some conflict is undecided.
+    pub fn result_text(&self) -> Option<String> {
+        let mut text = String::new();
+        for chunk in &self.chunks {
+            text.extend(chunk.result_lines()?);
+        }
+        Some(text)
+    }
+}
+
+/// Load the base, ours and theirs stages of `path` from the index and merge
+/// them line by line.
+pub fn load_conflict(repo: &Repository, path: &str) -> Result<ConflictFile, git2::Error> {
+    let index = repo.index()?;
+    let conflict = index
+        .conflicts()?
+        .filter_map(Result::ok)
+        .find(|conflict| {
+            [&conflict.our, &conflict.their, &conflict.ancestor]
+                .into_iter()
+                .flatten()
+                .any(|entry| entry.path == path.as_bytes())
+        })
+        .ok_or_else(|| git2::Error::from_str(&format!("{} is not conflicted", path)))?;
+
+    for entry in [&conflict.our, &conflict.their, &conflict.ancestor]
+        .into_iter()
+        .flatten()
+    {
+        if repo.find_blob(entry.id)?.is_binary() {
+            return Err(git2::Error::from_str(&format!(
+                "{} is a binary file, resolve it outside gpig",
+                path
+            )));
+        }
+    }
+
+    let template = conflict
+        .our
+        .as_ref()
+        .or(conflict.their.as_ref())
+        .or(conflict.ancestor.as_ref())
+        .ok_or_else(|| git2::Error::from_str(&format!("{} has no conflict stages", path)))?;
+    // a missing stage merges as an empty file
+    let empty = repo.blob(b"")?;
+    let stage = |entry: &Option<IndexEntry>| {
+        let source = entry.as_ref().unwrap_or(template);
+        IndexEntry {
+            ctime: source.ctime,
+            mtime: source.mtime,
+            dev: source.dev,
+            ino: source.ino,
+            mode: source.mode,
+            uid: source.uid,
+            gid: source.gid,
+            file_size: source.file_size,
+            id: entry.as_ref().map_or(empty, |entry| entry.id),
+            flags: source.flags,
+            flags_extended: source.flags_extended,
+            path: source.path.clone(),
+        }
+    };
+
+    let mut options = MergeFileOptions::new();
+    options
+        .style_diff3(true)
+        .ancestor_label(BASE_LABEL)
+        .our_label(OURS_LABEL)
+        .their_label(THEIRS_LABEL);
+    let merged = repo.merge_file_from_index(
+        &stage(&conflict.ancestor),
+        &stage(&conflict.our),
+        &stage(&conflict.their),
+        Some(&mut options),
+    )?;
+
+    // the resolution is written back as text, so a lossy decode would corrupt the file
+    let text = std::str::from_utf8(merged.content()).map_err(|_| {
+        git2::Error::from_str(&format!(
+            "{} is not UTF-8 text, resolve it outside gpig",
+            path
+        ))
+    })?;
+
+    Ok(ConflictFile {
+        path: path.to_string(),
+        chunks: parse_merge_output(text),
+        deleted_on_one_side: conflict.ancestor.is_some()
+            && (conflict.our.is_none() || conflict.their.is_none()),
+    })
+}
+
+/// Split diff3-style merge output, as written by `load_conflict`, into chunks.
+/// Only the markers carrying our labels count, and `=======` only after the base
+/// section, so marker-like lines in the file itself stay text. A conflict left
+/// open at the end of the text is kept as clean lines.
+pub fn parse_merge_output(text: &str) -> Vec<ConflictChunk> {
+    enum Section {
+        Clean,
+        Ours,
+        Base,
+        Theirs,
+    }
+
+    let open = format!("<<<<<<< {}", OURS_LABEL);
+    let base_marker = format!("||||||| {}", BASE_LABEL);
+    let close = format!(">>>>>>> {}", THEIRS_LABEL);
+
+    let mut chunks = Vec::new();
+    let mut clean = Vec::new();
+    let (mut ours, mut base, mut theirs) = (Vec::new(), Vec::new(), Vec::new());
+    // every line of the open conflict, markers included
+    let mut pending = Vec::new();
+    let mut section = Section::Clean;
+
+    for line in text.split_inclusive('\n') {
+        let marker = line.trim_end_matches(['\r', '\n']);
+        if !matches!(section, Section::Clean) {
+            pending.push(line.to_string());
+        }
+        match section {
+            Section::Clean if marker == open => {
+                pending.push(line.to_string());
+                section = Section::Ours;
+            }
+            Section::Ours if marker == base_marker => section = Section::Base,
+            Section::Base if marker == "=======" => section = Section::Theirs,
+            Section::Theirs if marker == close => {
+                if !clean.is_empty() {
+                    chunks.push(ConflictChunk::Clean(std::mem::take(&mut clean)));
+                }
+                chunks.push(ConflictChunk::Conflict {
+                    base: std::mem::take(&mut base),
+                    ours: std::mem::take(&mut ours),
+                    theirs: std::mem::take(&mut theirs),
+                    resolution: None,
+                });
+                pending.clear();
+                section = Section::Clean;
+            }
+            Section::Clean => clean.push(line.to_string()),
+            Section::Ours => ours.push(line.to_string()),
+            Section::Base => base.push(line.to_string()),
+            Section::Theirs => theirs.push(line.to_string()),
+        }
+    }
+    clean.append(&mut pending);
+    if !clean.is_empty() {
+        chunks.push(ConflictChunk::Clean(clean));
+    }
+
+    chunks
+}
+
+/// Write the resolved file to the working tree and stage it, which clears
+/// its conflict entries.
+pub fn mark_resolved(repo: &Repository, file: &ConflictFile) -> Result<(), git2::Error> {
+    let text = file.result_text().ok_or_else(|| {
+        git2::Error::from_str(&format!(
+            "{} still has {} unresolved conflict(s)",
+            file.path,
+            file.unresolved_count()
+        ))
+    })?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("cannot resolve conflicts in a bare repository"))?;
+    let full_path = workdir.join(&file.path);
+
+    let mut index = repo.index()?;
+    if text.is_empty() && file.deleted_on_one_side {
+        let _ = std::fs::remove_file(&full_path);
+        index.remove_path(Path::new(&file.path))?;
+    } else {
+        std::fs::write(&full_path, text).map_err(|e| git2::Error::from_str(&e.to_string()))?;
+        index.add_path(Path::new(&file.path))?;
+    }
+    index.write()
+}
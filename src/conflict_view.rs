@@ -0,0 +1,657 @@
+use gpui::prelude::*;
+use gpui::{
+    AnyElement, Context, Entity, EventEmitter, IntoElement, MouseButton, Render, SharedString,
+    Window, div, px,
+};
+
+use crate::conflict::{ConflictChunk, ConflictFile, Resolution};
+use crate::modal::{BUTTON_DANGER, BUTTON_NEUTRAL, BUTTON_PRIMARY, modal_button};
+use crate::operations::{ConflictState, OperationKind};
+use crate::text_input::TextInput;
+
+const PATH_LIST_WIDTH: f32 = 240.0;
+const BASE_BG: u32 = 0x2A2A2A;
+const OURS_BG: u32 = 0x1E3A28;
+const THEIRS_BG: u32 = 0x1E2A3A;
+
+#[derive(Clone)]
+pub enum ConflictViewEvent {
+    Continue,
+    Skip,
+    Abort,
+    OpenPath(String),
+    MarkResolved(ConflictFile),
+}
+
+/// A conflict chunk whose result is being typed in, one input per line.
+struct ChunkEdit {
+    chunk_index: usize,
+    lines: Vec<Entity<TextInput>>,
+}
+
+/// Shown in the content pane while an operation is stopped on conflicts.
+pub struct ConflictView {
+    state: ConflictState,
+    error: Option<String>,
+    file: Option<ConflictFile>,
+    edit: Option<ChunkEdit>,
+}
+
+impl EventEmitter<ConflictViewEvent> for ConflictView {}
+
+impl ConflictView {
+    pub fn new(state: ConflictState) -> Self {
+        Self {
+            state,
+            error: None,
+            file: None,
+            edit: None,
+        }
+    }
+
+    pub fn set_state(&mut self, state: ConflictState, cx: &mut Context<Self>) {
+        // drop the open file once it is no longer conflicted
+        if let Some(file) = &self.file
+            && !state.paths.contains(&file.path)
+        {
+            self.file = None;
+            self.edit = None;
+        }
+        self.state = state;
+        self.error = None;
+        cx.notify();
+    }
+
+    pub fn kind(&self) -> OperationKind {
+        self.state.kind
+    }
+
+    pub fn set_error(&mut self, error: impl Into<String>, cx: &mut Context<Self>) {
+        self.error = Some(error.into());
+        cx.notify();
+    }
+
+    pub fn set_file(&mut self, file: ConflictFile, cx: &mut Context<Self>) {
+        self.file = Some(file);
+        self.edit = None;
+        self.error = None;
+        cx.notify();
+    }
+
+    fn resolve(&mut self, chunk_index: usize, resolution: Resolution, cx: &mut Context<Self>) {
+        if let Some(file) = &mut self.file {
+            file.resolve(chunk_index, resolution);
+        }
+        self.edit = None;
+        cx.notify();
+    }
+
+    fn unresolve(&mut self, chunk_index: usize, cx: &mut Context<Self>) {
+        if let Some(file) = &mut self.file {
+            file.unresolve(chunk_index);
+        }
+        cx.notify();
+    }
+
+    fn start_edit(&mut self, chunk_index: usize, cx: &mut Context<Self>) {
+        let Some(chunk) = self
+            .file
+            .as_ref()
+            .and_then(|file| file.chunks.get(chunk_index))
+        else {
+            return;
+        };
+        // start from the current result, or from ours when undecided
+        let lines = match (chunk.result_lines(), chunk) {
+            (Some(lines), _) => lines,
+            (None, ConflictChunk::Conflict { ours, .. }) => ours.clone(),
+            (None, ConflictChunk::Clean(lines)) => lines.clone(),
+        };
+        let lines = lines
+            .iter()
+            .map(|line| {
+                let line = line.trim_end_matches(['\r', '\n']).to_string();
+                cx.new(|cx| {
+                    let mut input = TextInput::new("", cx);
+                    input.set_text(line, cx);
+                    input
+                })
+            })
+            .collect();
+        self.edit = Some(ChunkEdit { chunk_index, lines });
+        cx.notify();
+    }
+
+    fn add_edit_line(&mut self, cx: &mut Context<Self>) {
+        let input = cx.new(|cx| TextInput::new("", cx));
+        if let Some(edit) = &mut self.edit {
+            edit.lines.push(input);
+        }
+        cx.notify();
+    }
+
+    fn remove_edit_line(&mut self, line_index: usize, cx: &mut Context<Self>) {
+        if let Some(edit) = &mut self.edit
+            && line_index < edit.lines.len()
+        {
+            edit.lines.remove(line_index);
+        }
+        cx.notify();
+    }
+
+    fn apply_edit(&mut self, cx: &mut Context<Self>) {
+        let Some(edit) = self.edit.take() else {
+            return;
+        };
+        let lines = edit
+            .lines
+            .iter()
+            .map(|input| format!("{}\n", input.read(cx).text()))
+            .collect();
+        self.resolve(edit.chunk_index, Resolution::Custom(lines), cx);
+    }
+
+    fn render_lines(lines: &[String], color: u32) -> AnyElement {
+        div()
+            .flex()
+            .flex_col()
+            .font_family("monospace")
+            .text_size(px(12.0))
+            .text_color(gpui::rgb(color))
+            .children(lines.iter().map(|line| {
+                let line = line.trim_end_matches(['\r', '\n']);
+                // keep empty lines one row tall
+                div().min_h(px(16.0)).child(if line.is_empty() {
+                    " ".to_string()
+                } else {
+                    line.to_string()
+                })
+            }))
+            .into_any()
+    }
+
+    fn render_side(title: &str, lines: &[String], bg: u32) -> AnyElement {
+        div()
+            .flex_1()
+            .min_w(px(0.0))
+            .flex()
+            .flex_col()
+            .overflow_hidden()
+            .bg(gpui::rgb(bg))
+            .px(px(8.0))
+            .py(px(4.0))
+            .child(
+                div()
+                    .text_color(gpui::rgb(0x888888))
+                    .text_size(px(10.0))
+                    .child(title.to_string()),
+            )
+            .child(Self::render_lines(lines, 0xCCCCCC))
+            .into_any()
+    }
+
+    fn chunk_button(
+        &self,
+        id: SharedString,
+        label: &'static str,
+        cx: &mut Context<Self>,
+        on_click: impl Fn(&mut Self, &mut Context<Self>) + 'static,
+    ) -> AnyElement {
+        div()
+            .id(id)
+            .px(px(8.0))
+            .py(px(2.0))
+            .rounded(px(4.0))
+            .bg(gpui::rgb(BUTTON_NEUTRAL))
+            .text_color(gpui::white())
+            .text_size(px(11.0))
+            .cursor_pointer()
+            .hover(|style| style.opacity(0.85))
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _event, _window, cx| on_click(this, cx)),
+            )
+            .child(label)
+            .into_any()
+    }
+
+    fn render_edit(&self, edit: &ChunkEdit, cx: &mut Context<Self>) -> AnyElement {
+        let rows = edit
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(line_index, input)| {
+                div()
+                    .flex()
+                    .flex_row()
+                    .items_center()
+                    .gap_2()
+                    .child(div().flex_1().child(input.clone()))
+                    .child(self.chunk_button(
+                        format!("conflict_edit_remove_{}", line_index).into(),
+                        "✕",
+                        cx,
+                        move |this, cx| this.remove_edit_line(line_index, cx),
+                    ))
+            })
+            .collect::<Vec<_>>();
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .px(px(8.0))
+            .py(px(6.0))
+            .children(rows)
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .gap_2()
+                    .child(self.chunk_button(
+                        "conflict_edit_add".into(),
+                        "Add line",
+                        cx,
+                        |this, cx| this.add_edit_line(cx),
+                    ))
+                    .child(self.chunk_button(
+                        "conflict_edit_cancel".into(),
+                        "Cancel",
+                        cx,
+                        |this, cx| {
+                            this.edit = None;
+                            cx.notify();
+                        },
+                    ))
+                    .child(self.chunk_button(
+                        "conflict_edit_apply".into(),
+                        "Apply",
+                        cx,
+                        |this, cx| this.apply_edit(cx),
+                    )),
+            )
+            .into_any()
+    }
+
+    fn render_chunk(
+        &self,
+        chunk_index: usize,
+        chunk: &ConflictChunk,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let ConflictChunk::Conflict {
+            base,
+            ours,
+            theirs,
+            resolution,
+        } = chunk
+        else {
+            return div()
+                .px(px(8.0))
+                .py(px(4.0))
+                .child(Self::render_lines(
+                    &chunk.result_lines().unwrap_or_default(),
+                    0x888888,
+                ))
+                .into_any();
+        };
+
+        let choices = [
+            ("Use ours", Resolution::Ours),
+            ("Use theirs", Resolution::Theirs),
+            ("Use both", Resolution::Both),
+            ("Use base", Resolution::Base),
+        ];
+        let mut actions = choices
+            .into_iter()
+            .map(|(label, choice)| {
+                self.chunk_button(
+                    format!("conflict_{}_{}", chunk_index, label).into(),
+                    label,
+                    cx,
+                    move |this, cx| this.resolve(chunk_index, choice.clone(), cx),
+                )
+            })
+            .collect::<Vec<_>>();
+        actions.push(self.chunk_button(
+            format!("conflict_{}_edit", chunk_index).into(),
+            "Edit",
+            cx,
+            move |this, cx| this.start_edit(chunk_index, cx),
+        ));
+        if resolution.is_some() {
+            actions.push(self.chunk_button(
+                format!("conflict_{}_undo", chunk_index).into(),
+                "Undo",
+                cx,
+                move |this, cx| this.unresolve(chunk_index, cx),
+            ));
+        }
+
+        let editing = self
+            .edit
+            .as_ref()
+            .filter(|edit| edit.chunk_index == chunk_index);
+        let border = if resolution.is_some() {
+            0x2ECC71
+        } else {
+            0xE74C3C
+        };
+
+        div()
+            .flex()
+            .flex_col()
+            .my(px(4.0))
+            .border_1()
+            .border_color(gpui::rgb(border))
+            .rounded(px(4.0))
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .child(Self::render_side("base", base, BASE_BG))
+                    .child(Self::render_side("ours", ours, OURS_BG))
+                    .child(Self::render_side("theirs", theirs, THEIRS_BG)),
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .gap_2()
+                    .px(px(8.0))
+                    .py(px(4.0))
+                    .bg(gpui::rgb(0x252525))
+                    .children(actions),
+            )
+            .when_some(editing, |this, edit| this.child(self.render_edit(edit, cx)))
+            .when(editing.is_none(), |this| {
+                this.when_some(
+                    chunk.result_lines().filter(|_| resolution.is_some()),
+                    |this, lines| {
+                        this.child(
+                            div()
+                                .px(px(8.0))
+                                .py(px(4.0))
+                                .child(
+                                    div()
+                                        .text_color(gpui::rgb(0x2ECC71))
+                                        .text_size(px(10.0))
+                                        .child("result"),
+                                )
+                                .child(Self::render_lines(&lines, 0xCCCCCC)),
+                        )
+                    },
+                )
+            })
+            .into_any()
+    }
+
+    fn render_file(&self, file: &ConflictFile, cx: &mut Context<Self>) -> AnyElement {
+        let unresolved = file.unresolved_count();
+        let status = if unresolved == 0 {
+            "all conflicts resolved".to_string()
+        } else {
+            format!("{} unresolved", unresolved)
+        };
+        let file_for_event = file.clone();
+
+        div()
+            .flex_1()
+            .flex()
+            .flex_col()
+            .min_w(px(0.0))
+            .child(
+                div()
+                    .w_full()
+                    .flex()
+                    .flex_row()
+                    .items_center()
+                    .justify_between()
+                    .px(px(12.0))
+                    .py(px(8.0))
+                    .border_b_1()
+                    .border_color(gpui::rgb(0x333333))
+                    .bg(gpui::rgb(0x252525))
+                    .child(
+                        div()
+                            .flex()
+                            .flex_row()
+                            .gap_2()
+                            .items_center()
+                            .child(
+                                div()
+                                    .text_color(gpui::white())
+                                    .font_weight(gpui::FontWeight::BOLD)
+                                    .text_size(px(14.0))
+                                    .child(file.path.clone()),
+                            )
+                            .child(
+                                div()
+                                    .text_color(gpui::rgb(0x888888))
+                                    .text_size(px(12.0))
+                                    .child(status),
+                            ),
+                    )
+                    .child(
+                        modal_button(
+                            "conflict_mark_resolved",
+                            "Mark resolved",
+                            if unresolved == 0 {
+                                BUTTON_PRIMARY
+                            } else {
+                                BUTTON_NEUTRAL
+                            },
+                        )
+                        .on_mouse_down(
+                            MouseButton::Left,
+                            cx.listener(move |this, _event, _window, cx| {
+                                if unresolved > 0 {
+                                    this.set_error("Pick a side for every conflict first", cx);
+                                } else {
+                                    cx.emit(ConflictViewEvent::MarkResolved(
+                                        file_for_event.clone(),
+                                    ));
+                                }
+                            }),
+                        ),
+                    ),
+            )
+            .child(
+                div()
+                    .id("conflict-chunks")
+                    .flex_1()
+                    .flex()
+                    .flex_col()
+                    .overflow_y_scroll()
+                    .px(px(12.0))
+                    .py(px(8.0))
+                    .children(
+                        file.chunks
+                            .iter()
+                            .enumerate()
+                            .map(|(index, chunk)| self.render_chunk(index, chunk, cx))
+                            .collect::<Vec<_>>(),
+                    ),
+            )
+            .into_any()
+    }
+
+    fn render_path_list(&self, cx: &mut Context<Self>) -> AnyElement {
+        let selected = self.file.as_ref().map(|file| file.path.clone());
+
+        div()
+            .id("conflicted-files-list")
+            .w(px(PATH_LIST_WIDTH))
+            .h_full()
+            .flex()
+            .flex_col()
+            .overflow_y_scroll()
+            .border_r_1()
+            .border_color(gpui::rgb(0x333333))
+            .children(self.state.paths.iter().map(|path| {
+                let is_selected = selected.as_ref() == Some(path);
+                let open_path = path.clone();
+                div()
+                    .w_full()
+                    .flex()
+                    .flex_row()
+                    .items_center()
+                    .px(px(12.0))
+                    .py(px(8.0))
+                    .border_b_1()
+                    .border_color(gpui::rgb(0x2A2A2A))
+                    .cursor_pointer()
+                    .when(is_selected, |this| this.bg(gpui::rgb(0x2A2A2A)))
+                    .hover(|style| style.bg(gpui::rgb(0x2A2A2A)))
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |_this, _event, _window, cx| {
+                            cx.emit(ConflictViewEvent::OpenPath(open_path.clone()));
+                        }),
+                    )
+                    .child(
+                        div()
+                            .w(px(30.0))
+                            .text_color(gpui::rgb(0xE74C3C))
+                            .font_weight(gpui::FontWeight::BOLD)
+                            .text_size(px(12.0))
+                            .child("U"),
+                    )
+                    .child(
+                        div()
+                            .flex_1()
+                            .text_color(gpui::rgb(0xCCCCCC))
+                            .text_size(px(13.0))
+                            .font_family("monospace")
+                            .child(path.clone()),
+                    )
+            }))
+            .into_any()
+    }
+}
+
+impl Render for ConflictView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let kind = match self.state.progress {
+            Some(progress) => format!("{} ({})", self.state.kind.label(), progress.label()),
+            None => self.state.kind.label().to_string(),
+        };
+        let title = if self.state.paths.is_empty() {
+            format!("{} in progress", kind)
+        } else {
+            format!(
+                "{} stopped with {} conflicted file(s)",
+                kind,
+                self.state.paths.len()
+            )
+        };
+        let is_rebase = self.state.kind == OperationKind::Rebase;
+        let hint = if self.state.paths.is_empty() {
+            "All conflicts are resolved. Continue to create the commit."
+        } else {
+            "Open a file to pick a side per conflict, then mark it resolved. Abort returns to HEAD."
+        };
+
+        let file_pane = match &self.file {
+            Some(file) => self.render_file(file, cx),
+            None => div()
+                .flex_1()
+                .flex()
+                .items_center()
+                .justify_center()
+                .text_color(gpui::rgb(0x888888))
+                .child("Select a conflicted file")
+                .into_any(),
+        };
+
+        div()
+            .size_full()
+            .flex()
+            .flex_col()
+            .bg(gpui::rgb(0x1E1E1E))
+            .child(
+                div()
+                    .w_full()
+                    .flex()
+                    .flex_row()
+                    .items_center()
+                    .justify_between()
+                    .px(px(12.0))
+                    .py(px(8.0))
+                    .border_b_1()
+                    .border_color(gpui::rgb(0x333333))
+                    .bg(gpui::rgb(0x3A2A1A))
+                    .child(
+                        div()
+                            .text_color(gpui::rgb(0xF39C12))
+                            .font_weight(gpui::FontWeight::BOLD)
+                            .text_size(px(14.0))
+                            .child(title),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .flex_row()
+                            .gap_2()
+                            .child(
+                                modal_button("conflict_abort", "Abort", BUTTON_DANGER)
+                                    .on_mouse_down(
+                                        MouseButton::Left,
+                                        cx.listener(|_this, _event, _window, cx| {
+                                            cx.emit(ConflictViewEvent::Abort);
+                                        }),
+                                    ),
+                            )
+                            .when(is_rebase, |this| {
+                                this.child(
+                                    modal_button("conflict_skip", "Skip", BUTTON_NEUTRAL)
+                                        .on_mouse_down(
+                                            MouseButton::Left,
+                                            cx.listener(|_this, _event, _window, cx| {
+                                                cx.emit(ConflictViewEvent::Skip);
+                                            }),
+                                        ),
+                                )
+                            })
+                            .child(
+                                modal_button("conflict_continue", "Continue", BUTTON_PRIMARY)
+                                    .on_mouse_down(
+                                        MouseButton::Left,
+                                        cx.listener(|_this, _event, _window, cx| {
+                                            cx.emit(ConflictViewEvent::Continue);
+                                        }),
+                                    ),
+                            ),
+                    ),
+            )
+            .child(
+                div()
+                    .px(px(12.0))
+                    .py(px(8.0))
+                    .text_color(gpui::rgb(0x888888))
+                    .text_size(px(12.0))
+                    .child(hint),
+            )
+            .when_some(self.error.clone(), |this, error| {
+                this.child(
+                    div()
+                        .px(px(12.0))
+                        .pb(px(8.0))
+                        .text_color(gpui::rgb(0xE74C3C))
+                        .text_size(px(12.0))
+                        .child(error),
+                )
+            })
+            .child(
+                div()
+                    .flex_1()
+                    .flex()
+                    .flex_row()
+                    .min_h(px(0.0))
+                    .border_t_1()
+                    .border_color(gpui::rgb(0x333333))
+                    .child(self.render_path_list(cx))
+                    .child(file_pane),
+            )
+    }
+}
@@ -0,0 +1,472 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use git2::{Config, Cred, CredentialType};
+
+/// How many times the user is asked before the operation gives up.
+const MAX_PROMPTS: usize = 3;
+
+/// What the user is asked for when nothing else worked.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CredentialRequest {
+    UserPass {
+        url: String,
+        username: Option<String>,
+    },
+    Passphrase {
+        url: String,
+        key: PathBuf,
+    },
+}
+
+impl CredentialRequest {
+    pub fn title(&self) -> String {
+        match self {
+            CredentialRequest::UserPass { url, .. } => format!("Sign in to {}", url),
+            CredentialRequest::Passphrase { key, .. } => {
+                format!("Passphrase for {}", key.display())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CredentialAnswer {
+    /// Ignored for passphrases.
+    pub username: String,
+    pub secret: String,
+    /// Keep the answer in the session cache.
+    pub remember: bool,
+}
+
+/// Credentials kept in memory for the running session only.
+#[derive(Clone, Default)]
+pub struct CredentialCache {
+    entries: Arc<Mutex<HashMap<String, (String, String)>>>,
+}
+
+impl CredentialCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn get(&self, key: &str) -> Option<(String, String)> {
+        self.entries.lock().ok()?.get(key).cloned()
+    }
+
+    fn insert(&self, key: &str, username: &str, secret: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(key.to_string(), (username.to_string(), secret.to_string()));
+        }
+    }
+
+    fn remove(&self, key: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.remove(key);
+        }
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries
+            .lock()
+            .map(|entries| entries.len())
+            .unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+type Prompt = Box<dyn FnMut(CredentialRequest) -> Option<CredentialAnswer> + Send>;
+
+/// State for one remote operation's credentials callback. libgit2 calls it again after
+/// every rejected credential, so each source is tried at most once before moving on:
+/// SSH agent, SSH key files, cached or helper-provided passwords, then the prompt.
+pub struct CredentialChain {
+    cache: Option<CredentialCache>,
+    /// Looked up from the ssh configuration for the remote's host when first needed.
+    key_files: Option<Vec<PathBuf>>,
+    prompt: Option<Prompt>,
+    tried_agent: bool,
+    next_key: usize,
+    tried_cached: HashSet<String>,
+    tried_helper: bool,
+    tried_default: bool,
+    prompts: usize,
+    cancelled: bool,
+    /// Cache entry handed out last; dropped if libgit2 asks again.
+    last_cached: Option<String>,
+}
+
+impl CredentialChain {
+    pub fn new() -> Self {
+        Self {
+            cache: None,
+            key_files: None,
+            prompt: None,
+            tried_agent: false,
+            next_key: 0,
+            tried_cached: HashSet::new(),
+            tried_helper: false,
+            tried_default: false,
+            prompts: 0,
+            cancelled: false,
+            last_cached: None,
+        }
+    }
+
+    pub fn with_cache(mut self, cache: CredentialCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn with_key_files(mut self, key_files: Vec<PathBuf>) -> Self {
+        self.key_files = Some(key_files);
+        self
+    }
+
+    pub fn with_prompt(
+        mut self,
+        prompt: impl FnMut(CredentialRequest) -> Option<CredentialAnswer> + Send + 'static,
+    ) -> Self {
+        self.prompt = Some(Box::new(prompt));
+        self
+    }
+
+    /// The body of a `RemoteCallbacks::credentials` callback.
+    pub fn credentials(
+        &mut self,
+        config: &Config,
+        url: &str,
+        username: Option<&str>,
+        allowed: CredentialType,
+    ) -> Result<Cred, git2::Error> {
+        if let (Some(key), Some(cache)) = (self.last_cached.take(), &self.cache) {
+            cache.remove(&key);
+        }
+
+        if allowed.contains(CredentialType::USERNAME) {
+            return Cred::username(username.unwrap_or("git"));
+        }
+
+        if allowed.contains(CredentialType::SSH_KEY) {
+            let user = username.unwrap_or("git");
+            if !self.tried_agent {
+                self.tried_agent = true;
+                if let Ok(cred) = Cred::ssh_key_from_agent(user) {
+                    return Ok(cred);
+                }
+            }
+            let key_files = self
+                .key_files
+                .get_or_insert_with(|| ssh_key_files(config, url));
+            while self.next_key < key_files.len() {
+                let key = key_files[self.next_key].clone();
+                self.next_key += 1;
+                if key.exists() {
+                    return Cred::ssh_key(user, None, &key, None);
+                }
+            }
+            // the keys may be encrypted
+            if let Some(key) = key_files.iter().find(|key| key.exists()).cloned() {
+                let cache_key = key.display().to_string();
+                if let Some((_, passphrase)) = self.cached(&cache_key) {
+                    return Cred::ssh_key(user, None, &key, Some(&passphrase));
+                }
+                let request = CredentialRequest::Passphrase {
+                    url: url.to_string(),
+                    key: key.clone(),
+                };
+                if let Some(answer) = self.ask(request) {
+                    self.remember(&cache_key, &answer);
+                    return Cred::ssh_key(user, None, &key, Some(&answer.secret));
+                }
+            }
+        }
+
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            if let Some((user, password)) = self.cached(url) {
+                return Cred::userpass_plaintext(&user, &password);
+            }
+            if !self.tried_helper {
+                self.tried_helper = true;
+                if let Ok(cred) = Cred::credential_helper(config, url, username) {
+                    return Ok(cred);
+                }
+            }
+            let request = CredentialRequest::UserPass {
+                url: url.to_string(),
+                username: username.map(String::from),
+            };
+            if let Some(answer) = self.ask(request) {
+                self.remember(url, &answer);
+                return Cred::userpass_plaintext(&answer.username, &answer.secret);
+            }
+        }
+
+        if allowed.contains(CredentialType::DEFAULT) && !self.tried_default {
+            self.tried_default = true;
+            return Cred::default();
+        }
+
+        Err(git2::Error::from_str(if self.cancelled {
+            "authentication cancelled"
+        } else {
+            "authentication failed, no more credentials to try"
+        }))
+    }
+
+    /// A cached entry, handed out once per operation.
+    fn cached(&mut self, key: &str) -> Option<(String, String)> {
+        if !self.tried_cached.insert(key.to_string()) {
+            return None;
+        }
+        let entry = self.cache.as_ref()?.get(key)?;
+        self.last_cached = Some(key.to_string());
+        Some(entry)
+    }
+
+    fn ask(&mut self, request: CredentialRequest) -> Option<CredentialAnswer> {
+        if self.cancelled || self.prompts >= MAX_PROMPTS {
+            return None;
+        }
+        let prompt = self.prompt.as_mut()?;
+        self.prompts += 1;
+        let answer = prompt(request);
+        self.cancelled = answer.is_none();
+        answer
+    }
+
+    fn remember(&mut self, key: &str, answer: &CredentialAnswer) {
+        if let (true, Some(cache)) = (answer.remember, &self.cache) {
+            cache.insert(key, &answer.username, &answer.secret);
+            self.last_cached = Some(key.to_string());
+        }
+    }
+}
+
+impl Default for CredentialChain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The key files ssh would offer for `url`, read from `core.sshCommand` (or
+/// `GIT_SSH_COMMAND`) and the user's ssh config.
+fn ssh_key_files(config: &Config, url: &str) -> Vec<PathBuf> {
+    let Some(home) = std::env::var_os("HOME").map(PathBuf::from) else {
+        return Vec::new();
+    };
+    let ssh_command = std::env::var("GIT_SSH_COMMAND")
+        .ok()
+        .or_else(|| config.get_string("core.sshCommand").ok());
+    let config_file = ssh_command
+        .as_deref()
+        .and_then(|command| option_value(&shell_words(command), "-F"))
+        .map(|path| expand_home(&path, &home))
+        .unwrap_or_else(|| home.join(".ssh").join("config"));
+    let ssh_config = std::fs::read_to_string(config_file).unwrap_or_default();
+    configured_key_files(ssh_command.as_deref(), &ssh_config, url, &home)
+}
+
+/// Identity files for `url` in the order ssh tries them: `-i` options of `ssh_command`,
+/// then the `IdentityFile` entries of every matching `Host` block in `ssh_config`. ssh's
+/// own defaults are used only when neither names a key.
+pub fn configured_key_files(
+    ssh_command: Option<&str>,
+    ssh_config: &str,
+    url: &str,
+    home: &Path,
+) -> Vec<PathBuf> {
+    let (user, host) = ssh_host(url);
+    let mut files = Vec::new();
+    if let Some(command) = ssh_command {
+        let words = shell_words(command);
+        let mut words = words.iter();
+        while let Some(word) = words.next() {
+            if word == "-i" {
+                files.extend(words.next().cloned());
+            } else if let Some(path) = word.strip_prefix("-i") {
+                files.push(path.to_string());
+            } else if word == "-o" {
+                match words.next().and_then(|option| ssh_option(option)) {
+                    Some((keyword, path)) if keyword == "identityfile" => {
+                        files.push(path.to_string())
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    // a `Match` block is skipped as a whole, it needs more than the host to evaluate
+    let mut matching = true;
+    for line in ssh_config.lines() {
+        let Some((keyword, value)) = ssh_option(line.trim()) else {
+            continue;
+        };
+        match keyword.as_str() {
+            "host" => matching = host_matches(&shell_words(value), &host),
+            "match" => matching = false,
+            "identityfile" if matching => {
+                files.extend(shell_words(value).into_iter().next());
+            }
+            _ => {}
+        }
+    }
+
+    if files.is_empty() {
+        let ssh = home.join(".ssh");
+        return [
+            "id_rsa",
+            "id_ecdsa",
+            "id_ecdsa_sk",
+            "id_ed25519",
+            "id_ed25519_sk",
+        ]
+        .into_iter()
+        .map(|name| ssh.join(name))
+        .collect();
+    }
+    files
+        .into_iter()
+        .filter(|file| !file.eq_ignore_ascii_case("none"))
+        .map(|file| expand_home(&expand_tokens(&file, home, &user, &host), home))
+        .collect()
+}
+
+/// Expand the `%d`, `%h`, `%r` and `%%` tokens ssh allows in `IdentityFile`.
+fn expand_tokens(path: &str, home: &Path, user: &str, host: &str) -> String {
+    let mut expanded = String::new();
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('d') => expanded.push_str(&home.display().to_string()),
+            Some('h') => expanded.push_str(host),
+            Some('r') => expanded.push_str(user),
+            Some(other) => expanded.push(other),
+            None => expanded.push('%'),
+        }
+    }
+    expanded
+}
+
+/// The user and host of an ssh url, either `ssh://user@host:port/path` or `user@host:path`.
+fn ssh_host(url: &str) -> (String, String) {
+    let rest = url
+        .split_once("://")
+        .map(|(_, rest)| rest.split('/').next().unwrap_or_default())
+        .unwrap_or_else(|| url.split(':').next().unwrap_or_default());
+    let (user, host) = rest.rsplit_once('@').unwrap_or(("git", rest));
+    let host = if url.contains("://") {
+        host.split(':').next().unwrap_or(host)
+    } else {
+        host
+    };
+    (user.to_string(), host.to_string())
+}
+
+/// Whether a `Host` line's patterns accept `host`; a matching `!pattern` rejects it.
+fn host_matches(patterns: &[String], host: &str) -> bool {
+    let mut matched = false;
+    for pattern in patterns {
+        match pattern.strip_prefix('!') {
+            Some(pattern) if glob_matches(pattern, host) => return false,
+            Some(_) => {}
+            None => matched |= glob_matches(pattern, host),
+        }
+    }
+    matched
+}
+
+/// ssh's pattern syntax: `*` matches any run of characters and `?` exactly one.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut star = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Split an ssh config line or `-o` option into its lowercased keyword and the value,
+/// separated by whitespace or `=`.
+fn ssh_option(line: &str) -> Option<(String, &str)> {
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let end = line.find(|c: char| c.is_whitespace() || c == '=')?;
+    let value = line[end..].trim_start();
+    let value = value.strip_prefix('=').unwrap_or(value).trim();
+    Some((line[..end].to_lowercase(), value))
+}
+
+/// The value following `flag` in a split command line, either as `-F path` or `-Fpath`.
+fn option_value(words: &[String], flag: &str) -> Option<String> {
+    let mut words = words.iter();
+    while let Some(word) = words.next() {
+        if word == flag {
+            return words.next().cloned();
+        }
+        if let Some(value) = word.strip_prefix(flag) {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// Split `text` on whitespace, honouring single and double quotes and backslashes.
+fn shell_words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                word.get_or_insert_default();
+            }
+            (Some(q), c) if c == q => quote = None,
+            (Some('\''), c) => word.get_or_insert_default().push(c),
+            (_, '\\') => word.get_or_insert_default().extend(chars.next()),
+            (_, c) => word.get_or_insert_default().push(c),
+        }
+    }
+    words.extend(word);
+    words
+}
+
+fn expand_home(path: &str, home: &Path) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => home.join(rest),
+        None => PathBuf::from(path),
+    }
+}
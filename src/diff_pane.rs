@@ -1,7 +1,6 @@
-use gpui::prelude::FluentBuilder;
 use gpui::{
-    AnyElement, Context, EventEmitter, InteractiveElement, IntoElement, MouseButton, ParentElement,
-    Render, StatefulInteractiveElement, Styled, Window, div, px,
+    Context, EventEmitter, InteractiveElement, IntoElement, MouseButton, ParentElement, Render,
+    StatefulInteractiveElement, Styled, Window, div, px,
 };
 
 pub struct DiffPaneClosed;
@@ -0,0 +1,71 @@
+use std::fmt;
+
+use git2::{ErrorClass, ErrorCode};
+
+/// What went wrong in a `Garph` call, so the UI can react to each kind of failure.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GpigError {
+    NoRepository,
+    /// An error from libgit2, with its class and code kept for matching.
+    Git {
+        class: ErrorClass,
+        code: ErrorCode,
+        message: String,
+    },
+    /// The file is not among the commit's changes, e.g. it was renamed.
+    PathNotInDiff(String),
+    /// Binary or too large to show as text.
+    BinaryFile {
+        path: String,
+        size: usize,
+    },
+    /// A diff callback stopped the walk at one of the size limits.
+    LimitsExceeded,
+}
+
+impl GpigError {
+    /// The libgit2 error code, for git errors.
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            GpigError::Git { code, .. } => Some(*code),
+            _ => None,
+        }
+    }
+
+    /// Convert the error of a diff walk whose callbacks stop at the size limits; libgit2
+    /// reports that stop as code `User` (-7) with the message "no error".
+    pub(crate) fn from_diff(e: git2::Error) -> Self {
+        if e.code() == ErrorCode::User {
+            return GpigError::LimitsExceeded;
+        }
+        GpigError::from(e)
+    }
+}
+
+impl From<git2::Error> for GpigError {
+    fn from(e: git2::Error) -> Self {
+        GpigError::Git {
+            class: e.class(),
+            code: e.code(),
+            message: e.message().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for GpigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GpigError::NoRepository => write!(f, "No repository loaded"),
+            GpigError::Git { message, .. } => write!(f, "{}", message),
+            GpigError::PathNotInDiff(path) => {
+                write!(f, "'{}' is not among the changes of this commit", path)
+            }
+            GpigError::BinaryFile { path, size } => {
+                write!(f, "{} is a binary file ({} bytes)", path, size)
+            }
+            GpigError::LimitsExceeded => write!(f, "stopped at the diff size limits"),
+        }
+    }
+}
+
+impl std::error::Error for GpigError {}
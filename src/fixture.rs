@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use git2::{Oid, Repository, Signature, Time};
+
+/// Builds a real repository for tests, one step at a time:
+///
+/// ```no_run
+/// # use gpig::fixture::Fixture;
+/// # let dir = std::path::Path::new("/tmp/fixture");
+/// let fixture = Fixture::new(dir)
+///     .commit("first", &[("a.txt", "a\n")])
+///     .branch("feature")
+///     .commit("feature work", &[("b.txt", "b\n")])
+///     .checkout("main")
+///     .merge("merge feature", &["feature"]);
+/// let merge = fixture.oid("merge feature");
+/// ```
+///
+/// Commits are one minute apart so time order is commit order, and every commit can be
+/// looked up by its message. Panics on any git failure, it is only meant for tests.
+pub struct Fixture {
+    repo: Repository,
+    oids: HashMap<String, Oid>,
+    time: i64,
+}
+
+impl Fixture {
+    /// A new repository at `path` with HEAD on the unborn `main`.
+    pub fn new(path: &Path) -> Self {
+        Self {
+            repo: Self::init(path),
+            oids: HashMap::new(),
+            time: 1_700_000_000,
+        }
+    }
+
+    /// The repository `new` starts from, for tests that drive it step by step with
+    /// `commit_file` and `checkout_branch` instead of the builder.
+    pub fn init(path: &Path) -> Repository {
+        let mut options = git2::RepositoryInitOptions::new();
+        options.initial_head("main");
+        let repo = Repository::init_opts(path, &options).expect("init fixture repository");
+        let mut config = repo.config().expect("fixture config");
+        config.set_str("user.name", "gpig").expect("set user.name");
+        config
+            .set_str("user.email", "gpig@example.com")
+            .expect("set user.email");
+        repo
+    }
+
+    /// Write `content` to `path` in `repo`, stage it and commit on HEAD.
+    pub fn commit_file(repo: &Repository, path: &str, content: &str, message: &str) -> Oid {
+        Self::commit_files(repo, &[(path, content.as_bytes())], message)
+    }
+
+    /// Like `commit_file`, for several files or binary content.
+    pub fn commit_files(repo: &Repository, files: &[(&str, &[u8])], message: &str) -> Oid {
+        let workdir = repo.workdir().expect("fixture has a working tree");
+        let mut index = repo.index().expect("fixture index");
+        for (path, content) in files {
+            std::fs::write(workdir.join(path), content).expect("write fixture file");
+            index.add_path(Path::new(path)).expect("stage fixture file");
+        }
+        index.write().expect("write fixture index");
+        let signature = Signature::now("gpig", "gpig@example.com").expect("fixture signature");
+        commit_index(repo, message, &signature, &[])
+    }
+
+    /// Point branch `name` at `from`, creating it if needed, and check it out.
+    pub fn checkout_branch(repo: &Repository, name: &str, from: Oid) {
+        // unlike `Repository::branch`, this may also move the branch HEAD is on
+        let refname = format!("refs/heads/{}", name);
+        repo.reference(&refname, from, true, "fixture branch")
+            .expect("create branch");
+        repo.set_head(&refname).expect("switch branch");
+        repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+            .expect("check out branch");
+    }
+
+    /// Commit on the current branch, writing each `(path, content)`.
+    pub fn commit(self, message: &str, files: &[(&str, &str)]) -> Self {
+        let files: Vec<(&str, &[u8])> = files
+            .iter()
+            .map(|(path, content)| (*path, content.as_bytes()))
+            .collect();
+        self.commit_bytes(message, &files)
+    }
+
+    /// Like `commit`, for binary content.
+    pub fn commit_bytes(mut self, message: &str, files: &[(&str, &[u8])]) -> Self {
+        let mut index = self.repo.index().expect("fixture index");
+        for (path, content) in files {
+            let full = self.workdir().join(path);
+            if let Some(parent) = full.parent() {
+                std::fs::create_dir_all(parent).expect("create fixture directory");
+            }
+            std::fs::write(full, content).expect("write fixture file");
+            index.add_path(Path::new(path)).expect("stage fixture file");
+        }
+        index.write().expect("write fixture index");
+        self.commit_index(message, &[]);
+        self
+    }
+
+    /// A commit with the same tree as its parent.
+    pub fn empty_commit(mut self, message: &str) -> Self {
+        self.commit_index(message, &[]);
+        self
+    }
+
+    /// Commit the deletion of `paths`.
+    pub fn remove(mut self, message: &str, paths: &[&str]) -> Self {
+        let mut index = self.repo.index().expect("fixture index");
+        for path in paths {
+            std::fs::remove_file(self.workdir().join(path)).expect("remove fixture file");
+            index
+                .remove_path(Path::new(path))
+                .expect("unstage fixture file");
+        }
+        index.write().expect("write fixture index");
+        self.commit_index(message, &[]);
+        self
+    }
+
+    /// Commit moving `from` to `to` with the content unchanged.
+    pub fn rename(mut self, message: &str, from: &str, to: &str) -> Self {
+        let mut index = self.repo.index().expect("fixture index");
+        std::fs::rename(self.workdir().join(from), self.workdir().join(to))
+            .expect("rename fixture file");
+        index
+            .remove_path(Path::new(from))
+            .expect("unstage fixture file");
+        index.add_path(Path::new(to)).expect("stage fixture file");
+        index.write().expect("write fixture index");
+        self.commit_index(message, &[]);
+        self
+    }
+
+    /// Create `name` at HEAD and switch to it.
+    pub fn branch(self, name: &str) -> Self {
+        {
+            let head = self
+                .repo
+                .head()
+                .and_then(|head| head.peel_to_commit())
+                .expect("branch needs a commit");
+            self.repo.branch(name, &head, false).expect("create branch");
+        }
+        self.checkout(name)
+    }
+
+    pub fn checkout(self, name: &str) -> Self {
+        self.repo
+            .set_head(&format!("refs/heads/{}", name))
+            .expect("switch branch");
+        self.repo
+            .checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+            .expect("check out branch");
+        self
+    }
+
+    /// Merge `branches` into the current branch, an octopus merge when there are several.
+    /// The branches must not conflict.
+    pub fn merge(mut self, message: &str, branches: &[&str]) -> Self {
+        let parents = self.merge_into_index(branches);
+        self.commit_index(message, &parents);
+        self.repo
+            .checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+            .expect("check out merge");
+        self
+    }
+
+    /// Merge the branch trees one after another and stage the result.
+    fn merge_into_index(&self, branches: &[&str]) -> Vec<Oid> {
+        let head = self
+            .repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .expect("merge needs a commit");
+        let mut tree = head.tree().expect("head tree");
+        let mut parents = Vec::new();
+        for branch in branches {
+            let theirs = self
+                .repo
+                .revparse_single(branch)
+                .and_then(|object| object.peel_to_commit())
+                .expect("merge branch");
+            let base = self
+                .repo
+                .merge_base(head.id(), theirs.id())
+                .and_then(|base| self.repo.find_commit(base))
+                .and_then(|base| base.tree())
+                .expect("merge base");
+            let mut index = self
+                .repo
+                .merge_trees(&base, &tree, &theirs.tree().expect("branch tree"), None)
+                .expect("merge trees");
+            assert!(
+                !index.has_conflicts(),
+                "fixture merge of {} conflicts",
+                branch
+            );
+            let oid = index.write_tree_to(&self.repo).expect("write merge tree");
+            tree = self.repo.find_tree(oid).expect("merge tree");
+            parents.push(theirs.id());
+        }
+
+        let mut index = self.repo.index().expect("fixture index");
+        index.read_tree(&tree).expect("read merge tree");
+        index.write().expect("write fixture index");
+        parents
+    }
+
+    /// A lightweight tag at HEAD.
+    pub fn tag(self, name: &str) -> Self {
+        {
+            let head = self
+                .repo
+                .head()
+                .and_then(|head| head.peel(git2::ObjectType::Commit))
+                .expect("tag needs a commit");
+            self.repo
+                .tag_lightweight(name, &head, false)
+                .expect("create tag");
+        }
+        self
+    }
+
+    /// The commit made with `message`.
+    pub fn oid(&self, message: &str) -> Oid {
+        *self
+            .oids
+            .get(message)
+            .unwrap_or_else(|| panic!("no fixture commit '{}'", message))
+    }
+
+    pub fn repo(&self) -> &Repository {
+        &self.repo
+    }
+
+    /// A second handle to the repository, e.g. for `Garph::new`.
+    pub fn open(&self) -> Repository {
+        Repository::open(self.repo.path()).expect("reopen fixture repository")
+    }
+
+    fn workdir(&self) -> &Path {
+        self.repo.workdir().expect("fixture has a working tree")
+    }
+
+    /// Commit the index on HEAD, with `merged` as extra parents.
+    fn commit_index(&mut self, message: &str, merged: &[Oid]) {
+        self.time += 60;
+        let signature = Signature::new("gpig", "gpig@example.com", &Time::new(self.time, 0))
+            .expect("fixture signature");
+        let oid = commit_index(&self.repo, message, &signature, merged);
+        self.oids.insert(message.to_string(), oid);
+    }
+}
+
+/// Commit the index of `repo` on HEAD, with `merged` as extra parents.
+fn commit_index(repo: &Repository, message: &str, signature: &Signature, merged: &[Oid]) -> Oid {
+    let mut index = repo.index().expect("fixture index");
+    let tree = repo
+        .find_tree(index.write_tree().expect("write fixture tree"))
+        .expect("fixture tree");
+    let mut parents: Vec<git2::Commit> = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .into_iter()
+        .collect();
+    for oid in merged {
+        parents.push(repo.find_commit(*oid).expect("merged commit"));
+    }
+    let parents: Vec<&git2::Commit> = parents.iter().collect();
+    repo.commit(Some("HEAD"), signature, signature, message, &tree, &parents)
+        .expect("fixture commit")
+}
@@ -1,39 +1,79 @@
 use git2::{Oid, Repository};
 use gpui::prelude::FluentBuilder;
 use gpui::{
-    Context, EventEmitter, InteractiveElement, IntoElement, MouseButton, ParentElement,
-    PathBuilder, Pixels, Point, Render, StatefulInteractiveElement, Styled, Window, canvas, div,
-    px,
+    Context, EventEmitter, InteractiveElement, IntoElement, MouseButton, MouseDownEvent,
+    ParentElement, PathBuilder, Pixels, Point, Render, ScrollHandle, StatefulInteractiveElement,
+    Styled, Task, Window, canvas, div, point, px,
 };
 
+use crate::backend::{Backend, Head, RepoHandle, StashEntry};
 use crate::color::ColorManager;
 use crate::commit::CommitNode;
+use crate::conflict::{self, ConflictFile};
 use crate::edge::{Edge, EdgeManager};
+use crate::error::GpigError;
 use crate::history_oid::{HistoryOid, HistoryOidManager};
 use crate::lane::LaneManager;
+use crate::operations::{
+    self, ConflictState, MergeMode, MergePreview, OperationOutcome, ResetMode,
+};
+use crate::rebase::{self, RebaseStep};
+use crate::recent::RecentRepo;
+use crate::refs::RepoRefs;
+use crate::remote;
+use crate::restore::{self, WorkdirHunk};
+use crate::settings::{DiffLimits, Settings};
+use crate::stash::{self, StashOutcome};
 use std::cell::RefCell;
-use std::rc::Rc;
+use std::collections::HashMap;
+use std::path::PathBuf;
 
 const START_X: f32 = 30.0;
-const LANE_WIDTH: f32 = 15.0;
 const TEXT_GAP: f32 = 20.0;
-const COMMIT_HEIGHT: f32 = 20.0;
 const SIZE: Pixels = px(10.0);
 const GAP_ROW: f32 = 40.0;
-const LIMIT_ROW: usize = 100;
-const MAX_FILE_SIZE_BYTES: usize = 10 * 1024 * 1024; // 10 MB
-
-// Diff limits to prevent memory exhaustion
-const MAX_FILES_TO_SHOW: usize = 10;
-const MAX_LINES_PER_FILE: usize = 25;
-const MAX_TOTAL_LINES: usize = 100;
 
 pub const GIT_RED: u32 = 0xE64D3F;
 pub const GIT_YELLOW: u32 = 0xF1C40F;
 pub const GIT_GREEN: u32 = 0x2ECC71;
 pub const GIT_BLUE: u32 = 0x3498DB;
 pub const GIT_PURPLE: u32 = 0x9B59B6;
-pub const VEC_COLORS: &[u32] = &[GIT_PURPLE, GIT_BLUE, GIT_RED, GIT_YELLOW, GIT_GREEN];
+pub const GIT_GRAY: u32 = 0x95A5A6;
+pub const VEC_COLORS: &[u32] = &[
+    GIT_PURPLE, GIT_BLUE, GIT_RED, GIT_YELLOW, GIT_GREEN, GIT_GRAY,
+];
+/// Index into `VEC_COLORS` for stash nodes; lanes are colored from the entries before it.
+const STASH_COLOR: usize = 5;
+
+/// Why the graph could not be laid out, shown in place of (or above) the graph.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LayoutError {
+    /// HEAD does not lead to a readable commit.
+    BrokenHead(String),
+    /// Walking the history failed part way; the rows before it are still shown.
+    History(String),
+}
+
+impl LayoutError {
+    pub fn title(&self) -> &'static str {
+        match self {
+            LayoutError::BrokenHead(_) => "HEAD cannot be read",
+            LayoutError::History(_) => "The history could not be read completely",
+        }
+    }
+}
+
+impl std::fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LayoutError::BrokenHead(message) | LayoutError::History(message) => {
+                write!(f, "{}: {}", self.title(), message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LayoutError {}
 
 #[derive(Clone)]
 pub struct CommitSelected {
@@ -52,43 +92,438 @@ pub struct ChangedFile {
     pub new_oid: Option<git2::Oid>,
 }
 
+/// A selected commit has no row because it is older than the commits walked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CommitOutsideGraph {
+    pub oid: Oid,
+    pub rows: usize,
+}
+
 pub struct RepoPathChanged {
     pub path: String,
 }
 
+/// Clicks in the recent repositories list of the empty screen.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RecentRepoAction {
+    Open(PathBuf),
+    Pin(PathBuf, bool),
+    Remove(PathBuf),
+}
+
+/// Right click on a graph row, `position` is in window coordinates.
 #[derive(Clone)]
+pub struct CommitContextMenu {
+    pub oid: Oid,
+    pub position: Point<Pixels>,
+    /// Set when the row is a stash.
+    pub stash: Option<usize>,
+}
+
 pub struct Garph {
-    repo: Rc<RefCell<Option<Repository>>>,
+    /// For write operations on the UI thread; reads go through `handle`.
+    repo: RefCell<Option<Repository>>,
+    handle: Option<RepoHandle>,
     nodes: Vec<CommitNode>,
     edges: Vec<Edge>,
     content_height: Pixels,
     max_lane: usize,
+    selected_oid: Option<Oid>,
+    scroll_handle: ScrollHandle,
+    tag_labels: HashMap<Oid, Vec<String>>,
+    /// Branch HEAD points at when it has no commits yet.
+    unborn_branch: Option<String>,
+    layout_error: Option<LayoutError>,
+    /// Set when the repository changed and the next render should lay the graph out again.
+    layout_stale: bool,
+    layout_task: Option<Task<()>>,
+    /// Commit to scroll to once the pending layout arrives.
+    pending_scroll: Option<Oid>,
+    /// Shown while no repository is loaded.
+    recent: Vec<RecentRepo>,
+    settings: Settings,
 }
 
 impl Garph {
     pub fn new(repo: Option<Repository>) -> Self {
         Self {
-            repo: Rc::new(RefCell::new(repo)),
+            handle: repo
+                .as_ref()
+                .map(|repo| RepoHandle::Git(repo.path().to_path_buf())),
+            repo: RefCell::new(repo),
             nodes: Vec::new(),
             edges: Vec::new(),
             content_height: px(0.0),
             max_lane: 0,
+            selected_oid: None,
+            scroll_handle: ScrollHandle::new(),
+            tag_labels: HashMap::new(),
+            unborn_branch: None,
+            layout_error: None,
+            layout_stale: true,
+            layout_task: None,
+            pending_scroll: None,
+            recent: Vec::new(),
+            settings: Settings::default(),
         }
     }
 
-    pub fn update_repo(&mut self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    /// A graph over any backend. Only a git repository supports the write operations.
+    pub fn from_handle(handle: RepoHandle) -> Self {
+        let repo = match &handle {
+            RepoHandle::Git(git_dir) => Repository::open(git_dir).ok(),
+            #[cfg(any(test, feature = "test-support"))]
+            RepoHandle::Memory(_) => None,
+        };
+        Self {
+            handle: Some(handle),
+            ..Self::new(repo)
+        }
+    }
+
+    pub fn update_repo(&mut self, path: &str) -> Result<(), GpigError> {
         let repo = git2::Repository::open(path)?;
+        self.handle = Some(RepoHandle::Git(repo.path().to_path_buf()));
         *self.repo.borrow_mut() = Some(repo);
+        self.selected_oid = None;
+        self.layout_stale = true;
         Ok(())
     }
 
-    pub fn compute_commit_diff(
+    pub fn list_refs(&self) -> Result<RepoRefs, GpigError> {
+        self.read_backend(RepoRefs::from_backend)
+    }
+
+    /// Select a commit, scroll the graph to its row and notify subscribers.
+    pub fn select_commit(&mut self, oid: Oid, cx: &mut Context<Self>) {
+        self.selected_oid = Some(oid);
+        if self.layout_stale || self.layout_task.is_some() {
+            // the row may not exist until the pending layout arrives
+            self.pending_scroll = Some(oid);
+        } else if !self.scroll_to(oid) && self.handle.is_some() {
+            cx.emit(CommitOutsideGraph {
+                oid,
+                rows: self.nodes.len(),
+            });
+        }
+
+        if let Some(selected) = self.commit_selected_for(oid) {
+            cx.emit(selected);
+        }
+        cx.notify();
+    }
+
+    /// Scroll the commit's row into view; false when it has no row.
+    fn scroll_to(&mut self, oid: Oid) -> bool {
+        let Some(index) = self.nodes.iter().position(|n| n.oid == oid) else {
+            return false;
+        };
+        // keep a few rows of context above the selected commit
+        let top = (index as f32 - 5.0).max(0.0) * self.settings.layout.row_height;
+        let offset = self.scroll_handle.offset();
+        self.scroll_handle.set_offset(point(offset.x, px(-top)));
+        true
+    }
+
+    /// Create a tag on `oid`, annotated when a message is given.
+    pub fn create_tag(
         &self,
-        oid: &git2::Oid,
-    ) -> Result<String, Box<dyn std::error::Error>> {
+        oid: &Oid,
+        name: &str,
+        message: Option<&str>,
+    ) -> Result<(), GpigError> {
+        let repo = self.repo.borrow();
+        let repo = repo.as_ref().ok_or(GpigError::NoRepository)?;
+
+        if !git2::Reference::is_valid_name(&format!("refs/tags/{}", name)) {
+            return Err(
+                git2::Error::from_str(&format!("'{}' is not a valid tag name", name)).into(),
+            );
+        }
+
+        let target = repo.find_object(*oid, None)?;
+        match message {
+            Some(message) => {
+                let tagger = repo.signature()?;
+                repo.tag(name, &target, &tagger, message, false)?;
+            }
+            None => {
+                repo.tag_lightweight(name, &target, false)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn delete_tag(&self, name: &str) -> Result<(), GpigError> {
+        let repo = self.repo.borrow();
+        let repo = repo.as_ref().ok_or(GpigError::NoRepository)?;
+        repo.tag_delete(name)?;
+        Ok(())
+    }
+
+    pub fn tags_for(&self, oid: &Oid) -> Vec<String> {
+        self.tag_labels.get(oid).cloned().unwrap_or_default()
+    }
+
+    pub fn parents_of(&self, oid: &Oid) -> Vec<Oid> {
+        match self.nodes.iter().find(|n| n.oid == *oid) {
+            Some(node) => node.parents.clone(),
+            None => self
+                .commit_selected_for(*oid)
+                .map(|commit| commit.parents)
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn cherry_pick(
+        &self,
+        oid: &Oid,
+        mainline: Option<u32>,
+    ) -> Result<OperationOutcome, GpigError> {
+        self.with_repo(|repo| operations::cherry_pick(repo, *oid, mainline))
+    }
+
+    pub fn revert(&self, oid: &Oid, mainline: Option<u32>) -> Result<OperationOutcome, GpigError> {
+        self.with_repo(|repo| operations::revert(repo, *oid, mainline))
+    }
+
+    pub fn preview_merge(&self, oid: &Oid) -> Result<MergePreview, GpigError> {
+        self.with_repo(|repo| operations::preview_merge(repo, *oid))
+    }
+
+    pub fn merge(&self, oid: &Oid, mode: MergeMode) -> Result<OperationOutcome, GpigError> {
+        self.with_repo(|repo| operations::merge(repo, *oid, mode))
+    }
+
+    pub fn plan_rebase(&self, base: &Oid) -> Result<Vec<RebaseStep>, GpigError> {
+        self.with_repo(|repo| rebase::plan_rebase(repo, *base))
+    }
+
+    pub fn head_oid(&self) -> Option<Oid> {
+        self.with_repo(|repo| Ok(repo.head()?.peel_to_commit()?.id()))
+            .ok()
+    }
+
+    pub fn reset(&self, oid: &Oid, mode: ResetMode) -> Result<(), GpigError> {
+        self.with_repo(|repo| operations::reset(repo, *oid, mode))
+    }
+
+    pub fn uncommitted_changes(&self) -> Result<Vec<String>, GpigError> {
+        self.with_repo(operations::uncommitted_changes)
+    }
+
+    pub fn save_stash(
+        &self,
+        message: Option<&str>,
+        include_untracked: bool,
+    ) -> Result<Oid, GpigError> {
+        self.with_repo_mut(|repo| stash::save(repo, message, include_untracked))
+    }
+
+    pub fn apply_stash(&self, index: usize) -> Result<StashOutcome, GpigError> {
+        self.with_repo_mut(|repo| stash::apply(repo, index))
+    }
+
+    pub fn pop_stash(&self, index: usize) -> Result<StashOutcome, GpigError> {
+        self.with_repo_mut(|repo| stash::pop(repo, index))
+    }
+
+    pub fn drop_stash(&self, index: usize) -> Result<(), GpigError> {
+        self.with_repo_mut(|repo| stash::drop(repo, index))
+    }
+
+    pub fn workdir_hunks(&self, path: &str) -> Result<Vec<WorkdirHunk>, GpigError> {
+        self.with_repo(|repo| restore::workdir_hunks(repo, path))
+    }
+
+    pub fn discard_file(&self, path: &str) -> Result<(), GpigError> {
+        self.with_repo(|repo| restore::discard_file(repo, path))
+    }
+
+    pub fn discard_hunk(&self, path: &str, hunk_index: usize) -> Result<(), GpigError> {
+        self.with_repo(|repo| restore::discard_hunk(repo, path, hunk_index))
+    }
+
+    pub fn restore_file(&self, commit: &Oid, before: bool, path: &str) -> Result<(), GpigError> {
+        self.with_repo(|repo| restore::restore_file(repo, *commit, before, path))
+    }
+
+    /// Path of the `.git` directory, for opening the repository on another thread.
+    pub fn git_dir(&self) -> Option<PathBuf> {
+        self.repo
+            .borrow()
+            .as_ref()
+            .map(|repo| repo.path().to_path_buf())
+    }
+
+    /// Working tree, or the repository itself when bare.
+    pub fn workdir(&self) -> Option<PathBuf> {
+        let repo = self.repo.borrow();
+        let repo = repo.as_ref()?;
+        Some(repo.workdir().unwrap_or(repo.path()).to_path_buf())
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Apply new settings, laying the graph out again when its part changed.
+    pub fn set_settings(&mut self, settings: Settings, cx: &mut Context<Self>) {
+        let relayout =
+            settings.history != self.settings.history || settings.layout != self.settings.layout;
+        self.settings = settings;
+        if relayout {
+            self.refresh(cx);
+        }
+        cx.notify();
+    }
+
+    pub fn set_recent(&mut self, recent: Vec<RecentRepo>, cx: &mut Context<Self>) {
+        self.recent = recent;
+        cx.notify();
+    }
+
+    pub fn has_commit(&self, oid: Oid) -> bool {
+        self.read_backend(|backend| backend.commit_info(oid))
+            .is_ok()
+    }
+
+    pub fn repo_handle(&self) -> Option<RepoHandle> {
+        self.handle.clone()
+    }
+
+    pub fn add_remote(&self, name: &str, url: &str) -> Result<(), GpigError> {
+        self.with_repo(|repo| remote::add_remote(repo, name, url))
+    }
+
+    pub fn rename_remote(&self, name: &str, new_name: &str) -> Result<(), GpigError> {
+        self.with_repo(|repo| remote::rename_remote(repo, name, new_name))
+    }
+
+    pub fn remove_remote(&self, name: &str) -> Result<(), GpigError> {
+        self.with_repo(|repo| remote::remove_remote(repo, name))
+    }
+
+    pub fn set_remote_urls(
+        &self,
+        name: &str,
+        url: &str,
+        push_url: Option<&str>,
+    ) -> Result<(), GpigError> {
+        self.with_repo(|repo| remote::set_remote_urls(repo, name, url, push_url))
+    }
+
+    pub fn conflict_state(&self) -> Result<Option<ConflictState>, GpigError> {
+        self.with_repo(operations::conflict_state)
+    }
+
+    pub fn load_conflict(&self, path: &str) -> Result<ConflictFile, GpigError> {
+        self.with_repo(|repo| conflict::load_conflict(repo, path))
+    }
+
+    pub fn mark_resolved(&self, file: &ConflictFile) -> Result<(), GpigError> {
+        self.with_repo(|repo| conflict::mark_resolved(repo, file))
+    }
+
+    pub fn continue_operation(&self) -> Result<Oid, GpigError> {
+        self.with_repo(operations::continue_operation)
+    }
+
+    pub fn abort_operation(&self) -> Result<(), GpigError> {
+        self.with_repo(operations::abort_operation)
+    }
+
+    fn with_repo_mut<T>(
+        &self,
+        f: impl FnOnce(&mut Repository) -> Result<T, git2::Error>,
+    ) -> Result<T, GpigError> {
+        let mut repo = self.repo.borrow_mut();
+        let repo = repo.as_mut().ok_or(GpigError::NoRepository)?;
+        Ok(f(repo)?)
+    }
+
+    fn with_repo<T>(
+        &self,
+        f: impl FnOnce(&Repository) -> Result<T, git2::Error>,
+    ) -> Result<T, GpigError> {
+        self.read_repo(|repo| Ok(f(repo)?))
+    }
+
+    /// The open repository, or a backend from `handle` when there is none.
+    fn read_backend<T>(
+        &self,
+        f: impl FnOnce(&dyn Backend) -> Result<T, GpigError>,
+    ) -> Result<T, GpigError> {
+        if let Some(repo) = self.repo.borrow().as_ref() {
+            return f(repo);
+        }
+        let handle = self.handle.as_ref().ok_or(GpigError::NoRepository)?;
+        f(handle.open()?.as_ref())
+    }
+
+    fn read_repo<T>(
+        &self,
+        f: impl FnOnce(&Repository) -> Result<T, GpigError>,
+    ) -> Result<T, GpigError> {
         let repo = self.repo.borrow();
-        let repo = repo.as_ref().ok_or("No repository loaded")?;
+        let repo = repo.as_ref().ok_or(GpigError::NoRepository)?;
+        f(repo)
+    }
+
+    fn commit_selected_for(&self, oid: Oid) -> Option<CommitSelected> {
+        if let Some(node) = self.nodes.iter().find(|n| n.oid == oid) {
+            return Some(CommitSelected {
+                oid,
+                message: node.message.clone(),
+                author: node.author.clone(),
+                timestamp: node.timestamp,
+                parents: node.parents.clone(),
+            });
+        }
+
+        // commit is outside the rendered rows, look it up directly
+        let commit = self.read_backend(|backend| backend.commit_info(oid)).ok()?;
+        Some(CommitSelected {
+            oid,
+            message: commit.message,
+            author: commit.author,
+            timestamp: commit.time,
+            parents: commit.parents,
+        })
+    }
+
+    pub fn compute_commit_diff(&self, oid: &git2::Oid) -> Result<String, GpigError> {
+        self.read_repo(|repo| Self::commit_diff(repo, oid, &self.settings.diff))
+    }
 
+    pub fn compute_diff_between_commits(
+        &self,
+        old_oid: &git2::Oid,
+        new_oid: &git2::Oid,
+    ) -> Result<String, GpigError> {
+        self.read_repo(|repo| {
+            Self::diff_between_commits(repo, old_oid, new_oid, &self.settings.diff)
+        })
+    }
+
+    pub fn get_changed_files(&self, oid: &git2::Oid) -> Result<Vec<ChangedFile>, GpigError> {
+        self.read_backend(|backend| backend.changed_files(*oid))
+    }
+
+    pub fn compute_file_diff(
+        &self,
+        commit_oid: &git2::Oid,
+        file_path: &str,
+    ) -> Result<String, GpigError> {
+        self.read_backend(|backend| backend.file_diff(*commit_oid, file_path, &self.settings.diff))
+    }
+
+    /// Diff of a whole commit against its first parent, cut at the diff limits.
+    pub fn commit_diff(
+        repo: &Repository,
+        oid: &git2::Oid,
+        limits: &DiffLimits,
+    ) -> Result<String, GpigError> {
         let commit = repo.find_commit(*oid)?;
         let parents: Vec<git2::Commit> = commit.parents().collect();
 
@@ -100,7 +535,7 @@ impl Garph {
 
             for entry in tree.iter() {
                 let name = entry.name().unwrap_or_default();
-                if Self::is_binary_file(repo, &commit, name) {
+                if Self::is_binary_file(repo, &commit, name, limits.max_file_size_bytes) {
                     diff_lines.push(format!(
                         "+++ a/{} (binary file, size: {})",
                         name,
@@ -132,10 +567,10 @@ impl Garph {
         let diff_lines = RefCell::new(Vec::new());
         let mut diff_stats = Vec::new();
 
-        diff.foreach(
+        let diff_result = diff.foreach(
             &mut |delta, _| {
                 let mut file_count = file_count.borrow_mut();
-                if *file_count >= MAX_FILES_TO_SHOW {
+                if *file_count >= limits.max_files {
                     return false;
                 }
 
@@ -147,7 +582,8 @@ impl Garph {
                     .unwrap_or("unknown");
 
                 // Check if file is binary or too large
-                let is_binary = Self::is_binary_file(repo, &commit, file_path);
+                let is_binary =
+                    Self::is_binary_file(repo, &commit, file_path, limits.max_file_size_bytes);
 
                 match delta.status() {
                     git2::Delta::Added => {
@@ -160,7 +596,12 @@ impl Garph {
                         }
                     }
                     git2::Delta::Deleted => {
-                        let is_binary_old = Self::is_binary_file(repo, parent, file_path);
+                        let is_binary_old = Self::is_binary_file(
+                            repo,
+                            parent,
+                            file_path,
+                            limits.max_file_size_bytes,
+                        );
                         if is_binary_old {
                             let size = Self::get_file_size(repo, parent, file_path).unwrap_or(0);
                             diff_stats
@@ -186,7 +627,12 @@ impl Garph {
                             .path()
                             .and_then(|p| p.to_str())
                             .unwrap_or("unknown");
-                        let is_binary_old = Self::is_binary_file(repo, parent, old_path);
+                        let is_binary_old = Self::is_binary_file(
+                            repo,
+                            parent,
+                            old_path,
+                            limits.max_file_size_bytes,
+                        );
                         if is_binary_old {
                             diff_stats.push(format!("--- a/{} (binary file)", old_path));
                         } else {
@@ -206,7 +652,12 @@ impl Garph {
                             .path()
                             .and_then(|p| p.to_str())
                             .unwrap_or("unknown");
-                        let is_binary_old = Self::is_binary_file(repo, parent, old_path);
+                        let is_binary_old = Self::is_binary_file(
+                            repo,
+                            parent,
+                            old_path,
+                            limits.max_file_size_bytes,
+                        );
                         if is_binary_old {
                             diff_stats.push(format!("--- a/{} (binary file)", old_path));
                         } else {
@@ -229,7 +680,7 @@ impl Garph {
             None,
             Some(&mut |_: git2::DiffDelta<'_>, hunk: git2::DiffHunk<'_>| {
                 let mut line_count = line_count.borrow_mut();
-                if *line_count >= MAX_TOTAL_LINES {
+                if *line_count >= limits.max_total_lines {
                     return false;
                 }
                 *line_count += 1;
@@ -244,7 +695,9 @@ impl Garph {
                 let mut line_count = line_count.borrow_mut();
                 let mut current_file_lines = current_file_lines.borrow_mut();
 
-                if *line_count >= MAX_TOTAL_LINES || *current_file_lines >= MAX_LINES_PER_FILE {
+                if *line_count >= limits.max_total_lines
+                    || *current_file_lines >= limits.max_lines_per_file
+                {
                     return false;
                 }
 
@@ -264,7 +717,13 @@ impl Garph {
                 *current_file_lines += 1;
                 true
             }),
-        )?;
+        );
+        // stopping at a limit is reported as an error; the truncation notes cover it
+        let stopped = match diff_result.map_err(GpigError::from_diff) {
+            Ok(()) => false,
+            Err(GpigError::LimitsExceeded) => true,
+            Err(e) => return Err(e),
+        };
 
         // Combine stats and lines
         let mut result = Vec::new();
@@ -280,33 +739,37 @@ impl Garph {
         let file_count = file_count.into_inner();
         let line_count = line_count.into_inner();
 
-        if file_count >= MAX_FILES_TO_SHOW {
+        if file_count >= limits.max_files {
             result.push("".to_string());
             result.push(format!(
                 "... (showing first {} files, diff truncated)",
-                MAX_FILES_TO_SHOW
+                limits.max_files
             ));
         }
 
-        if line_count >= MAX_TOTAL_LINES {
+        if line_count >= limits.max_total_lines {
             result.push("".to_string());
             result.push(format!(
                 "... (showing first {} lines, diff truncated)",
-                MAX_TOTAL_LINES
+                limits.max_total_lines
+            ));
+        } else if stopped && file_count < limits.max_files {
+            result.push("".to_string());
+            result.push(format!(
+                "... (a file exceeded {} lines, diff truncated)",
+                limits.max_lines_per_file
             ));
         }
 
         Ok(result.join("\n"))
     }
 
-    pub fn compute_diff_between_commits(
-        &self,
+    pub fn diff_between_commits(
+        repo: &Repository,
         old_oid: &git2::Oid,
         new_oid: &git2::Oid,
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        let repo = self.repo.borrow();
-        let repo = repo.as_ref().ok_or("No repository loaded")?;
-
+        limits: &DiffLimits,
+    ) -> Result<String, GpigError> {
         let old_commit = repo.find_commit(*old_oid)?;
         let new_commit = repo.find_commit(*new_oid)?;
 
@@ -322,10 +785,10 @@ impl Garph {
         let diff_lines = RefCell::new(Vec::new());
         let mut diff_stats = Vec::new();
 
-        diff.foreach(
+        let diff_result = diff.foreach(
             &mut |delta, _| {
                 let mut file_count = file_count.borrow_mut();
-                if *file_count >= MAX_FILES_TO_SHOW {
+                if *file_count >= limits.max_files {
                     return false;
                 }
 
@@ -375,7 +838,7 @@ impl Garph {
             None,
             Some(&mut |_: git2::DiffDelta<'_>, hunk: git2::DiffHunk<'_>| {
                 let mut line_count = line_count.borrow_mut();
-                if *line_count >= MAX_TOTAL_LINES {
+                if *line_count >= limits.max_total_lines {
                     return false;
                 }
                 *line_count += 1;
@@ -390,7 +853,9 @@ impl Garph {
                 let mut line_count = line_count.borrow_mut();
                 let mut current_file_lines = current_file_lines.borrow_mut();
 
-                if *line_count >= MAX_TOTAL_LINES || *current_file_lines >= MAX_LINES_PER_FILE {
+                if *line_count >= limits.max_total_lines
+                    || *current_file_lines >= limits.max_lines_per_file
+                {
                     return false;
                 }
 
@@ -410,7 +875,13 @@ impl Garph {
                 *current_file_lines += 1;
                 true
             }),
-        )?;
+        );
+        // stopping at a limit is reported as an error; the truncation notes cover it
+        let stopped = match diff_result.map_err(GpigError::from_diff) {
+            Ok(()) => false,
+            Err(GpigError::LimitsExceeded) => true,
+            Err(e) => return Err(e),
+        };
 
         // Combine stats and lines
         let mut result = Vec::new();
@@ -426,32 +897,35 @@ impl Garph {
         let file_count = file_count.into_inner();
         let line_count = line_count.into_inner();
 
-        if file_count >= MAX_FILES_TO_SHOW {
+        if file_count >= limits.max_files {
             result.push("".to_string());
             result.push(format!(
                 "... (showing first {} files, diff truncated)",
-                MAX_FILES_TO_SHOW
+                limits.max_files
             ));
         }
 
-        if line_count >= MAX_TOTAL_LINES {
+        if line_count >= limits.max_total_lines {
             result.push("".to_string());
             result.push(format!(
                 "... (showing first {} lines, diff truncated)",
-                MAX_TOTAL_LINES
+                limits.max_total_lines
+            ));
+        } else if stopped && file_count < limits.max_files {
+            result.push("".to_string());
+            result.push(format!(
+                "... (a file exceeded {} lines, diff truncated)",
+                limits.max_lines_per_file
             ));
         }
 
         Ok(result.join("\n"))
     }
 
-    pub fn get_changed_files(
-        &self,
+    pub fn changed_files(
+        repo: &Repository,
         oid: &git2::Oid,
-    ) -> Result<Vec<ChangedFile>, Box<dyn std::error::Error>> {
-        let repo = self.repo.borrow();
-        let repo = repo.as_ref().ok_or("No repository loaded")?;
-
+    ) -> Result<Vec<ChangedFile>, GpigError> {
         let commit = repo.find_commit(*oid)?;
         let parents: Vec<git2::Commit> = commit.parents().collect();
 
@@ -460,16 +934,19 @@ impl Garph {
         if parents.is_empty() {
             // Initial commit - get all files as added
             let tree = commit.tree()?;
-            for entry in tree.iter() {
-                if let Some(name) = entry.name() {
+            tree.walk(git2::TreeWalkMode::PreOrder, |root, entry| {
+                if entry.kind() == Some(git2::ObjectType::Blob)
+                    && let Some(name) = entry.name()
+                {
                     changed_files.push(ChangedFile {
-                        path: name.to_string(),
+                        path: format!("{}{}", root, name),
                         status: git2::Delta::Added,
                         old_oid: None,
-                        new_oid: Some(oid.clone()),
+                        new_oid: Some(entry.id()),
                     });
                 }
-            }
+                git2::TreeWalkResult::Ok
+            })?;
         } else {
             // Get diff with first parent
             let parent = &parents[0];
@@ -559,7 +1036,7 @@ impl Garph {
                 }
                 Err(e) => {
                     eprintln!("Error processing diff: {}", e);
-                    return Err(format!("Failed to process diff: {}", e).into());
+                    return Err(e.into());
                 }
             }
         }
@@ -567,26 +1044,22 @@ impl Garph {
         Ok(changed_files)
     }
 
-    pub fn compute_file_diff(
-        &self,
+    pub fn file_diff(
+        repo: &Repository,
         commit_oid: &git2::Oid,
         file_path: &str,
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        const MAX_FILE_DIFF_LINES: usize = 200;
-
-        let repo = self.repo.borrow();
-        let repo = repo.as_ref().ok_or("No repository loaded")?;
-
+        limits: &DiffLimits,
+    ) -> Result<String, GpigError> {
         let commit = repo.find_commit(*commit_oid)?;
         let commit_tree = commit.tree()?;
 
         // Check if file is binary or too large
-        if Self::is_binary_file(repo, &commit, file_path) {
+        if Self::is_binary_file(repo, &commit, file_path, limits.max_file_size_bytes) {
             let size = Self::get_file_size(repo, &commit, file_path).unwrap_or(0);
-            return Ok(format!(
-                "{} is a binary file\nSize: {} bytes",
-                file_path, size
-            ));
+            return Err(GpigError::BinaryFile {
+                path: file_path.to_string(),
+                size,
+            });
         }
 
         let diff = match commit.parent(0) {
@@ -660,7 +1133,7 @@ impl Garph {
             None,
             Some(&mut |_: git2::DiffDelta<'_>, hunk: git2::DiffHunk<'_>| {
                 let line_count = line_count.borrow_mut();
-                if *line_count >= MAX_FILE_DIFF_LINES {
+                if *line_count >= limits.max_file_diff_lines {
                     return false;
                 }
                 diff_lines
@@ -672,7 +1145,7 @@ impl Garph {
                        _: Option<git2::DiffHunk<'_>>,
                        line: git2::DiffLine<'_>| {
                 let mut line_count = line_count.borrow_mut();
-                if *line_count >= MAX_FILE_DIFF_LINES {
+                if *line_count >= limits.max_file_diff_lines {
                     return false;
                 }
 
@@ -693,33 +1166,30 @@ impl Garph {
             }),
         );
 
-        // Handle any errors from diff.foreach()
-        match result {
-            Ok(_) => {}
+        // Handle any errors from diff.foreach(); hitting the line limit stops it with
+        // code User (-7), which the truncation note below covers
+        match result.map_err(GpigError::from_diff) {
+            Ok(()) | Err(GpigError::LimitsExceeded) => {}
             Err(e) => {
                 eprintln!("Error processing diff for file {}: {}", file_path, e);
-                return Err(format!("Failed to compute diff for file {}: {}", file_path, e).into());
+                return Err(e);
             }
         }
 
         let result = diff_lines.into_inner();
         let found = file_found.into_inner();
 
-        // If file was not found in diff, return informative message
         if !found {
-            return Ok(format!(
-                "File '{}' not found in diff\nThis may be because:\n- The file path has changed\n- The file was renamed\n- The file is in a subdirectory",
-                file_path
-            ));
+            return Err(GpigError::PathNotInDiff(file_path.to_string()));
         }
 
         // Add truncation message if limit was hit
-        let final_result = if line_count.into_inner() >= MAX_FILE_DIFF_LINES {
+        let final_result = if line_count.into_inner() >= limits.max_file_diff_lines {
             let mut truncated = result;
             truncated.push("".to_string());
             truncated.push(format!(
                 "... (showing first {} lines, diff truncated)",
-                MAX_FILE_DIFF_LINES
+                limits.max_file_diff_lines
             ));
             truncated
         } else {
@@ -731,86 +1201,239 @@ impl Garph {
 
     /* ---------------- compute graph (loop เดียว) ---------------- */
 
-    fn recompute(&mut self) {
-        self.nodes.clear();
-        self.edges.clear();
-        self.max_lane = 0;
+    /// Lay the graph out again on this thread; a failure is kept in `layout_error`.
+    pub fn recompute(&mut self) {
+        let layout = self
+            .read_backend(|backend| Ok(GraphLayout::build(backend, &self.settings)))
+            .unwrap_or_default();
+        self.apply_layout(layout);
+    }
 
-        let repo = self.repo.borrow();
-        let Some(repo) = repo.as_ref() else {
+    /// Lay the graph out again on the background executor. The current rows stay up until
+    /// it finishes, and a newer refresh drops the pending one.
+    pub fn refresh(&mut self, cx: &mut Context<Self>) {
+        self.layout_stale = false;
+        let Some(handle) = self.repo_handle() else {
+            self.layout_task = None;
+            self.recompute();
+            cx.notify();
             return;
         };
-        let mut revwalk = repo.revwalk().unwrap();
-        revwalk
-            .set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)
-            .unwrap();
-        revwalk.push_head().unwrap();
-
-        let mut lane_manager = LaneManager::new();
-        let mut edge_manager = EdgeManager::new();
-        let mut color_manager = ColorManager::new(VEC_COLORS.to_vec());
-
-        let mut history_oids_manager = HistoryOidManager::new();
-
-        for (index, oid) in revwalk.take(LIMIT_ROW).enumerate() {
-            let oid = oid.unwrap();
-            let commit = repo.find_commit(oid).unwrap();
-            let parents: Vec<Oid> = commit.parents().map(|p| p.id()).collect();
-            let lane = lane_manager.assign_commit(&oid, &parents);
-
-            let color = color_manager.get_color(&lane);
+        let settings = self.settings.clone();
+        let layout = cx.background_executor().spawn(async move {
+            match handle.open() {
+                Ok(backend) => GraphLayout::build(backend.as_ref(), &settings),
+                Err(e) => GraphLayout {
+                    error: Some(LayoutError::History(e.to_string())),
+                    ..GraphLayout::default()
+                },
+            }
+        });
+        self.layout_task = Some(cx.spawn(async move |this, cx| {
+            let layout = layout.await;
+            this.update(cx, |garph, cx| {
+                garph.layout_task = None;
+                if let Some(oid) = garph.apply_layout(layout) {
+                    cx.emit(CommitOutsideGraph {
+                        oid,
+                        rows: garph.nodes.len(),
+                    });
+                }
+                cx.notify();
+            })
+            .ok();
+        }));
+        cx.notify();
+    }
 
-            let pos = Point::new(
-                (START_X + (lane as f32) * LANE_WIDTH).into(),
-                (COMMIT_HEIGHT * index as f32).into(),
-            );
+    /// True while a background layout is running.
+    pub fn is_loading(&self) -> bool {
+        self.layout_task.is_some()
+    }
 
-            // Track maximum lane
-            if lane > self.max_lane {
-                self.max_lane = lane;
-            }
+    /// Returns the commit waiting to be scrolled to when the new rows do not include it.
+    fn apply_layout(&mut self, layout: GraphLayout) -> Option<Oid> {
+        self.nodes = layout.nodes;
+        self.edges = layout.edges;
+        self.content_height = layout.content_height;
+        self.max_lane = layout.max_lane;
+        self.tag_labels = layout.tag_labels;
+        self.unborn_branch = layout.unborn_branch;
+        self.layout_error = layout.error;
+
+        let oid = self.pending_scroll.take()?;
+        (!self.scroll_to(oid)).then_some(oid)
+    }
 
-            let current_edge_point = Point::new(pos.x + SIZE / 2.0, pos.y + SIZE / 2.0);
+    pub fn nodes(&self) -> &[CommitNode] {
+        &self.nodes
+    }
 
-            // connect edges
-            if let Some(history_oids) = history_oids_manager.get(&oid) {
-                for history in history_oids {
-                    if history.edge_point.x > current_edge_point.x {
-                        edge_manager.add(history.edge_point, current_edge_point, history.color);
+    pub fn layout_error(&self) -> Option<&LayoutError> {
+        self.layout_error.as_ref()
+    }
 
-                        if history.lane > 0 {
-                            color_manager.remove_lane_color(&history.lane);
-                        }
-                    } else if history.edge_point.x < current_edge_point.x {
-                        edge_manager.add(current_edge_point, history.edge_point, color);
-                    } else {
-                        edge_manager.add(history.edge_point, current_edge_point, history.color);
-                    }
-                }
-            }
+    /// Branch HEAD points at when the repository has no commits yet.
+    pub fn unborn_branch(&self) -> Option<&str> {
+        self.unborn_branch.as_deref()
+    }
 
-            for parent in &parents {
-                history_oids_manager
-                    .add_history(*parent, HistoryOid::new(current_edge_point, color, lane));
-            }
+    /// Reopen the repository from disk, dropping what the old handle cached, and lay out again.
+    pub fn retry(&mut self) {
+        self.reopen();
+        self.recompute();
+    }
 
-            self.nodes.push(CommitNode::new(
-                oid,
-                commit.message().unwrap_or_default().to_string(),
-                commit.author().email().unwrap_or_default().to_string(),
-                commit.time(),
-                parents,
-                pos,
-                color,
-            ));
+    fn reopen(&mut self) {
+        if let Some(git_dir) = self.git_dir()
+            && let Ok(repo) = Repository::open(&git_dir)
+        {
+            *self.repo.borrow_mut() = Some(repo);
         }
-
-        self.edges = edge_manager.take_edges();
-        self.content_height = px(self.nodes.len() as f32 * COMMIT_HEIGHT + GAP_ROW);
     }
 
     /* ---------------- view helpers ---------------- */
 
+    /// Centered when nothing could be drawn, otherwise a banner above the partial graph.
+    fn render_layout_error(
+        error: LayoutError,
+        centered: bool,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        let detail = match &error {
+            LayoutError::BrokenHead(message) | LayoutError::History(message) => message.clone(),
+        };
+        div()
+            .absolute()
+            .when(centered, |this| this.inset_0().justify_center())
+            .when(!centered, |this| {
+                this.top_0()
+                    .left_0()
+                    .right_0()
+                    .py(px(8.0))
+                    .bg(gpui::rgb(0x3A2020))
+                    .border_b_1()
+                    .border_color(gpui::rgb(0x5A2A2A))
+            })
+            .flex()
+            .flex_col()
+            .items_center()
+            .gap_2()
+            .child(
+                div()
+                    .text_color(gpui::rgb(0xE74C3C))
+                    .text_size(px(14.0))
+                    .child(error.title()),
+            )
+            .child(
+                div()
+                    .text_color(gpui::rgb(0x969696))
+                    .text_size(px(12.0))
+                    .child(detail),
+            )
+            .child(
+                div()
+                    .id("graph_retry")
+                    .px(px(14.0))
+                    .py(px(6.0))
+                    .bg(gpui::rgb(0x4A90D9))
+                    .rounded(px(6.0))
+                    .text_color(gpui::white())
+                    .text_size(px(13.0))
+                    .cursor_pointer()
+                    .hover(|style| style.bg(gpui::rgb(0x357ABD)))
+                    .child("Retry")
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _event, _window, cx| {
+                            this.reopen();
+                            this.refresh(cx);
+                        }),
+                    ),
+            )
+    }
+
+    fn render_recent(recent: &[RecentRepo], cx: &mut Context<Self>) -> impl IntoElement {
+        let action = |id: String, label: &'static str, event: RecentRepoAction| {
+            div()
+                .id(gpui::ElementId::Name(id.into()))
+                .px(px(6.0))
+                .py(px(2.0))
+                .rounded(px(4.0))
+                .text_size(px(11.0))
+                .text_color(gpui::rgb(0x888888))
+                .hover(|style| style.bg(gpui::rgb(0x444444)))
+                .child(label)
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |_this, _event, _window, cx| {
+                        cx.stop_propagation();
+                        cx.emit(event.clone());
+                    }),
+                )
+        };
+        div()
+            .w(px(260.0))
+            .mt(px(8.0))
+            .flex()
+            .flex_col()
+            .gap_1()
+            .child(
+                div()
+                    .text_color(gpui::rgb(0x6A6A6A))
+                    .text_size(px(12.0))
+                    .child("Recent"),
+            )
+            .children(recent.iter().enumerate().map(|(index, entry)| {
+                let path = entry.path.clone();
+                div()
+                    .id(("recent_repo", index))
+                    .flex()
+                    .items_center()
+                    .px(px(8.0))
+                    .py(px(4.0))
+                    .rounded(px(4.0))
+                    .cursor_pointer()
+                    .hover(|style| style.bg(gpui::rgb(0x333333)))
+                    .child(
+                        div()
+                            .flex_1()
+                            .min_w_0()
+                            .flex()
+                            .flex_col()
+                            .child(div().text_color(gpui::white()).text_size(px(13.0)).child(
+                                format!(
+                                    "{}{}",
+                                    if entry.pinned { "📌 " } else { "" },
+                                    entry.name()
+                                ),
+                            ))
+                            .child(
+                                div()
+                                    .text_color(gpui::rgb(0x6A6A6A))
+                                    .text_size(px(11.0))
+                                    .child(entry.path.display().to_string()),
+                            ),
+                    )
+                    .child(action(
+                        format!("recent_pin_{}", index),
+                        if entry.pinned { "Unpin" } else { "Pin" },
+                        RecentRepoAction::Pin(path.clone(), !entry.pinned),
+                    ))
+                    .child(action(
+                        format!("recent_remove_{}", index),
+                        "Remove",
+                        RecentRepoAction::Remove(path.clone()),
+                    ))
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |_this, _event, _window, cx| {
+                            cx.emit(RecentRepoAction::Open(path.clone()));
+                        }),
+                    )
+            }))
+    }
+
     fn clean_message(message: &str) -> String {
         message.lines().next().unwrap_or(message).to_string()
     }
@@ -823,7 +1446,12 @@ impl Garph {
         }
     }
 
-    fn is_binary_file(repo: &git2::Repository, commit: &git2::Commit, file_path: &str) -> bool {
+    fn is_binary_file(
+        repo: &git2::Repository,
+        commit: &git2::Commit,
+        file_path: &str,
+        max_size: usize,
+    ) -> bool {
         let tree = match commit.tree() {
             Ok(t) => t,
             Err(_) => return false,
@@ -842,7 +1470,7 @@ impl Garph {
         match object.as_blob() {
             Some(blob) => {
                 // Check file size
-                if blob.size() > MAX_FILE_SIZE_BYTES {
+                if blob.size() > max_size {
                     return true;
                 }
 
@@ -865,7 +1493,7 @@ impl Garph {
         let entry = tree.get_path(std::path::Path::new(file_path)).ok()?;
         let object = entry.to_object(repo).ok()?;
         let blob = object.as_blob()?;
-        Some(blob.size() as usize)
+        Some(blob.size())
     }
 }
 
@@ -873,15 +1501,35 @@ impl EventEmitter<CommitSelected> for Garph {}
 
 impl EventEmitter<RepoPathChanged> for Garph {}
 
+impl EventEmitter<CommitOutsideGraph> for Garph {}
+
+impl EventEmitter<CommitContextMenu> for Garph {}
+
+impl EventEmitter<RecentRepoAction> for Garph {}
+
 impl Render for Garph {
     fn render(&mut self, _w: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        self.recompute();
+        if self.layout_stale {
+            self.refresh(cx);
+        }
+
+        let has_repo = self.handle.is_some();
+        let recent = self.recent.clone();
+        let lane_width = self.settings.layout.lane_width;
+        let row_height = self.settings.layout.row_height;
 
-        let has_repo = self.repo.borrow().is_some();
         let nodes = self.nodes.clone();
         let edges = self.edges.clone();
         let height = self.content_height;
         let max_lane = self.max_lane;
+        let selected_oid = self.selected_oid;
+        let tag_labels = self.tag_labels.clone();
+        let unborn_branch = self.unborn_branch.clone();
+        let layout_error = self.layout_error.clone();
+        let loading = self.layout_task.is_some()
+            && nodes.is_empty()
+            && layout_error.is_none()
+            && unborn_branch.is_none();
 
         div()
             .size_full()
@@ -890,6 +1538,7 @@ impl Render for Garph {
             .flex_col()
             .id("garph")
             .overflow_scroll()
+            .track_scroll(&self.scroll_handle)
             .bg(gpui::rgb(0x282828))
             .when(!has_repo, |div1| {
                 div1.child(
@@ -927,6 +1576,52 @@ impl Render for Garph {
                                         );
                                     }),
                                 ),
+                        )
+                        .when(!recent.is_empty(), |this| {
+                            this.child(Self::render_recent(&recent, cx))
+                        }),
+                )
+            })
+            .when(has_repo && loading, |div1| {
+                div1.child(
+                    div()
+                        .absolute()
+                        .inset_0()
+                        .flex()
+                        .items_center()
+                        .justify_center()
+                        .text_color(gpui::rgb(0x969696))
+                        .text_size(px(14.0))
+                        .child("Loading history..."),
+                )
+            })
+            .when_some(layout_error, |div1, error| {
+                div1.child(Self::render_layout_error(error, nodes.is_empty(), cx))
+            })
+            .when_some(unborn_branch, |div1, branch| {
+                div1.child(
+                    div()
+                        .absolute()
+                        .inset_0()
+                        .flex()
+                        .flex_col()
+                        .items_center()
+                        .justify_center()
+                        .gap_2()
+                        .child(
+                            div()
+                                .text_color(gpui::rgb(0x969696))
+                                .text_size(px(14.0))
+                                .child("No commits yet"),
+                        )
+                        .child(
+                            div()
+                                .text_color(gpui::rgb(0x6A6A6A))
+                                .text_size(px(12.0))
+                                .child(format!(
+                                    "The history appears here after the first commit on {}",
+                                    branch
+                                )),
                         ),
                 )
             })
@@ -985,22 +1680,31 @@ impl Render for Garph {
                         let author_text = n.author.clone();
                         let timestamp = n.timestamp;
                         let parents = n.parents.clone();
+                        let mut tags = tag_labels.get(&oid).cloned().unwrap_or_default();
+                        let stash = n.stash;
+                        if let Some(stash) = stash {
+                            tags.push(format!("stash@{{{}}}", stash));
+                        }
 
                         // Calculate text position based on max lane to ensure no overlap
                         let container_text_left =
-                            START_X + (max_lane as f32) * LANE_WIDTH + TEXT_GAP;
+                            START_X + (max_lane as f32) * lane_width + TEXT_GAP;
 
                         div()
                             .absolute()
                             .top(n.position.y)
                             .left(px(0.0))
                             .right(px(0.0))
-                            .h(px(COMMIT_HEIGHT))
+                            .h(px(row_height))
                             .group("commit-row")
+                            .when(selected_oid == Some(oid), |row| {
+                                row.bg(gpui::hsla(0.58, 0.5, 0.4, 0.35))
+                            })
                             .hover(|style| style.bg(gpui::hsla(0.0, 0.0, 0.22, 0.3)))
                             .on_mouse_down(
                                 MouseButton::Left,
-                                cx.listener(move |_this, _event, _window, cx| {
+                                cx.listener(move |this, _event, _window, cx| {
+                                    this.selected_oid = Some(oid);
                                     cx.emit(CommitSelected {
                                         oid,
                                         message: message_text.clone(),
@@ -1010,6 +1714,18 @@ impl Render for Garph {
                                     });
                                 }),
                             )
+                            .on_mouse_down(
+                                MouseButton::Right,
+                                cx.listener(move |this, event: &MouseDownEvent, _window, cx| {
+                                    this.selected_oid = Some(oid);
+                                    cx.emit(CommitContextMenu {
+                                        oid,
+                                        position: event.position,
+                                        stash,
+                                    });
+                                    cx.notify();
+                                }),
+                            )
                             // node (independent absolute positioning)
                             .child(
                                 div()
@@ -1019,6 +1735,10 @@ impl Render for Garph {
                                     .bg(gpui::rgb(VEC_COLORS[n.color]))
                                     .border_color(gpui::black())
                                     .rounded(px(5.0))
+                                    // stashes are drawn as outlined squares
+                                    .when(stash.is_some(), |node| {
+                                        node.rounded(px(1.0)).border_1().border_color(gpui::white())
+                                    })
                                     .group_hover("commit-row", |style| style.size(SIZE + px(20.0))),
                             )
                             // text (independent absolute positioning)
@@ -1026,6 +1746,10 @@ impl Render for Garph {
                                 div()
                                     .absolute()
                                     .left(px(container_text_left))
+                                    .flex()
+                                    .flex_row()
+                                    .items_center()
+                                    .gap_1()
                                     .px(px(10.0))
                                     .py(px(5.0))
                                     .max_w(px(600.0))
@@ -1035,9 +1759,194 @@ impl Render for Garph {
                                     .text_color(gpui::rgb(0x969696))
                                     .text_size(px(10.0))
                                     .line_clamp(1)
-                                    .child(format!("{}", truncated_message)),
+                                    // tag badges
+                                    .children(tags.into_iter().map(|tag| {
+                                        let badge = if tag.starts_with("stash@") {
+                                            VEC_COLORS[STASH_COLOR]
+                                        } else {
+                                            GIT_YELLOW
+                                        };
+                                        div()
+                                            .px(px(4.0))
+                                            .rounded(px(3.0))
+                                            .bg(gpui::rgb(badge))
+                                            .text_color(gpui::black())
+                                            .child(tag)
+                                    }))
+                                    .child(truncated_message),
                             )
                     }))),
             )
     }
 }
+
+/// Rows and edges of the graph, built off the UI thread and swapped in whole.
+#[derive(Default)]
+struct GraphLayout {
+    nodes: Vec<CommitNode>,
+    edges: Vec<Edge>,
+    content_height: Pixels,
+    max_lane: usize,
+    tag_labels: HashMap<Oid, Vec<String>>,
+    unborn_branch: Option<String>,
+    error: Option<LayoutError>,
+}
+
+impl GraphLayout {
+    fn build(backend: &dyn Backend, settings: &Settings) -> Self {
+        let mut layout = Self::default();
+        layout.error = Self::walk(backend, settings, &mut layout).err();
+        layout
+    }
+
+    fn walk(
+        backend: &dyn Backend,
+        settings: &Settings,
+        layout: &mut Self,
+    ) -> Result<(), LayoutError> {
+        let lane_width = settings.layout.lane_width;
+        let row_height = settings.layout.row_height;
+        for (name, oid) in backend.tags() {
+            layout.tag_labels.entry(oid).or_default().push(name);
+        }
+        let head = match backend.resolve_head() {
+            Head::Commit(oid) => oid,
+            Head::Unborn(branch) => {
+                // a new repository: HEAD names a branch without commits
+                layout.unborn_branch = Some(branch);
+                return Ok(());
+            }
+            Head::Broken(message) => return Err(LayoutError::BrokenHead(message)),
+        };
+        let history_error = |e: GpigError| LayoutError::History(e.to_string());
+        let oids = backend
+            .walk(head, settings.history.limit_rows, settings.history.sort)
+            .map_err(history_error)?;
+        let shallow = backend.shallow_boundary();
+
+        // stashes hang off their base commit, newest first
+        let mut stashes: HashMap<Oid, Vec<StashEntry>> = HashMap::new();
+        for stash in backend.stashes() {
+            stashes.entry(stash.base).or_default().push(stash);
+        }
+
+        let mut lane_manager = LaneManager::new();
+        let mut edge_manager = EdgeManager::new();
+        let mut color_manager = ColorManager::new(VEC_COLORS[..STASH_COLOR].to_vec());
+
+        let mut history_oids_manager = HistoryOidManager::new();
+
+        let mut index = 0;
+        let mut error = None;
+        for oid in oids {
+            let commit = match backend.commit_info(oid) {
+                Ok(commit) => commit,
+                Err(e) => {
+                    error = Some(history_error(e));
+                    break;
+                }
+            };
+            // parents cut off by a shallow clone or missing from the object database
+            // end the lane here instead of reserving it forever
+            let parents: Vec<Oid> = commit
+                .parents
+                .iter()
+                .copied()
+                .filter(|parent| backend.contains(*parent))
+                .collect();
+            let truncated = parents.len() < commit.parents.len() || shallow.contains(&oid);
+
+            // stash rows sit right above their base, in a lane no branch uses
+            for stash in stashes.remove(&oid).unwrap_or_default() {
+                let Ok(stash_commit) = backend.commit_info(stash.oid) else {
+                    continue;
+                };
+                let lane = lane_manager.get_lanes().len();
+                layout.max_lane = layout.max_lane.max(lane);
+                let pos = Point::new(
+                    (START_X + (lane as f32) * lane_width).into(),
+                    (row_height * index as f32).into(),
+                );
+                let edge_point = Point::new(pos.x + SIZE / 2.0, pos.y + SIZE / 2.0);
+                history_oids_manager
+                    .add_history(oid, HistoryOid::new(edge_point, STASH_COLOR, lane));
+
+                let mut node = CommitNode::new(
+                    stash.oid,
+                    stash.message,
+                    stash_commit.author,
+                    stash_commit.time,
+                    vec![oid],
+                    pos,
+                    STASH_COLOR,
+                );
+                node.stash = Some(stash.index);
+                layout.nodes.push(node);
+                index += 1;
+            }
+
+            let lane = lane_manager.assign_commit(&oid, &parents);
+
+            let color = color_manager.get_color(&lane);
+
+            let pos = Point::new(
+                (START_X + (lane as f32) * lane_width).into(),
+                (row_height * index as f32).into(),
+            );
+
+            // Track maximum lane
+            if lane > layout.max_lane {
+                layout.max_lane = lane;
+            }
+
+            let current_edge_point = Point::new(pos.x + SIZE / 2.0, pos.y + SIZE / 2.0);
+
+            // connect edges
+            if let Some(history_oids) = history_oids_manager.get(&oid) {
+                for history in history_oids {
+                    if history.edge_point.x > current_edge_point.x {
+                        edge_manager.add(history.edge_point, current_edge_point, history.color);
+
+                        if history.lane > 0 {
+                            color_manager.remove_lane_color(&history.lane);
+                        }
+                    } else if history.edge_point.x < current_edge_point.x {
+                        edge_manager.add(current_edge_point, history.edge_point, color);
+                    } else {
+                        edge_manager.add(history.edge_point, current_edge_point, history.color);
+                    }
+                }
+            }
+
+            for parent in &parents {
+                history_oids_manager
+                    .add_history(*parent, HistoryOid::new(current_edge_point, color, lane));
+            }
+            if truncated {
+                // a short stub below the node marks history that is not available
+                let stub_end = Point::new(
+                    current_edge_point.x,
+                    current_edge_point.y + px(row_height * 0.6),
+                );
+                edge_manager.add(current_edge_point, stub_end, color);
+            }
+
+            let mut node = CommitNode::new(
+                oid,
+                commit.message,
+                commit.author,
+                commit.time,
+                parents,
+                pos,
+                color,
+            );
+            node.truncated = truncated;
+            layout.nodes.push(node);
+            index += 1;
+        }
+
+        layout.edges = edge_manager.take_edges();
+        layout.content_height = px(layout.nodes.len() as f32 * row_height + GAP_ROW);
+        error.map_or(Ok(()), Err)
+    }
+}
@@ -30,12 +30,15 @@ impl HistoryOidManager {
         }
     }
     pub fn add_history(&mut self, oid: Oid, history_oid: HistoryOid) {
-        self.history_oid
-            .entry(oid)
-            .or_insert_with(Vec::new)
-            .push(history_oid);
+        self.history_oid.entry(oid).or_default().push(history_oid);
     }
     pub fn get(&self, oid: &Oid) -> Option<&[HistoryOid]> {
         self.history_oid.get(oid).map(|oids| oids.as_slice())
     }
 }
+
+impl Default for HistoryOidManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,93 @@
+use std::path::PathBuf;
+
+use git2::{Repository, RepositoryInitOptions};
+
+/// Starter `.gitignore` files offered when creating a repository.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GitignoreTemplate {
+    General,
+    Rust,
+    Node,
+    Python,
+}
+
+impl GitignoreTemplate {
+    pub const ALL: [GitignoreTemplate; 4] = [
+        GitignoreTemplate::General,
+        GitignoreTemplate::Rust,
+        GitignoreTemplate::Node,
+        GitignoreTemplate::Python,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GitignoreTemplate::General => "General",
+            GitignoreTemplate::Rust => "Rust",
+            GitignoreTemplate::Node => "Node",
+            GitignoreTemplate::Python => "Python",
+        }
+    }
+
+    pub fn content(&self) -> String {
+        let general =
+            "# editors and operating systems\n.DS_Store\nThumbs.db\n.idea/\n.vscode/\n*.swp\n*~\n";
+        let specific = match self {
+            GitignoreTemplate::General => "",
+            GitignoreTemplate::Rust => "\n# build output\n/target/\n",
+            GitignoreTemplate::Node => {
+                "\n# dependencies and build output\nnode_modules/\ndist/\n*.log\n"
+            }
+            GitignoreTemplate::Python => {
+                "\n# bytecode and environments\n__pycache__/\n*.py[cod]\n.venv/\n*.egg-info/\n"
+            }
+        };
+        format!("{}{}", general, specific)
+    }
+}
+
+/// What the init dialog collected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InitOptions {
+    /// Created if missing.
+    pub path: PathBuf,
+    pub initial_branch: String,
+    pub bare: bool,
+    pub gitignore: Option<GitignoreTemplate>,
+}
+
+/// The branch name `git init` would use, from `init.defaultBranch`.
+pub fn default_branch() -> String {
+    git2::Config::open_default()
+        .and_then(|config| config.get_string("init.defaultBranch"))
+        .unwrap_or_else(|_| "main".to_string())
+}
+
+/// Create a repository; an existing repository at the path is left untouched.
+pub fn init(options: &InitOptions) -> Result<Repository, git2::Error> {
+    let branch = options.initial_branch.trim();
+    if !git2::Branch::name_is_valid(branch)? {
+        return Err(git2::Error::from_str(&format!(
+            "'{}' is not a valid branch name",
+            branch
+        )));
+    }
+    if options.bare && options.gitignore.is_some() {
+        return Err(git2::Error::from_str(
+            "a bare repository has no working tree to put a .gitignore in",
+        ));
+    }
+
+    let mut init_options = RepositoryInitOptions::new();
+    init_options
+        .bare(options.bare)
+        .no_reinit(true)
+        .mkpath(true)
+        .initial_head(branch);
+    let repo = Repository::init_opts(&options.path, &init_options)?;
+
+    if let (Some(template), Some(workdir)) = (options.gitignore, repo.workdir()) {
+        std::fs::write(workdir.join(".gitignore"), template.content())
+            .map_err(|e| git2::Error::from_str(&e.to_string()))?;
+    }
+    Ok(repo)
+}
@@ -58,3 +58,9 @@ impl LaneManager {
         lane
     }
 }
+
+impl Default for LaneManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
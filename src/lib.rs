@@ -1,12 +1,36 @@
+pub mod backend;
+pub mod cli;
 pub mod color;
 pub mod commit;
+pub mod conflict;
+pub mod conflict_view;
+pub mod credentials;
 pub mod diff_pane;
 pub mod edge;
+pub mod error;
+#[cfg(any(test, feature = "test-support"))]
+pub mod fixture;
 pub mod garph;
 pub mod history_oid;
+pub mod init;
 pub mod lane;
 pub mod menu;
+pub mod modal;
+pub mod operations;
+pub mod rebase;
+pub mod rebase_planner;
+pub mod recent;
+pub mod refs;
+pub mod remote;
+pub mod remote_manager;
+pub mod restore;
+pub mod settings;
+pub mod sidebar;
+pub mod stash;
+pub mod tabs;
+pub mod text_input;
 
 pub mod actions;
 pub mod title;
+pub mod watcher;
 pub mod workspace;
@@ -1,47 +1,56 @@
 use dotenv::dotenv;
 use gpig::actions::{OpenFile, Quit};
-use gpig::garph::Garph;
-use gpig::workspace::Workspace;
+use gpig::cli::{self, Launch};
+use gpig::tabs::{self, Tabs, UserFiles};
+use gpig::text_input;
 use gpui::{App, AppContext, Application, KeyBinding, WindowOptions};
 use rfd::FileDialog;
 use std::error::Error;
 
 fn main() -> Result<(), Box<dyn Error>> {
+    // .env may set GPIG_REPO
     dotenv().ok();
-    let garph_value = Garph::new(None);
+    let repo = match cli::parse(std::env::args_os().skip(1), std::env::var_os(cli::REPO_ENV)) {
+        Launch::Help => {
+            println!("{}", cli::USAGE);
+            return Ok(());
+        }
+        Launch::Open { path, explicit } => match cli::discover(&path) {
+            Ok(repo) => Some(repo),
+            Err(e) => {
+                if explicit {
+                    eprintln!("No git repository at {}: {}", path.display(), e.message());
+                }
+                None
+            }
+        },
+    };
 
     Application::new().run(move |cx: &mut App| {
-        let garph = cx.new(|_| garph_value.clone());
-
         cx.bind_keys([KeyBinding::new("ctrl-q", Quit, None)]);
+        cx.bind_keys(text_input::key_bindings());
+        cx.bind_keys(tabs::key_bindings());
         cx.on_action(|_action: &Quit, cx: &mut App| {
             println!("Quit action received");
             cx.quit();
         });
 
-        let garph_for_action = garph.clone();
-        cx.on_action(move |_action: &OpenFile, cx: &mut App| {
-            println!("OpenFile action handler triggered!");
-            let garph_clone = garph_for_action.clone();
-            if let Some(path) = FileDialog::new().pick_folder() {
-                let path_str = format!("{}", path.display());
-                println!("OpenFile action received: {}", path_str);
-                cx.update_entity(&garph_clone, |garph, _cx| {
-                    if let Err(e) = garph.update_repo(&path_str) {
-                        eprintln!("Failed to update repo: {}", e);
-                    }
-                });
-            }
-        });
-
         cx.open_window(
             WindowOptions {
                 // window_bounds: Some(WindowBounds::Windowed(bounds)),
                 ..Default::default()
             },
             move |_, cx: &mut App| {
-                let garph = garph.clone();
-                cx.new(|cx| Workspace::new(Some(garph), cx))
+                let tabs = cx.new(|cx| Tabs::new(repo, UserFiles::locate(), cx));
+
+                // File → Open and ctrl-o open the picked repository in a tab
+                let tabs_for_action = tabs.clone();
+                cx.on_action(move |_action: &OpenFile, cx: &mut App| {
+                    if let Some(path) = FileDialog::new().pick_folder() {
+                        tabs_for_action.update(cx, |tabs, cx| tabs.open_or_notify(&path, cx));
+                    }
+                });
+                tabs
             },
         )
         .unwrap();
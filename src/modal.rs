@@ -0,0 +1,98 @@
+use gpui::prelude::*;
+use gpui::{AnyElement, Div, ElementId, MouseButton, SharedString, Stateful, div, px};
+
+pub const BUTTON_PRIMARY: u32 = 0x4A90D9;
+pub const BUTTON_DANGER: u32 = 0xC0392B;
+pub const BUTTON_NEUTRAL: u32 = 0x444444;
+
+/// Dimmed backdrop with a centered dialog card holding `body` and a row of `buttons`.
+pub fn render_modal(
+    title: impl Into<SharedString>,
+    body: impl IntoElement,
+    buttons: Vec<AnyElement>,
+) -> AnyElement {
+    div()
+        .absolute()
+        .inset_0()
+        .flex()
+        .items_center()
+        .justify_center()
+        .bg(gpui::hsla(0.0, 0.0, 0.0, 0.5))
+        // keep clicks inside the dialog from reaching the panes below
+        .on_mouse_down(MouseButton::Left, |_event, _window, cx| {
+            cx.stop_propagation();
+        })
+        .child(
+            div()
+                .w(px(420.0))
+                .flex()
+                .flex_col()
+                .gap_3()
+                .p(px(16.0))
+                .bg(gpui::rgb(0x1E1E1E))
+                .border_1()
+                .border_color(gpui::rgb(0x333333))
+                .rounded(px(6.0))
+                .shadow_lg()
+                .child(
+                    div()
+                        .text_color(gpui::white())
+                        .font_weight(gpui::FontWeight::BOLD)
+                        .text_size(px(14.0))
+                        .child(title.into()),
+                )
+                .child(body)
+                .child(
+                    div()
+                        .flex()
+                        .flex_row()
+                        .justify_end()
+                        .gap_2()
+                        .children(buttons),
+                ),
+        )
+        .into_any()
+}
+
+pub fn modal_button(
+    id: impl Into<ElementId>,
+    label: impl Into<SharedString>,
+    color: u32,
+) -> Stateful<Div> {
+    div()
+        .id(id.into())
+        .px(px(14.0))
+        .py(px(6.0))
+        .bg(gpui::rgb(color))
+        .rounded(px(4.0))
+        .text_color(gpui::white())
+        .text_size(px(13.0))
+        .cursor_pointer()
+        .hover(|style| style.opacity(0.85))
+        .child(label.into())
+}
+
+pub fn field_label(label: impl Into<SharedString>) -> Div {
+    div()
+        .text_color(gpui::rgb(0x969696))
+        .text_size(px(12.0))
+        .child(label.into())
+}
+
+pub fn checkbox(
+    id: impl Into<ElementId>,
+    label: impl Into<SharedString>,
+    checked: bool,
+) -> Stateful<Div> {
+    div()
+        .id(id.into())
+        .flex()
+        .flex_row()
+        .items_center()
+        .gap_2()
+        .cursor_pointer()
+        .text_color(gpui::rgb(0xCCCCCC))
+        .text_size(px(13.0))
+        .child(if checked { "☑" } else { "☐" })
+        .child(label.into())
+}
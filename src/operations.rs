@@ -0,0 +1,646 @@
+use std::path::Path;
+
+use git2::{BranchType, Commit, Index, Oid, Repository, RepositoryState, Signature};
+
+use crate::rebase::{self, RebaseProgress};
+
+/// Written by a conflicted squash merge, which has no MERGE_HEAD.
+const SQUASH_MSG: &str = "SQUASH_MSG";
+/// Names the operation gpig left stopped when git keeps no state for it.
+pub(crate) const OPERATION_FILE: &str = "GPIG_OPERATION";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OperationKind {
+    CherryPick,
+    Revert,
+    Merge,
+    Squash,
+    Rebase,
+    /// Applying a stash left conflicts; there is nothing to commit afterwards.
+    StashApply,
+}
+
+impl OperationKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            OperationKind::CherryPick => "Cherry-pick",
+            OperationKind::Revert => "Revert",
+            OperationKind::Merge => "Merge",
+            OperationKind::Squash => "Squash merge",
+            OperationKind::Rebase => "Rebase",
+            OperationKind::StashApply => "Stash apply",
+        }
+    }
+
+    fn from_repo(repo: &Repository) -> Option<Self> {
+        match repo.state() {
+            RepositoryState::Merge => Some(OperationKind::Merge),
+            RepositoryState::Clean => {
+                let conflicts = repo
+                    .index()
+                    .map(|index| index.has_conflicts())
+                    .unwrap_or(false);
+                let recorded = std::fs::read_to_string(repo.path().join(OPERATION_FILE))
+                    .ok()
+                    .and_then(|name| Self::from_name(name.trim()));
+                // a clean `git merge --squash` leaves SQUASH_MSG behind too
+                if repo.path().join(SQUASH_MSG).exists()
+                    && (conflicts || recorded == Some(OperationKind::Squash))
+                {
+                    Some(OperationKind::Squash)
+                } else if conflicts && recorded == Some(OperationKind::StashApply) {
+                    Some(OperationKind::StashApply)
+                } else {
+                    None
+                }
+            }
+            state => Self::from_state(state),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            OperationKind::CherryPick => "cherry-pick",
+            OperationKind::Revert => "revert",
+            OperationKind::Merge => "merge",
+            OperationKind::Squash => "squash",
+            OperationKind::Rebase => "rebase",
+            OperationKind::StashApply => "stash-apply",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [
+            OperationKind::CherryPick,
+            OperationKind::Revert,
+            OperationKind::Merge,
+            OperationKind::Squash,
+            OperationKind::Rebase,
+            OperationKind::StashApply,
+        ]
+        .into_iter()
+        .find(|kind| kind.name() == name)
+    }
+
+    fn from_state(state: RepositoryState) -> Option<Self> {
+        match state {
+            RepositoryState::CherryPick | RepositoryState::CherryPickSequence => {
+                Some(OperationKind::CherryPick)
+            }
+            RepositoryState::Revert | RepositoryState::RevertSequence => {
+                Some(OperationKind::Revert)
+            }
+            RepositoryState::Rebase
+            | RepositoryState::RebaseInteractive
+            | RepositoryState::RebaseMerge => Some(OperationKind::Rebase),
+            _ => None,
+        }
+    }
+}
+
+/// An operation that applies a single commit on top of HEAD.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommitOperation {
+    CherryPick,
+    Revert,
+}
+
+impl CommitOperation {
+    /// The state a conflicted run leaves behind.
+    pub fn kind(&self) -> OperationKind {
+        match self {
+            CommitOperation::CherryPick => OperationKind::CherryPick,
+            CommitOperation::Revert => OperationKind::Revert,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OperationOutcome {
+    /// The operation applied cleanly and created this commit.
+    Committed(Oid),
+    /// The working tree and index were left with conflicts in these paths.
+    Conflicts(Vec<String>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeMode {
+    FastForwardOnly,
+    NoFastForward,
+    Squash,
+}
+
+impl MergeMode {
+    pub const ALL: [MergeMode; 3] = [
+        MergeMode::FastForwardOnly,
+        MergeMode::NoFastForward,
+        MergeMode::Squash,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MergeMode::FastForwardOnly => "Fast-forward only",
+            MergeMode::NoFastForward => "Always create a merge commit",
+            MergeMode::Squash => "Squash into a single commit",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResetMode {
+    Soft,
+    Mixed,
+    Hard,
+}
+
+impl ResetMode {
+    pub const ALL: [ResetMode; 3] = [ResetMode::Soft, ResetMode::Mixed, ResetMode::Hard];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ResetMode::Soft => "Soft: keep index and working tree",
+            ResetMode::Mixed => "Mixed: keep working tree, reset index",
+            ResetMode::Hard => "Hard: discard all uncommitted changes",
+        }
+    }
+
+    fn reset_type(&self) -> git2::ResetType {
+        match self {
+            ResetMode::Soft => git2::ResetType::Soft,
+            ResetMode::Mixed => git2::ResetType::Mixed,
+            ResetMode::Hard => git2::ResetType::Hard,
+        }
+    }
+}
+
+/// Result of merging in memory, before the working tree is touched.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MergePreview {
+    pub up_to_date: bool,
+    pub can_fast_forward: bool,
+    pub conflicts: Vec<String>,
+}
+
+/// An operation that stopped half way and waits for the user.
+#[derive(Clone, Debug)]
+pub struct ConflictState {
+    pub kind: OperationKind,
+    pub paths: Vec<String>,
+    /// Set while a rebase is stopped.
+    pub progress: Option<RebaseProgress>,
+}
+
+/// Apply `oid` on top of HEAD. `mainline` is the 1-based parent to diff
+/// against and is required for merge commits.
+pub fn cherry_pick(
+    repo: &Repository,
+    oid: Oid,
+    mainline: Option<u32>,
+) -> Result<OperationOutcome, git2::Error> {
+    check_idle(repo)?;
+    check_nothing_staged(repo)?;
+    let commit = repo.find_commit(oid)?;
+    check_mainline(&commit, mainline)?;
+
+    let mut opts = git2::CherrypickOptions::new();
+    if let Some(mainline) = mainline {
+        opts.mainline(mainline);
+    }
+    repo.cherrypick(&commit, Some(&mut opts))?;
+
+    let mut index = repo.index()?;
+    if index.has_conflicts() {
+        return Ok(OperationOutcome::Conflicts(conflicted_paths(&index)?));
+    }
+
+    let committer = repo.signature()?;
+    let message = commit.message().unwrap_or_default().to_string();
+    let head = repo.head()?.peel_to_commit()?;
+    let new_oid = commit_index(
+        repo,
+        &mut index,
+        &commit.author(),
+        &committer,
+        &message,
+        &[&head],
+    )?;
+    repo.cleanup_state()?;
+    Ok(OperationOutcome::Committed(new_oid))
+}
+
+/// Create a commit on HEAD that undoes `oid`.
+pub fn revert(
+    repo: &Repository,
+    oid: Oid,
+    mainline: Option<u32>,
+) -> Result<OperationOutcome, git2::Error> {
+    check_idle(repo)?;
+    check_nothing_staged(repo)?;
+    let commit = repo.find_commit(oid)?;
+    check_mainline(&commit, mainline)?;
+
+    let mut opts = git2::RevertOptions::new();
+    if let Some(mainline) = mainline {
+        opts.mainline(mainline);
+    }
+    repo.revert(&commit, Some(&mut opts))?;
+
+    let mut index = repo.index()?;
+    if index.has_conflicts() {
+        return Ok(OperationOutcome::Conflicts(conflicted_paths(&index)?));
+    }
+
+    let signature = repo.signature()?;
+    let message = revert_message(&commit);
+    let head = repo.head()?.peel_to_commit()?;
+    let new_oid = commit_index(repo, &mut index, &signature, &signature, &message, &[&head])?;
+    repo.cleanup_state()?;
+    Ok(OperationOutcome::Committed(new_oid))
+}
+
+/// Merge `oid` in memory to find out what merging it into HEAD would do.
+pub fn preview_merge(repo: &Repository, oid: Oid) -> Result<MergePreview, git2::Error> {
+    let annotated = repo.find_annotated_commit(oid)?;
+    let (analysis, _) = repo.merge_analysis(&[&annotated])?;
+
+    let conflicts = if analysis.is_normal() {
+        let head = repo.head()?.peel_to_commit()?;
+        let theirs = repo.find_commit(oid)?;
+        let index = repo.merge_commits(&head, &theirs, None)?;
+        conflicted_paths(&index)?
+    } else {
+        Vec::new()
+    };
+
+    Ok(MergePreview {
+        up_to_date: analysis.is_up_to_date(),
+        can_fast_forward: analysis.is_fast_forward(),
+        conflicts,
+    })
+}
+
+/// Merge `oid` into the current branch. Conflicts leave the repository in a
+/// merging state with MERGE_HEAD (or SQUASH_MSG for squash merges).
+pub fn merge(
+    repo: &Repository,
+    oid: Oid,
+    mode: MergeMode,
+) -> Result<OperationOutcome, git2::Error> {
+    check_idle(repo)?;
+    let annotated = repo.find_annotated_commit(oid)?;
+    let (analysis, _) = repo.merge_analysis(&[&annotated])?;
+    let head = repo.head()?.peel_to_commit()?;
+
+    if analysis.is_up_to_date() {
+        return Err(git2::Error::from_str("already up to date"));
+    }
+
+    match mode {
+        MergeMode::FastForwardOnly => {
+            if !analysis.is_fast_forward() {
+                return Err(git2::Error::from_str(
+                    "branches have diverged, cannot fast-forward",
+                ));
+            }
+            fast_forward(repo, oid)?;
+            Ok(OperationOutcome::Committed(oid))
+        }
+        MergeMode::NoFastForward | MergeMode::Squash => {
+            check_nothing_staged(repo)?;
+            let theirs = repo.find_commit(oid)?;
+            let mut checkout = git2::build::CheckoutBuilder::new();
+            checkout.safe();
+            repo.merge(&[&annotated], None, Some(&mut checkout))?;
+
+            let squash = mode == MergeMode::Squash;
+            let message = if squash {
+                squash_message(repo, &head, &theirs)?
+            } else {
+                merge_message(repo, oid)?
+            };
+            let message_file = if squash {
+                // a squash merge records no second parent
+                repo.cleanup_state()?;
+                let _ = repo.remove_message();
+                SQUASH_MSG
+            } else {
+                "MERGE_MSG"
+            };
+            std::fs::write(repo.path().join(message_file), &message)
+                .map_err(|e| git2::Error::from_str(&e.to_string()))?;
+
+            let mut index = repo.index()?;
+            if index.has_conflicts() {
+                if squash {
+                    record_operation(repo, OperationKind::Squash)?;
+                }
+                return Ok(OperationOutcome::Conflicts(conflicted_paths(&index)?));
+            }
+
+            let signature = repo.signature()?;
+            let parents: Vec<&Commit> = if squash {
+                vec![&head]
+            } else {
+                vec![&head, &theirs]
+            };
+            let new_oid =
+                commit_index(repo, &mut index, &signature, &signature, &message, &parents)?;
+            finish_operation(repo)?;
+            Ok(OperationOutcome::Committed(new_oid))
+        }
+    }
+}
+
+fn fast_forward(repo: &Repository, oid: Oid) -> Result<(), git2::Error> {
+    let target = repo.find_object(oid, None)?;
+    let mut checkout = git2::build::CheckoutBuilder::new();
+    checkout.safe();
+    repo.checkout_tree(&target, Some(&mut checkout))?;
+
+    let mut head = repo.head()?;
+    if head.is_branch() {
+        head.set_target(oid, &format!("merge {}: Fast-forward", oid))?;
+    } else {
+        repo.set_head_detached(oid)?;
+    }
+    Ok(())
+}
+
+fn merge_message(repo: &Repository, oid: Oid) -> Result<String, git2::Error> {
+    for branch in repo.branches(Some(BranchType::Local))? {
+        let (branch, _) = branch?;
+        if branch.get().target() == Some(oid)
+            && let Some(name) = branch.name()?
+        {
+            return Ok(format!("Merge branch '{}'", name));
+        }
+    }
+    Ok(format!("Merge commit '{}'", oid))
+}
+
+fn squash_message(
+    repo: &Repository,
+    head: &Commit,
+    theirs: &Commit,
+) -> Result<String, git2::Error> {
+    let mut message = String::from("Squashed commit of the following:\n");
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(theirs.id())?;
+    revwalk.hide(head.id())?;
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        message.push_str(&format!(
+            "\ncommit {}\n\n    {}\n",
+            commit.id(),
+            commit.summary().unwrap_or_default()
+        ));
+    }
+    Ok(message)
+}
+
+/// Move the current branch (or detached HEAD) to `oid`. The previous HEAD is
+/// kept in ORIG_HEAD and in the reflog, so the reset can be undone. The reflog
+/// entries read like git's own, "reset: updating ORIG_HEAD" and "reset: moving to".
+pub fn reset(repo: &Repository, oid: Oid, mode: ResetMode) -> Result<(), git2::Error> {
+    check_idle(repo)?;
+    let old_head = repo.head()?.peel_to_commit()?.id();
+    let target = repo.find_object(oid, None)?;
+    repo.reference("ORIG_HEAD", old_head, true, "reset: updating ORIG_HEAD")?;
+
+    let mut checkout = git2::build::CheckoutBuilder::new();
+    checkout.force();
+    let checkout = match mode {
+        ResetMode::Hard => Some(&mut checkout),
+        _ => None,
+    };
+    repo.reset(&target, mode.reset_type(), checkout)
+}
+
+/// Tracked changes a hard reset would throw away, as "M path" lines.
+pub fn uncommitted_changes(repo: &Repository) -> Result<Vec<String>, git2::Error> {
+    let mut changes = Vec::new();
+    for entry in repo.statuses(None)?.iter() {
+        let status = entry.status();
+        // untracked and ignored files survive a hard reset
+        let letter = if status.is_conflicted() {
+            "U"
+        } else if status.is_index_new() {
+            "A"
+        } else if status.is_index_deleted() || status.is_wt_deleted() {
+            "D"
+        } else if status.intersects(
+            git2::Status::INDEX_MODIFIED
+                | git2::Status::INDEX_RENAMED
+                | git2::Status::INDEX_TYPECHANGE
+                | git2::Status::WT_MODIFIED
+                | git2::Status::WT_RENAMED
+                | git2::Status::WT_TYPECHANGE,
+        ) {
+            "M"
+        } else {
+            continue;
+        };
+        changes.push(format!(
+            "{} {}",
+            letter,
+            String::from_utf8_lossy(entry.path_bytes())
+        ));
+    }
+    Ok(changes)
+}
+
+pub fn revert_message(commit: &Commit) -> String {
+    format!(
+        "Revert \"{}\"\n\nThis reverts commit {}.\n",
+        commit.summary().unwrap_or_default(),
+        commit.id()
+    )
+}
+
+pub fn conflicted_paths(index: &Index) -> Result<Vec<String>, git2::Error> {
+    let mut paths = Vec::new();
+    for conflict in index.conflicts()? {
+        let conflict = conflict?;
+        if let Some(entry) = conflict.our.or(conflict.their).or(conflict.ancestor) {
+            paths.push(String::from_utf8_lossy(&entry.path).to_string());
+        }
+    }
+    Ok(paths)
+}
+
+/// Describe the operation the repository is in the middle of, if any.
+pub fn conflict_state(repo: &Repository) -> Result<Option<ConflictState>, git2::Error> {
+    let Some(kind) = OperationKind::from_repo(repo) else {
+        return Ok(None);
+    };
+    let paths = conflicted_paths(&repo.index()?)?;
+    let progress = match kind {
+        OperationKind::Rebase => rebase::rebase_progress(repo)?,
+        _ => None,
+    };
+    Ok(Some(ConflictState {
+        kind,
+        paths,
+        progress,
+    }))
+}
+
+/// Commit the resolved index of a stopped operation and leave the
+/// operation state.
+pub fn continue_operation(repo: &Repository) -> Result<Oid, git2::Error> {
+    let mut index = repo.index()?;
+    if index.has_conflicts() {
+        return Err(git2::Error::from_str(
+            "resolve all conflicts before continuing",
+        ));
+    }
+
+    let committer = repo.signature()?;
+    // keep the original author when finishing a cherry-pick
+    let author = match repo.find_reference("CHERRY_PICK_HEAD") {
+        Ok(reference) => reference.peel_to_commit()?.author().to_owned(),
+        Err(_) => committer.clone(),
+    };
+    let message = match std::fs::read_to_string(repo.path().join(SQUASH_MSG)) {
+        Ok(message) => message,
+        Err(_) => repo.message().unwrap_or_default(),
+    };
+
+    let mut parents = vec![repo.head()?.peel_to_commit()?];
+    if repo.state() == RepositoryState::Merge {
+        let merge_heads = std::fs::read_to_string(repo.path().join("MERGE_HEAD"))
+            .map_err(|e| git2::Error::from_str(&e.to_string()))?;
+        for line in merge_heads.lines().filter(|l| !l.trim().is_empty()) {
+            parents.push(repo.find_commit(Oid::from_str(line.trim())?)?);
+        }
+    }
+    let parents: Vec<&Commit> = parents.iter().collect();
+
+    let oid = commit_index(repo, &mut index, &author, &committer, &message, &parents)?;
+    finish_operation(repo)?;
+    Ok(oid)
+}
+
+/// Throw away a stopped operation and go back to HEAD. Like `git merge --abort`,
+/// only the paths the operation touched are reset; other local changes are kept.
+pub fn abort_operation(repo: &Repository) -> Result<(), git2::Error> {
+    if OperationKind::from_repo(repo) == Some(OperationKind::Rebase) {
+        return rebase::abort_rebase(repo);
+    }
+    let head = repo.head()?.peel_to_commit()?;
+    let tree = head.tree()?;
+
+    // the operation wrote its result to the index: conflicts and staged paths
+    let index = repo.index()?;
+    let mut paths = conflicted_paths(&index)?;
+    let diff = repo.diff_tree_to_index(Some(&tree), Some(&index), None)?;
+    for delta in diff.deltas() {
+        for file in [delta.old_file(), delta.new_file()] {
+            if let Some(path) = file.path().and_then(|p| p.to_str())
+                && !paths.iter().any(|p| p == path)
+            {
+                paths.push(path.to_string());
+            }
+        }
+    }
+
+    if !paths.is_empty() {
+        repo.reset_default(Some(head.as_object()), &paths)?;
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        checkout.force();
+        let mut in_head = false;
+        for path in &paths {
+            if tree.get_path(Path::new(path)).is_ok() {
+                checkout.path(path);
+                in_head = true;
+            } else if let Some(workdir) = repo.workdir() {
+                // added by the operation
+                match std::fs::remove_file(workdir.join(path)) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                        return Err(git2::Error::from_str(&e.to_string()));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if in_head {
+            repo.checkout_head(Some(&mut checkout))?;
+        }
+    }
+    finish_operation(repo)
+}
+
+/// Refuse to start an operation while another one waits for the user.
+fn check_idle(repo: &Repository) -> Result<(), git2::Error> {
+    if let Some(kind) = OperationKind::from_repo(repo) {
+        return Err(git2::Error::from_str(&format!(
+            "a {} is in progress, continue or abort it first",
+            kind.label().to_lowercase()
+        )));
+    }
+    if repo.state() != RepositoryState::Clean {
+        return Err(git2::Error::from_str(
+            "the repository is in the middle of another operation",
+        ));
+    }
+    // left by an operation that was finished outside gpig
+    let _ = std::fs::remove_file(repo.path().join(OPERATION_FILE));
+    Ok(())
+}
+
+/// Refuse to run while the index differs from HEAD, since the commit the operation
+/// makes would take the staged changes with it.
+fn check_nothing_staged(repo: &Repository) -> Result<(), git2::Error> {
+    let head = repo.head()?.peel_to_tree()?;
+    let staged = repo.diff_tree_to_index(Some(&head), None, None)?;
+    if staged.deltas().len() > 0 {
+        return Err(git2::Error::from_str(
+            "commit or stash your staged changes first",
+        ));
+    }
+    Ok(())
+}
+
+/// Remember that gpig stopped `kind` half way, for operations git has no state for.
+pub(crate) fn record_operation(repo: &Repository, kind: OperationKind) -> Result<(), git2::Error> {
+    std::fs::write(repo.path().join(OPERATION_FILE), kind.name())
+        .map_err(|e| git2::Error::from_str(&e.to_string()))
+}
+
+/// Drop every state file an operation may have left behind.
+fn finish_operation(repo: &Repository) -> Result<(), git2::Error> {
+    repo.cleanup_state()?;
+    // MERGE_MSG and SQUASH_MSG are not removed by cleanup_state
+    let _ = repo.remove_message();
+    let _ = std::fs::remove_file(repo.path().join(SQUASH_MSG));
+    let _ = std::fs::remove_file(repo.path().join(OPERATION_FILE));
+    Ok(())
+}
+
+fn check_mainline(commit: &Commit, mainline: Option<u32>) -> Result<(), git2::Error> {
+    let parents = commit.parent_count() as u32;
+    match mainline {
+        None if parents > 1 => Err(git2::Error::from_str(&format!(
+            "commit {} is a merge, choose a mainline parent",
+            commit.id()
+        ))),
+        Some(m) if m == 0 || m > parents => Err(git2::Error::from_str(&format!(
+            "mainline {} is out of range for commit {}",
+            m,
+            commit.id()
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn commit_index(
+    repo: &Repository,
+    index: &mut Index,
+    author: &Signature,
+    committer: &Signature,
+    message: &str,
+    parents: &[&Commit],
+) -> Result<Oid, git2::Error> {
+    let tree = repo.find_tree(index.write_tree()?)?;
+    repo.commit(Some("HEAD"), author, committer, message, &tree, parents)
+}
@@ -0,0 +1,652 @@
+use std::path::PathBuf;
+
+use git2::{Commit, ErrorCode, Oid, Rebase, Repository, RepositoryState, Sort, Status};
+
+use crate::operations::conflicted_paths;
+
+/// git and libgit2 keep rebase state here. Interactive rebases use the same files as
+/// `git rebase -i`, so git sees one that gpig started and can abort it. Only gpig can
+/// continue it: new and folded messages are kept in gpig's own files.
+const STATE_DIR: &str = "rebase-merge";
+const TODO_FILE: &str = "git-rebase-todo";
+const DONE_FILE: &str = "done";
+/// Names the commit a rebase stopped on, next to `.git/HEAD`.
+const REBASE_HEAD: &str = "REBASE_HEAD";
+/// gpig's own files: the commit a pick and its squashes and fixups are folded onto,
+/// the commit whose author the result keeps, and the message it gets.
+const GROUP_BASE_FILE: &str = "gpig-group-base";
+const GROUP_COMMIT_FILE: &str = "gpig-group-commit";
+const GROUP_MESSAGE_FILE: &str = "gpig-group-message";
+const GROUP_FILES: [&str; 3] = [GROUP_BASE_FILE, GROUP_COMMIT_FILE, GROUP_MESSAGE_FILE];
+/// Suffix of the copies of the group files taken before a step, put back when it is
+/// skipped.
+const PREVIOUS_SUFFIX: &str = ".previous";
+
+/// How far a rebase has got, 1-based.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RebaseProgress {
+    pub current: usize,
+    pub total: usize,
+}
+
+impl RebaseProgress {
+    pub fn label(&self) -> String {
+        format!("applying {}/{}", self.current, self.total)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RebaseAction {
+    Pick,
+    Reword,
+    Squash,
+    Fixup,
+    Drop,
+}
+
+impl RebaseAction {
+    pub const ALL: [RebaseAction; 5] = [
+        RebaseAction::Pick,
+        RebaseAction::Reword,
+        RebaseAction::Squash,
+        RebaseAction::Fixup,
+        RebaseAction::Drop,
+    ];
+
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            RebaseAction::Pick => "pick",
+            RebaseAction::Reword => "reword",
+            RebaseAction::Squash => "squash",
+            RebaseAction::Fixup => "fixup",
+            RebaseAction::Drop => "drop",
+        }
+    }
+
+    fn from_keyword(keyword: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|action| action.keyword() == keyword)
+    }
+}
+
+/// One line of an interactive rebase plan.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RebaseStep {
+    pub action: RebaseAction,
+    pub oid: Oid,
+    pub summary: String,
+    /// The full commit message; used as the new message when rewording.
+    pub message: String,
+}
+
+impl RebaseStep {
+    /// Give the commit `summary` as its new first paragraph, keeping the body.
+    pub fn reword(&mut self, summary: &str) {
+        let summary = summary.trim();
+        let body = self
+            .message
+            .trim_start()
+            .split_once("\n\n")
+            .map(|(_, body)| body.trim())
+            .unwrap_or_default();
+        self.message = if body.is_empty() {
+            format!("{}\n", summary)
+        } else {
+            format!("{}\n\n{}\n", summary, body)
+        };
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RebaseOutcome {
+    /// Every commit was applied; HEAD now points at this commit.
+    Finished { head: Oid, applied: usize },
+    /// A commit did not apply cleanly and waits for the user.
+    Stopped {
+        progress: RebaseProgress,
+        paths: Vec<String>,
+    },
+}
+
+/// Replay the commits of the current branch that are not in `onto` on top of it,
+/// reporting each commit before it is applied.
+pub fn rebase_onto(
+    repo: &Repository,
+    onto: Oid,
+    progress: &mut dyn FnMut(RebaseProgress),
+) -> Result<RebaseOutcome, git2::Error> {
+    let upstream = repo.find_annotated_commit(onto)?;
+    let mut rebase = repo.rebase(None, Some(&upstream), None, None)?;
+    run(repo, &mut rebase, progress)
+}
+
+/// Commit the resolved index of the stopped commit and apply the rest.
+pub fn continue_rebase(
+    repo: &Repository,
+    progress: &mut dyn FnMut(RebaseProgress),
+) -> Result<RebaseOutcome, git2::Error> {
+    if repo.index()?.has_conflicts() {
+        return Err(git2::Error::from_str(
+            "resolve all conflicts before continuing",
+        ));
+    }
+    if is_interactive(repo) {
+        return continue_interactive(repo, progress);
+    }
+    let mut rebase = repo.open_rebase(None)?;
+    commit_current(repo, &mut rebase)?;
+    run(repo, &mut rebase, progress)
+}
+
+/// Drop the stopped commit and apply the rest.
+pub fn skip_rebase(
+    repo: &Repository,
+    progress: &mut dyn FnMut(RebaseProgress),
+) -> Result<RebaseOutcome, git2::Error> {
+    if is_interactive(repo) {
+        return skip_interactive(repo, progress);
+    }
+    let mut rebase = repo.open_rebase(None)?;
+    discard_to_head(repo)?;
+    run(repo, &mut rebase, progress)
+}
+
+/// Put the branch back where it was before the rebase started.
+pub fn abort_rebase(repo: &Repository) -> Result<(), git2::Error> {
+    if is_interactive(repo) {
+        return abort_interactive(repo);
+    }
+    repo.open_rebase(None)?.abort()
+}
+
+pub fn rebase_progress(repo: &Repository) -> Result<Option<RebaseProgress>, git2::Error> {
+    if is_interactive(repo) {
+        return interactive_progress(repo).map(Some);
+    }
+    let mut rebase = match repo.open_rebase(None) {
+        Ok(rebase) => rebase,
+        Err(e) if e.code() == ErrorCode::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    Ok(progress_of(&mut rebase))
+}
+
+fn progress_of(rebase: &mut Rebase) -> Option<RebaseProgress> {
+    rebase.operation_current().map(|current| RebaseProgress {
+        current: current + 1,
+        total: rebase.len(),
+    })
+}
+
+/// Throw away the half-applied commit. Unlike a hard reset this keeps the
+/// rebase state directory.
+fn discard_to_head(repo: &Repository) -> Result<(), git2::Error> {
+    let head = repo.head()?.peel_to_commit()?;
+    let mut checkout = git2::build::CheckoutBuilder::new();
+    checkout.force();
+    repo.checkout_tree(head.as_object(), Some(&mut checkout))?;
+    let mut index = repo.index()?;
+    index.read_tree(&head.tree()?)?;
+    index.write()
+}
+
+fn run(
+    repo: &Repository,
+    rebase: &mut Rebase,
+    progress: &mut dyn FnMut(RebaseProgress),
+) -> Result<RebaseOutcome, git2::Error> {
+    while let Some(operation) = rebase.next() {
+        operation?;
+        let current = progress_of(rebase)
+            .ok_or_else(|| git2::Error::from_str("the rebase lost track of its current commit"))?;
+        progress(current);
+        let index = repo.index()?;
+        if index.has_conflicts() {
+            return Ok(RebaseOutcome::Stopped {
+                progress: current,
+                paths: conflicted_paths(&index)?,
+            });
+        }
+        commit_current(repo, rebase)?;
+    }
+
+    let onto = read_onto(repo)?;
+    let signature = repo.signature()?;
+    rebase.finish(Some(&signature))?;
+    let head = repo.head()?.peel_to_commit()?.id();
+    Ok(RebaseOutcome::Finished {
+        head,
+        applied: count_new(repo, onto, head)?,
+    })
+}
+
+/// Commits on `head` that are not on `onto`. Skipped and already upstream commits
+/// leave nothing behind, so this is what a rebase actually applied.
+fn count_new(repo: &Repository, onto: Oid, head: Oid) -> Result<usize, git2::Error> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(head)?;
+    revwalk.hide(onto)?;
+    Ok(revwalk.count())
+}
+
+fn commit_current(repo: &Repository, rebase: &mut Rebase) -> Result<(), git2::Error> {
+    let signature = repo.signature()?;
+    match rebase.commit(None, &signature, None) {
+        Ok(_) => Ok(()),
+        // the change is already upstream, nothing left to commit
+        Err(e) if e.code() == ErrorCode::Applied => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// The commits between `base` and HEAD, oldest first, all set to pick.
+pub fn plan_rebase(repo: &Repository, base: Oid) -> Result<Vec<RebaseStep>, git2::Error> {
+    let head = repo.head()?.peel_to_commit()?;
+    if head.id() != base && !repo.graph_descendant_of(head.id(), base)? {
+        return Err(git2::Error::from_str(
+            "the base commit is not part of the current branch",
+        ));
+    }
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+    revwalk.push(head.id())?;
+    revwalk.hide(base)?;
+
+    let mut steps = Vec::new();
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        if commit.parent_count() > 1 {
+            return Err(git2::Error::from_str(&format!(
+                "commit {} is a merge and cannot be rebased interactively",
+                commit.id()
+            )));
+        }
+        steps.push(RebaseStep {
+            action: RebaseAction::Pick,
+            oid: commit.id(),
+            summary: commit.summary().unwrap_or_default().to_string(),
+            message: commit.message().unwrap_or_default().to_string(),
+        });
+    }
+    Ok(steps)
+}
+
+/// Check out `base` and apply `steps` in order on top of it, reporting each step
+/// before it is applied.
+pub fn start_interactive_rebase(
+    repo: &Repository,
+    base: Oid,
+    steps: &[RebaseStep],
+    progress: &mut dyn FnMut(RebaseProgress),
+) -> Result<RebaseOutcome, git2::Error> {
+    if repo.state() != RepositoryState::Clean {
+        return Err(git2::Error::from_str(
+            "finish the operation in progress before rebasing",
+        ));
+    }
+    if let Some(step) = steps.iter().find(|step| step.action != RebaseAction::Drop)
+        && matches!(step.action, RebaseAction::Squash | RebaseAction::Fixup)
+    {
+        return Err(git2::Error::from_str(
+            "the first commit cannot be squashed or fixed up, there is nothing to fold it into",
+        ));
+    }
+    if steps.iter().any(|step| {
+        step.action == RebaseAction::Reword
+            && step
+                .message
+                .lines()
+                .next()
+                .is_none_or(|summary| summary.trim().is_empty())
+    }) {
+        return Err(git2::Error::from_str("reworded commits need a summary"));
+    }
+    let dirty = repo.statuses(None)?.iter().any(|entry| {
+        !entry.status().is_empty() && !entry.status().intersects(Status::WT_NEW | Status::IGNORED)
+    });
+    if dirty {
+        return Err(git2::Error::from_str(
+            "commit or stash your changes before rebasing",
+        ));
+    }
+
+    let head = repo.head()?;
+    let head_name = match head.name() {
+        Some(name) if head.is_branch() => name.to_string(),
+        _ => "detached HEAD".to_string(),
+    };
+    let orig_head = head.peel_to_commit()?.id();
+
+    let dir = state_dir(repo);
+    std::fs::create_dir_all(&dir).map_err(io_error)?;
+    write_state(repo, "head-name", &format!("{}\n", head_name))?;
+    write_state(repo, "onto", &format!("{}\n", base))?;
+    write_state(repo, "orig-head", &format!("{}\n", orig_head))?;
+    write_state(repo, "interactive", "")?;
+    write_state(repo, DONE_FILE, "")?;
+    let mut todo = String::new();
+    for step in steps {
+        todo.push_str(&format!(
+            "{} {} {}\n",
+            step.action.keyword(),
+            step.oid,
+            step.summary
+        ));
+        if step.action == RebaseAction::Reword {
+            write_state(repo, &format!("message-{}", step.oid), &step.message)?;
+        }
+    }
+    write_state(repo, TODO_FILE, &todo)?;
+    repo.reference("ORIG_HEAD", orig_head, true, "rebase (start): ORIG_HEAD")?;
+
+    let checkout = repo
+        .find_object(base, None)
+        .and_then(|target| {
+            let mut checkout = git2::build::CheckoutBuilder::new();
+            checkout.safe();
+            repo.checkout_tree(&target, Some(&mut checkout))
+        })
+        .and_then(|()| repo.set_head_detached(base));
+    if let Err(e) = checkout {
+        let _ = std::fs::remove_dir_all(&dir);
+        return Err(e);
+    }
+
+    run_interactive(repo, progress)
+}
+
+fn is_interactive(repo: &Repository) -> bool {
+    state_dir(repo).join("interactive").exists()
+}
+
+fn state_dir(repo: &Repository) -> PathBuf {
+    repo.path().join(STATE_DIR)
+}
+
+fn io_error(e: std::io::Error) -> git2::Error {
+    git2::Error::from_str(&e.to_string())
+}
+
+fn read_state(repo: &Repository, name: &str) -> Result<String, git2::Error> {
+    std::fs::read_to_string(state_dir(repo).join(name)).map_err(io_error)
+}
+
+fn write_state(repo: &Repository, name: &str, content: &str) -> Result<(), git2::Error> {
+    std::fs::write(state_dir(repo).join(name), content).map_err(io_error)
+}
+
+fn read_oid(repo: &Repository, name: &str) -> Result<Oid, git2::Error> {
+    Oid::from_str(read_state(repo, name)?.trim())
+}
+
+fn read_onto(repo: &Repository) -> Result<Oid, git2::Error> {
+    read_oid(repo, "onto")
+}
+
+/// Lines of a todo or done file that name a command, without git's comments.
+fn command_lines(text: &str) -> impl Iterator<Item = &str> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+fn parse_step(repo: &Repository, line: &str) -> Result<RebaseStep, git2::Error> {
+    let mut parts = line.splitn(3, ' ');
+    let action = parts
+        .next()
+        .and_then(RebaseAction::from_keyword)
+        .ok_or_else(|| {
+            git2::Error::from_str(&format!(
+                "'{}' is not a rebase command gpig understands, continue it with git",
+                line
+            ))
+        })?;
+    let oid = Oid::from_str(parts.next().unwrap_or_default())?;
+    let message = match action {
+        RebaseAction::Reword => read_state(repo, &format!("message-{}", oid))?,
+        _ => String::new(),
+    };
+    Ok(RebaseStep {
+        action,
+        oid,
+        summary: parts.next().unwrap_or_default().to_string(),
+        message,
+    })
+}
+
+fn interactive_progress(repo: &Repository) -> Result<RebaseProgress, git2::Error> {
+    let done = command_lines(&read_state(repo, DONE_FILE)?).count();
+    let todo = command_lines(&read_state(repo, TODO_FILE)?).count();
+    Ok(RebaseProgress {
+        current: done.max(1).min(done + todo),
+        total: done + todo,
+    })
+}
+
+/// Move the first todo line over to the done list before it is applied, as git does.
+fn next_step(repo: &Repository) -> Result<Option<RebaseStep>, git2::Error> {
+    let todo = read_state(repo, TODO_FILE)?;
+    let mut lines = command_lines(&todo);
+    let Some(current) = lines.next() else {
+        return Ok(None);
+    };
+    let step = parse_step(repo, current)?;
+    let rest: String = lines.map(|line| format!("{}\n", line)).collect();
+    let done = read_state(repo, DONE_FILE)?;
+    write_state(repo, DONE_FILE, &format!("{}{}\n", done, current))?;
+    write_state(repo, TODO_FILE, &rest)?;
+
+    let done = command_lines(&read_state(repo, DONE_FILE)?).count();
+    write_state(repo, "msgnum", &format!("{}\n", done))?;
+    write_state(
+        repo,
+        "end",
+        &format!("{}\n", done + command_lines(&rest).count()),
+    )?;
+    Ok(Some(step))
+}
+
+fn run_interactive(
+    repo: &Repository,
+    progress: &mut dyn FnMut(RebaseProgress),
+) -> Result<RebaseOutcome, git2::Error> {
+    while let Some(step) = next_step(repo)? {
+        progress(interactive_progress(repo)?);
+        if step.action == RebaseAction::Drop {
+            continue;
+        }
+
+        let commit = repo.find_commit(step.oid)?;
+        save_group(repo)?;
+        start_group(repo, &step, &commit)?;
+        repo.cherrypick(&commit, None)?;
+        // the rebase, not a cherry-pick, owns the stopped state
+        let _ = std::fs::remove_file(repo.path().join("CHERRY_PICK_HEAD"));
+        let _ = repo.remove_message();
+
+        let index = repo.index()?;
+        if index.has_conflicts() {
+            record_stop(repo, &commit)?;
+            return Ok(RebaseOutcome::Stopped {
+                progress: interactive_progress(repo)?,
+                paths: conflicted_paths(&index)?,
+            });
+        }
+        commit_group(repo)?;
+    }
+    finish_interactive(repo)
+}
+
+/// Copy the group files aside, so skipping the step about to start can undo what it
+/// did to the group.
+fn save_group(repo: &Repository) -> Result<(), git2::Error> {
+    for name in GROUP_FILES {
+        let previous = format!("{}{}", name, PREVIOUS_SUFFIX);
+        match read_state(repo, name) {
+            Ok(content) => write_state(repo, &previous, &content)?,
+            Err(_) => {
+                let _ = std::fs::remove_file(state_dir(repo).join(previous));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Put the group files back as they were before the stopped step.
+fn restore_group(repo: &Repository) -> Result<(), git2::Error> {
+    for name in GROUP_FILES {
+        match read_state(repo, &format!("{}{}", name, PREVIOUS_SUFFIX)) {
+            Ok(content) => write_state(repo, name, &content)?,
+            Err(_) => {
+                let _ = std::fs::remove_file(state_dir(repo).join(name));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A pick or reword starts a new commit on HEAD; squashes and fixups fold into the
+/// commit the last one started, even when that one turned out to be empty. With no
+/// commit to fold into, because every earlier one was skipped, they start one too.
+fn start_group(repo: &Repository, step: &RebaseStep, commit: &Commit) -> Result<(), git2::Error> {
+    let message = commit.message().unwrap_or_default();
+    let starts = match step.action {
+        RebaseAction::Pick | RebaseAction::Reword => true,
+        RebaseAction::Squash | RebaseAction::Fixup => {
+            !state_dir(repo).join(GROUP_COMMIT_FILE).exists()
+        }
+        RebaseAction::Drop => false,
+    };
+    if starts {
+        let head = repo.head()?.peel_to_commit()?.id();
+        write_state(repo, GROUP_BASE_FILE, &head.to_string())?;
+        write_state(repo, GROUP_COMMIT_FILE, &commit.id().to_string())?;
+        let message = match step.action {
+            RebaseAction::Reword => step.message.as_str(),
+            _ => message,
+        };
+        return write_state(repo, GROUP_MESSAGE_FILE, message);
+    }
+    if step.action == RebaseAction::Squash {
+        let folded = read_state(repo, GROUP_MESSAGE_FILE)?;
+        write_state(
+            repo,
+            GROUP_MESSAGE_FILE,
+            &format!("{}\n\n{}", folded.trim_end(), message),
+        )?;
+    }
+    Ok(())
+}
+
+/// Record where the rebase stopped the way git does, so git shows the stopped commit.
+fn record_stop(repo: &Repository, commit: &Commit) -> Result<(), git2::Error> {
+    let group = repo.find_commit(read_oid(repo, GROUP_COMMIT_FILE)?)?;
+    let author = group.author();
+    let quote = |value: &str| format!("'{}'", value.replace('\'', "'\\''"));
+    let time = author.when();
+    let offset = time.offset_minutes();
+    write_state(
+        repo,
+        "author-script",
+        &format!(
+            "GIT_AUTHOR_NAME={}\nGIT_AUTHOR_EMAIL={}\nGIT_AUTHOR_DATE={}\n",
+            quote(&String::from_utf8_lossy(author.name_bytes())),
+            quote(&String::from_utf8_lossy(author.email_bytes())),
+            quote(&format!(
+                "@{} {}{:02}{:02}",
+                time.seconds(),
+                if offset < 0 { '-' } else { '+' },
+                offset.abs() / 60,
+                offset.abs() % 60
+            )),
+        ),
+    )?;
+    write_state(repo, "message", &read_state(repo, GROUP_MESSAGE_FILE)?)?;
+    write_state(repo, "stopped-sha", &format!("{}\n", commit.id()))?;
+    std::fs::write(repo.path().join(REBASE_HEAD), format!("{}\n", commit.id())).map_err(io_error)
+}
+
+fn clear_stop(repo: &Repository) {
+    for name in ["author-script", "message", "stopped-sha"] {
+        let _ = std::fs::remove_file(state_dir(repo).join(name));
+    }
+    let _ = std::fs::remove_file(repo.path().join(REBASE_HEAD));
+}
+
+/// Commit the index as the result of the current group, replacing the commit an
+/// earlier step of the group made. A group whose changes are all upstream leaves
+/// HEAD on its base.
+fn commit_group(repo: &Repository) -> Result<(), git2::Error> {
+    let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+    let base = repo.find_commit(read_oid(repo, GROUP_BASE_FILE)?)?;
+    if tree.id() == base.tree_id() {
+        return repo.set_head_detached(base.id());
+    }
+
+    let group = repo.find_commit(read_oid(repo, GROUP_COMMIT_FILE)?)?;
+    let author = group.author();
+    let committer = repo.signature()?;
+    let message = read_state(repo, GROUP_MESSAGE_FILE)?;
+    let oid = repo.commit(None, &author, &committer, &message, &tree, &[&base])?;
+    repo.set_head_detached(oid)
+}
+
+fn finish_interactive(repo: &Repository) -> Result<RebaseOutcome, git2::Error> {
+    let head = repo.head()?.peel_to_commit()?.id();
+    let head_name = read_state(repo, "head-name")?.trim().to_string();
+    let applied = count_new(repo, read_onto(repo)?, head)?;
+
+    if head_name.starts_with("refs/") {
+        repo.reference(
+            &head_name,
+            head,
+            true,
+            &format!("rebase (finish): {} onto {}", head_name, head),
+        )?;
+        repo.set_head(&head_name)?;
+    }
+    clear_stop(repo);
+    std::fs::remove_dir_all(state_dir(repo)).map_err(io_error)?;
+
+    Ok(RebaseOutcome::Finished { head, applied })
+}
+
+fn continue_interactive(
+    repo: &Repository,
+    progress: &mut dyn FnMut(RebaseProgress),
+) -> Result<RebaseOutcome, git2::Error> {
+    if repo.path().join(REBASE_HEAD).exists() {
+        commit_group(repo)?;
+        clear_stop(repo);
+    }
+    run_interactive(repo, progress)
+}
+
+fn skip_interactive(
+    repo: &Repository,
+    progress: &mut dyn FnMut(RebaseProgress),
+) -> Result<RebaseOutcome, git2::Error> {
+    discard_to_head(repo)?;
+    // the skipped commit leaves no trace in the group it started or folded into
+    if repo.path().join(REBASE_HEAD).exists() {
+        restore_group(repo)?;
+    }
+    clear_stop(repo);
+    run_interactive(repo, progress)
+}
+
+fn abort_interactive(repo: &Repository) -> Result<(), git2::Error> {
+    let orig_head = read_oid(repo, "orig-head")?;
+    let head_name = read_state(repo, "head-name")?.trim().to_string();
+
+    let target = repo.find_object(orig_head, None)?;
+    repo.reset(&target, git2::ResetType::Hard, None)?;
+    if head_name.starts_with("refs/") {
+        repo.set_head(&head_name)?;
+    }
+    clear_stop(repo);
+    let _ = std::fs::remove_dir_all(state_dir(repo));
+    Ok(())
+}
@@ -0,0 +1,329 @@
+use git2::Oid;
+use gpui::prelude::*;
+use gpui::{
+    AnyElement, Context, Entity, EventEmitter, IntoElement, MouseButton, Render, SharedString,
+    Window, div, px,
+};
+
+use crate::modal::{BUTTON_NEUTRAL, BUTTON_PRIMARY, modal_button};
+use crate::rebase::{RebaseAction, RebaseStep};
+use crate::text_input::TextInput;
+
+#[derive(Clone)]
+pub enum RebasePlannerEvent {
+    Start { base: Oid, steps: Vec<RebaseStep> },
+    Cancel,
+}
+
+/// The row being dragged, drawn under the cursor while reordering.
+#[derive(Clone)]
+struct DraggedStep {
+    index: usize,
+    label: SharedString,
+}
+
+impl Render for DraggedStep {
+    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .px(px(12.0))
+            .py(px(6.0))
+            .bg(gpui::rgb(0x2A2A2A))
+            .border_1()
+            .border_color(gpui::rgb(0x4A90D9))
+            .rounded(px(4.0))
+            .text_color(gpui::white())
+            .text_size(px(12.0))
+            .child(self.label.clone())
+    }
+}
+
+struct PlannedStep {
+    step: RebaseStep,
+    /// Only the summary line is edited; the body of the message is kept.
+    summary: Entity<TextInput>,
+}
+
+/// Editable todo list for an interactive rebase, oldest commit first.
+pub struct RebasePlanner {
+    base: Oid,
+    steps: Vec<PlannedStep>,
+    error: Option<String>,
+}
+
+impl EventEmitter<RebasePlannerEvent> for RebasePlanner {}
+
+impl RebasePlanner {
+    pub fn new(base: Oid, steps: Vec<RebaseStep>, cx: &mut Context<Self>) -> Self {
+        let steps = steps
+            .into_iter()
+            .map(|step| {
+                let summary = cx.new(|cx| {
+                    let mut input = TextInput::new("New summary line", cx);
+                    input.set_text(step.summary.clone(), cx);
+                    input
+                });
+                PlannedStep { step, summary }
+            })
+            .collect();
+
+        Self {
+            base,
+            steps,
+            error: None,
+        }
+    }
+
+    pub fn set_error(&mut self, error: impl Into<String>, cx: &mut Context<Self>) {
+        self.error = Some(error.into());
+        cx.notify();
+    }
+
+    fn set_action(&mut self, index: usize, action: RebaseAction, cx: &mut Context<Self>) {
+        if let Some(planned) = self.steps.get_mut(index) {
+            planned.step.action = action;
+        }
+        self.error = None;
+        cx.notify();
+    }
+
+    fn move_step(&mut self, from: usize, to: usize, cx: &mut Context<Self>) {
+        if from == to || from >= self.steps.len() || to >= self.steps.len() {
+            return;
+        }
+        let planned = self.steps.remove(from);
+        self.steps.insert(to, planned);
+        self.error = None;
+        cx.notify();
+    }
+
+    fn start(&mut self, cx: &mut Context<Self>) {
+        let steps = self
+            .steps
+            .iter()
+            .map(|planned| {
+                let mut step = planned.step.clone();
+                if step.action == RebaseAction::Reword {
+                    step.reword(&planned.summary.read(cx).text());
+                }
+                step
+            })
+            .collect();
+        cx.emit(RebasePlannerEvent::Start {
+            base: self.base,
+            steps,
+        });
+    }
+
+    fn action_color(action: RebaseAction) -> u32 {
+        match action {
+            RebaseAction::Pick => 0x2ECC71,
+            RebaseAction::Reword => 0x4A90D9,
+            RebaseAction::Squash => 0x9B59B6,
+            RebaseAction::Fixup => 0xF39C12,
+            RebaseAction::Drop => 0xE74C3C,
+        }
+    }
+
+    fn render_step(
+        &self,
+        index: usize,
+        planned: &PlannedStep,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let step = &planned.step;
+        let short_oid = step.oid.to_string()[..7].to_string();
+        let dragged = DraggedStep {
+            index,
+            label: format!("{} {}", short_oid, step.summary).into(),
+        };
+        let dropped = step.action == RebaseAction::Drop;
+
+        let actions = RebaseAction::ALL.into_iter().map(|action| {
+            let selected = step.action == action;
+            div()
+                .id(SharedString::from(format!(
+                    "rebase_step_{}_{}",
+                    index,
+                    action.keyword()
+                )))
+                .px(px(6.0))
+                .py(px(2.0))
+                .rounded(px(4.0))
+                .text_size(px(11.0))
+                .cursor_pointer()
+                .when(selected, |this| {
+                    this.bg(gpui::rgb(Self::action_color(action)))
+                        .text_color(gpui::white())
+                })
+                .when(!selected, |this| {
+                    this.text_color(gpui::rgb(0x888888))
+                        .hover(|style| style.bg(gpui::rgb(0x333333)))
+                })
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |this, _event, _window, cx| {
+                        this.set_action(index, action, cx);
+                    }),
+                )
+                .child(action.keyword())
+        });
+
+        div()
+            .id(SharedString::from(format!("rebase_step_{}", index)))
+            .w_full()
+            .flex()
+            .flex_col()
+            .border_b_1()
+            .border_color(gpui::rgb(0x2A2A2A))
+            .on_drag(dragged, |dragged, _offset, _window, cx| {
+                cx.new(|_| dragged.clone())
+            })
+            .drag_over::<DraggedStep>(|style, _, _, _| style.bg(gpui::rgb(0x2A3A4A)))
+            .on_drop(
+                cx.listener(move |this, dragged: &DraggedStep, _window, cx| {
+                    this.move_step(dragged.index, index, cx);
+                }),
+            )
+            .child(
+                div()
+                    .w_full()
+                    .flex()
+                    .flex_row()
+                    .items_center()
+                    .gap_2()
+                    .px(px(12.0))
+                    .py(px(6.0))
+                    .child(
+                        div()
+                            .text_color(gpui::rgb(0x666666))
+                            .cursor_grab()
+                            .child("⋮⋮"),
+                    )
+                    .child(div().flex().flex_row().gap_1().children(actions))
+                    .child(
+                        div()
+                            .text_color(gpui::rgb(0x888888))
+                            .text_size(px(12.0))
+                            .font_family("monospace")
+                            .child(short_oid),
+                    )
+                    .child(
+                        div()
+                            .flex_1()
+                            .overflow_hidden()
+                            .whitespace_nowrap()
+                            .text_size(px(13.0))
+                            .text_color(gpui::rgb(if dropped { 0x666666 } else { 0xCCCCCC }))
+                            .when(dropped, |this| this.line_through())
+                            .child(step.summary.clone()),
+                    ),
+            )
+            .when(step.action == RebaseAction::Reword, |this| {
+                this.child(
+                    div()
+                        .pl(px(40.0))
+                        .pr(px(12.0))
+                        .pb(px(6.0))
+                        .child(planned.summary.clone()),
+                )
+            })
+            .into_any()
+    }
+}
+
+impl Render for RebasePlanner {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let title = format!(
+            "Interactive rebase of {} commit(s) onto {}",
+            self.steps.len(),
+            &self.base.to_string()[..7]
+        );
+        let rows = self
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(index, planned)| self.render_step(index, planned, cx))
+            .collect::<Vec<_>>();
+
+        div()
+            .size_full()
+            .flex()
+            .flex_col()
+            .bg(gpui::rgb(0x1E1E1E))
+            .child(
+                div()
+                    .w_full()
+                    .flex()
+                    .flex_row()
+                    .items_center()
+                    .justify_between()
+                    .px(px(12.0))
+                    .py(px(8.0))
+                    .border_b_1()
+                    .border_color(gpui::rgb(0x333333))
+                    .bg(gpui::rgb(0x252525))
+                    .child(
+                        div()
+                            .text_color(gpui::white())
+                            .font_weight(gpui::FontWeight::BOLD)
+                            .text_size(px(14.0))
+                            .child(title),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .flex_row()
+                            .gap_2()
+                            .child(
+                                modal_button("rebase_plan_cancel", "Cancel", BUTTON_NEUTRAL)
+                                    .on_mouse_down(
+                                        MouseButton::Left,
+                                        cx.listener(|_this, _event, _window, cx| {
+                                            cx.emit(RebasePlannerEvent::Cancel);
+                                        }),
+                                    ),
+                            )
+                            .child(
+                                modal_button("rebase_plan_start", "Start rebase", BUTTON_PRIMARY)
+                                    .on_mouse_down(
+                                        MouseButton::Left,
+                                        cx.listener(|this, _event, _window, cx| {
+                                            this.start(cx);
+                                        }),
+                                    ),
+                            ),
+                    ),
+            )
+            .child(
+                div()
+                    .px(px(12.0))
+                    .py(px(8.0))
+                    .text_color(gpui::rgb(0x888888))
+                    .text_size(px(12.0))
+                    .child(
+                        "Commits are applied top to bottom. Drag rows to reorder; squash and fixup fold a commit into the one above.",
+                    ),
+            )
+            .when_some(self.error.clone(), |this, error| {
+                this.child(
+                    div()
+                        .px(px(12.0))
+                        .pb(px(8.0))
+                        .text_color(gpui::rgb(0xE74C3C))
+                        .text_size(px(12.0))
+                        .child(error),
+                )
+            })
+            .child(
+                div()
+                    .id("rebase-plan-steps")
+                    .flex_1()
+                    .flex()
+                    .flex_col()
+                    .overflow_y_scroll()
+                    .border_t_1()
+                    .border_color(gpui::rgb(0x333333))
+                    .children(rows),
+            )
+    }
+}
@@ -0,0 +1,109 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Unpinned entries beyond this are dropped, oldest first.
+const MAX_UNPINNED: usize = 10;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecentRepo {
+    /// Working tree, or the repository itself when bare.
+    pub path: PathBuf,
+    #[serde(default)]
+    pub pinned: bool,
+    /// Unix seconds of the last open.
+    pub opened: i64,
+}
+
+impl RecentRepo {
+    /// Last path component, for the list.
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.path.display().to_string())
+    }
+}
+
+/// Recently opened repositories, kept in a per-user state file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecentRepos {
+    #[serde(default, rename = "repo")]
+    entries: Vec<RecentRepo>,
+}
+
+impl RecentRepos {
+    /// `recent.toml` in the user's state directory.
+    pub fn user_file() -> Option<PathBuf> {
+        dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .map(|dir| dir.join("gpig").join("recent.toml"))
+    }
+
+    /// An unreadable or missing file is an empty list.
+    pub fn load(file: &Path) -> Self {
+        std::fs::read_to_string(file)
+            .ok()
+            .and_then(|text| toml::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, file: &Path) -> std::io::Result<()> {
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let text = toml::to_string(self).map_err(std::io::Error::other)?;
+        std::fs::write(file, text)
+    }
+
+    /// Pinned first, then the most recently opened.
+    pub fn entries(&self) -> Vec<RecentRepo> {
+        let mut entries = self.entries.clone();
+        entries.sort_by(|a, b| b.pinned.cmp(&a.pinned).then(b.opened.cmp(&a.opened)));
+        entries
+    }
+
+    /// Move `path` to the top, keeping its pin.
+    pub fn record(&mut self, path: &Path, now: i64) {
+        match self.entries.iter_mut().find(|entry| entry.path == path) {
+            Some(entry) => entry.opened = now,
+            None => self.entries.push(RecentRepo {
+                path: path.to_path_buf(),
+                pinned: false,
+                opened: now,
+            }),
+        }
+        let mut unpinned: Vec<(i64, PathBuf)> = self
+            .entries
+            .iter()
+            .filter(|entry| !entry.pinned)
+            .map(|entry| (entry.opened, entry.path.clone()))
+            .collect();
+        if unpinned.len() > MAX_UNPINNED {
+            unpinned.sort();
+            let dropped: Vec<PathBuf> = unpinned[..unpinned.len() - MAX_UNPINNED]
+                .iter()
+                .map(|(_, path)| path.clone())
+                .collect();
+            self.entries
+                .retain(|entry| entry.pinned || !dropped.contains(&entry.path));
+        }
+    }
+
+    pub fn set_pinned(&mut self, path: &Path, pinned: bool) {
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.path == path) {
+            entry.pinned = pinned;
+        }
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.entries.retain(|entry| entry.path != path);
+    }
+
+    /// Drop entries whose folder is gone, returning how many.
+    pub fn prune_missing(&mut self) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|entry| entry.path.exists());
+        before - self.entries.len()
+    }
+}
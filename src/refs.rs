@@ -0,0 +1,213 @@
+use git2::{BranchType, Oid, Repository, Time};
+
+use crate::backend::Backend;
+use crate::error::GpigError;
+
+#[derive(Clone, Debug)]
+pub struct BranchInfo {
+    pub name: String,
+    pub oid: Oid,
+    pub is_head: bool,
+    pub upstream: Option<String>,
+    pub ahead: usize,
+    pub behind: usize,
+    pub last_commit: Time,
+}
+
+#[derive(Clone, Debug)]
+pub struct RemoteInfo {
+    pub name: String,
+    pub url: String,
+    /// Only set when pushes go somewhere other than `url`.
+    pub push_url: Option<String>,
+    pub branches: Vec<BranchInfo>,
+}
+
+#[derive(Clone, Debug)]
+pub struct TagInfo {
+    pub name: String,
+    pub oid: Oid,
+}
+
+#[derive(Clone, Debug)]
+pub struct StashInfo {
+    pub index: usize,
+    pub message: String,
+    pub oid: Oid,
+}
+
+/// Snapshot of every ref the sidebar shows, grouped by kind.
+#[derive(Clone, Debug, Default)]
+pub struct RepoRefs {
+    pub local_branches: Vec<BranchInfo>,
+    pub remotes: Vec<RemoteInfo>,
+    pub tags: Vec<TagInfo>,
+    pub stashes: Vec<StashInfo>,
+}
+
+impl RepoRefs {
+    pub fn load(repo: &mut Repository) -> Result<Self, git2::Error> {
+        let local_branches = Self::load_local_branches(repo)?;
+        let remotes = Self::load_remotes(repo)?;
+        let tags = Self::load_tags(repo)?;
+        let stashes = Self::load_stashes(repo)?;
+
+        Ok(Self {
+            local_branches,
+            remotes,
+            tags,
+            stashes,
+        })
+    }
+
+    /// The same snapshot read through a `Backend`, so it works without a repository on
+    /// disk.
+    pub fn from_backend(backend: &dyn Backend) -> Result<Self, GpigError> {
+        let mut tags: Vec<TagInfo> = backend
+            .tags()
+            .into_iter()
+            .map(|(name, oid)| TagInfo { name, oid })
+            .collect();
+        tags.sort_by(|a, b| a.name.cmp(&b.name));
+        let stashes = backend
+            .stashes()
+            .into_iter()
+            .map(|stash| StashInfo {
+                index: stash.index,
+                message: stash.message,
+                oid: stash.oid,
+            })
+            .collect();
+
+        Ok(Self {
+            local_branches: backend.branches()?,
+            remotes: backend.remotes()?,
+            tags,
+            stashes,
+        })
+    }
+
+    pub(crate) fn load_local_branches(repo: &Repository) -> Result<Vec<BranchInfo>, git2::Error> {
+        let mut branches = Vec::new();
+
+        for branch in repo.branches(Some(BranchType::Local))? {
+            let (branch, _) = branch?;
+            let Some(name) = branch.name()?.map(|n| n.to_string()) else {
+                continue;
+            };
+            // unborn or symbolic branches have no commit to point at
+            let Some(oid) = branch.get().target() else {
+                continue;
+            };
+            let last_commit = repo.find_commit(oid)?.time();
+
+            let (upstream, ahead, behind) = match branch.upstream() {
+                Ok(upstream) => {
+                    let upstream_name = upstream.name()?.map(|n| n.to_string());
+                    let (ahead, behind) = match upstream.get().target() {
+                        Some(upstream_oid) => repo.graph_ahead_behind(oid, upstream_oid)?,
+                        None => (0, 0),
+                    };
+                    (upstream_name, ahead, behind)
+                }
+                Err(_) => (None, 0, 0),
+            };
+
+            branches.push(BranchInfo {
+                name,
+                oid,
+                is_head: branch.is_head(),
+                upstream,
+                ahead,
+                behind,
+                last_commit,
+            });
+        }
+
+        branches.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(branches)
+    }
+
+    pub(crate) fn load_remotes(repo: &Repository) -> Result<Vec<RemoteInfo>, git2::Error> {
+        let mut remotes: Vec<RemoteInfo> = repo
+            .remotes()?
+            .iter()
+            .flatten()
+            .map(|name| {
+                let remote = repo.find_remote(name)?;
+                Ok(RemoteInfo {
+                    name: name.to_string(),
+                    url: remote.url().unwrap_or_default().to_string(),
+                    push_url: remote.pushurl().map(String::from),
+                    branches: Vec::new(),
+                })
+            })
+            .collect::<Result<_, git2::Error>>()?;
+
+        for branch in repo.branches(Some(BranchType::Remote))? {
+            let (branch, _) = branch?;
+            let Some(full_name) = branch.name()?.map(|n| n.to_string()) else {
+                continue;
+            };
+            // `origin/HEAD` is a symbolic ref, skip it
+            let Some(oid) = branch.get().target() else {
+                continue;
+            };
+            let Some(remote) = remotes
+                .iter_mut()
+                .find(|r| full_name.starts_with(&format!("{}/", r.name)))
+            else {
+                continue;
+            };
+            let last_commit = repo.find_commit(oid)?.time();
+
+            remote.branches.push(BranchInfo {
+                name: full_name[remote.name.len() + 1..].to_string(),
+                oid,
+                is_head: false,
+                upstream: None,
+                ahead: 0,
+                behind: 0,
+                last_commit,
+            });
+        }
+
+        for remote in &mut remotes {
+            remote.branches.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+        Ok(remotes)
+    }
+
+    fn load_tags(repo: &Repository) -> Result<Vec<TagInfo>, git2::Error> {
+        let mut tags = Vec::new();
+
+        for name in repo.tag_names(None)?.iter().flatten() {
+            let reference = repo.find_reference(&format!("refs/tags/{}", name))?;
+            // annotated tags point at a tag object, peel down to the commit
+            let Ok(commit) = reference.peel_to_commit() else {
+                continue;
+            };
+            tags.push(TagInfo {
+                name: name.to_string(),
+                oid: commit.id(),
+            });
+        }
+
+        Ok(tags)
+    }
+
+    fn load_stashes(repo: &mut Repository) -> Result<Vec<StashInfo>, git2::Error> {
+        let mut stashes = Vec::new();
+
+        repo.stash_foreach(|index, message, oid| {
+            stashes.push(StashInfo {
+                index,
+                message: message.to_string(),
+                oid: *oid,
+            });
+            true
+        })?;
+
+        Ok(stashes)
+    }
+}
@@ -0,0 +1,506 @@
+use std::path::PathBuf;
+
+use git2::build::RepoBuilder;
+use git2::{
+    BranchType, Config, ErrorCode, FetchOptions, FetchPrune, Oid, PushOptions, RemoteCallbacks,
+    Repository, SubmoduleUpdateOptions,
+};
+
+use crate::credentials::CredentialChain;
+use crate::operations::{self, MergeMode, OperationOutcome};
+use crate::rebase::{self, RebaseOutcome, RebaseProgress};
+
+/// A network operation started from the UI.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SyncAction {
+    /// Fetch one remote, or all of them.
+    Fetch(Option<String>),
+    Pull,
+    Push,
+    /// Delete remote-tracking branches that no longer exist on the remote.
+    Prune(String),
+    Clone(CloneOptions),
+}
+
+impl SyncAction {
+    pub fn label(&self) -> String {
+        match self {
+            SyncAction::Fetch(Some(remote)) => format!("Fetching {}", remote),
+            SyncAction::Fetch(None) => "Fetching all remotes".to_string(),
+            SyncAction::Pull => "Pulling".to_string(),
+            SyncAction::Push => "Pushing".to_string(),
+            SyncAction::Prune(remote) => format!("Pruning {}", remote),
+            SyncAction::Clone(options) => format!("Cloning {}", options.url),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TransferProgress {
+    pub sending: bool,
+    pub objects: usize,
+    pub total_objects: usize,
+    pub bytes: usize,
+}
+
+impl TransferProgress {
+    pub fn label(&self) -> String {
+        let verb = if self.sending { "Sending" } else { "Receiving" };
+        format!(
+            "{} objects {}/{}, {}",
+            verb,
+            self.objects,
+            self.total_objects,
+            format_bytes(self.bytes)
+        )
+    }
+}
+
+fn format_bytes(bytes: usize) -> String {
+    match bytes {
+        b if b >= 1 << 20 => format!("{:.1} MiB", b as f64 / (1 << 20) as f64),
+        b if b >= 1 << 10 => format!("{:.1} KiB", b as f64 / (1 << 10) as f64),
+        b => format!("{} B", b),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PullOutcome {
+    UpToDate,
+    FastForwarded(Oid),
+    Merged(OperationOutcome),
+    Rebased(RebaseOutcome),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PushOutcome {
+    pub remote: String,
+    /// The branch pushed to on the remote.
+    pub branch: String,
+    /// The branch had no upstream and now tracks the pushed branch.
+    pub set_upstream: bool,
+}
+
+/// What the clone dialog collected.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CloneOptions {
+    pub url: String,
+    /// The folder to create; it must not exist or be empty.
+    pub destination: PathBuf,
+    /// Check out this branch instead of the remote's default branch.
+    pub branch: Option<String>,
+    /// Fetch only this many commits of history.
+    pub depth: Option<u32>,
+    /// Also clone the submodules, and theirs.
+    pub recursive: bool,
+}
+
+/// How `pull` integrates the upstream branch, from `pull.rebase`, `branch.<name>.rebase`
+/// and `pull.ff`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PullMode {
+    FastForwardOnly,
+    /// Fast-forward when possible, merge otherwise.
+    Merge,
+    /// Always create a merge commit, as with `pull.ff=false`.
+    NoFastForward,
+    Rebase,
+}
+
+pub fn pull_mode(repo: &Repository, branch: &str) -> Result<PullMode, git2::Error> {
+    let config = repo.config()?.snapshot()?;
+    let rebase = match rebase_setting(&config, &format!("branch.{}.rebase", branch))? {
+        Some(rebase) => rebase,
+        None => rebase_setting(&config, "pull.rebase")?.unwrap_or(false),
+    };
+    if rebase {
+        return Ok(PullMode::Rebase);
+    }
+    match config.get_str("pull.ff") {
+        Ok("only") => Ok(PullMode::FastForwardOnly),
+        Ok(_) if !config.get_bool("pull.ff")? => Ok(PullMode::NoFastForward),
+        _ => Ok(PullMode::Merge),
+    }
+}
+
+/// Read a `pull.rebase` style value: a boolean, or `interactive`, which gpig runs as a
+/// plain rebase since the plan is unchanged. Rebasing merges is left to git.
+fn rebase_setting(config: &Config, key: &str) -> Result<Option<bool>, git2::Error> {
+    match config.get_str(key) {
+        Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e),
+        Ok("interactive" | "i") => Ok(Some(true)),
+        Ok(value @ ("merges" | "m" | "preserve" | "p")) => Err(git2::Error::from_str(&format!(
+            "{} = {} is not supported, pull with git instead",
+            key, value
+        ))),
+        Ok(_) => config.get_bool(key).map(Some),
+    }
+}
+
+/// Fetch `remote`, or every configured remote. Returns the names fetched.
+pub fn fetch(
+    repo: &Repository,
+    remote: Option<&str>,
+    progress: &mut dyn FnMut(TransferProgress),
+    credentials: &mut CredentialChain,
+) -> Result<Vec<String>, git2::Error> {
+    let config = repo.config()?;
+    let names: Vec<String> = match remote {
+        Some(name) => vec![name.to_string()],
+        None => repo.remotes()?.iter().flatten().map(String::from).collect(),
+    };
+
+    for name in &names {
+        let mut remote = repo.find_remote(name)?;
+        let mut options = fetch_options(&config, progress, credentials);
+        remote.fetch(&[] as &[&str], Some(&mut options), None)?;
+    }
+    Ok(names)
+}
+
+/// Name of the remote the current branch tracks.
+pub fn upstream_remote(repo: &Repository) -> Result<String, git2::Error> {
+    let (refname, _) = current_branch(repo)?;
+    let remote = repo
+        .branch_upstream_remote(&refname)
+        .map_err(|_| no_upstream())?;
+    Ok(remote.as_str().unwrap_or_default().to_string())
+}
+
+/// Bring the current branch up to date with its already fetched upstream, reporting
+/// each commit a rebase applies.
+fn integrate_upstream(
+    repo: &Repository,
+    progress: &mut dyn FnMut(RebaseProgress),
+) -> Result<PullOutcome, git2::Error> {
+    let (refname, branch) = current_branch(repo)?;
+    let upstream = repo
+        .branch_upstream_name(&refname)
+        .map_err(|_| no_upstream())?;
+    let upstream = repo.refname_to_id(upstream.as_str().unwrap_or_default())?;
+
+    let annotated = repo.find_annotated_commit(upstream)?;
+    let (analysis, _) = repo.merge_analysis(&[&annotated])?;
+    if analysis.is_up_to_date() {
+        return Ok(PullOutcome::UpToDate);
+    }
+    let mode = pull_mode(repo, &branch)?;
+    if analysis.is_fast_forward() && mode != PullMode::NoFastForward {
+        operations::merge(repo, upstream, MergeMode::FastForwardOnly)?;
+        return Ok(PullOutcome::FastForwarded(upstream));
+    }
+
+    match mode {
+        PullMode::FastForwardOnly => Err(git2::Error::from_str(
+            "the branches have diverged and pull.ff is set to only",
+        )),
+        PullMode::Merge | PullMode::NoFastForward => Ok(PullOutcome::Merged(operations::merge(
+            repo,
+            upstream,
+            MergeMode::NoFastForward,
+        )?)),
+        PullMode::Rebase => Ok(PullOutcome::Rebased(rebase::rebase_onto(
+            repo, upstream, progress,
+        )?)),
+    }
+}
+
+/// Fetch the upstream remote, then integrate it; `rebase_progress` hears about each
+/// commit a rebasing pull replays.
+pub fn pull(
+    repo: &Repository,
+    progress: &mut dyn FnMut(TransferProgress),
+    rebase_progress: &mut dyn FnMut(RebaseProgress),
+    credentials: &mut CredentialChain,
+) -> Result<PullOutcome, git2::Error> {
+    let remote = upstream_remote(repo)?;
+    fetch(repo, Some(&remote), progress, credentials)?;
+    integrate_upstream(repo, rebase_progress)
+}
+
+/// Push the current branch to its upstream, or to the default remote under the same
+/// name, setting the upstream in that case.
+pub fn push(
+    repo: &Repository,
+    progress: &mut dyn FnMut(TransferProgress),
+    credentials: &mut CredentialChain,
+) -> Result<PushOutcome, git2::Error> {
+    let config = repo.config()?;
+    let (refname, branch) = current_branch(repo)?;
+    // the upstream may have another name than the local branch
+    let (remote_name, destination, set_upstream) = match repo.branch_upstream_remote(&refname) {
+        Ok(remote) => {
+            let merge = repo.branch_upstream_merge(&refname)?;
+            (
+                remote.as_str().unwrap_or_default().to_string(),
+                merge.as_str().unwrap_or_default().to_string(),
+                false,
+            )
+        }
+        Err(_) => (default_remote(repo)?, refname.clone(), true),
+    };
+    let remote_branch = destination
+        .strip_prefix("refs/heads/")
+        .unwrap_or(&destination)
+        .to_string();
+
+    let mut remote = repo.find_remote(&remote_name)?;
+    let mut rejection = None;
+    {
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.credentials(|url, username, allowed| {
+            credentials.credentials(&config, url, username, allowed)
+        });
+        callbacks.push_transfer_progress(|current, total, bytes| {
+            progress(TransferProgress {
+                sending: true,
+                objects: current,
+                total_objects: total,
+                bytes,
+            });
+        });
+        callbacks.push_update_reference(|_refname, status| {
+            if let Some(status) = status {
+                rejection = Some(status.to_string());
+            }
+            Ok(())
+        });
+        let mut options = PushOptions::new();
+        options.remote_callbacks(callbacks);
+        remote
+            .push(
+                &[format!("{}:{}", refname, destination)],
+                Some(&mut options),
+            )
+            .map_err(|e| explain_push_error(e, &remote_name, &remote_branch))?;
+    }
+    if let Some(status) = rejection {
+        return Err(explain_push_error(
+            git2::Error::from_str(&status),
+            &remote_name,
+            &remote_branch,
+        ));
+    }
+
+    if set_upstream {
+        repo.find_branch(&branch, BranchType::Local)?
+            .set_upstream(Some(&format!("{}/{}", remote_name, branch)))?;
+    }
+    Ok(PushOutcome {
+        remote: remote_name,
+        branch: remote_branch,
+        set_upstream,
+    })
+}
+
+pub fn add_remote(repo: &Repository, name: &str, url: &str) -> Result<(), git2::Error> {
+    validate_remote(name, url)?;
+    repo.remote(name, url)?;
+    Ok(())
+}
+
+pub fn rename_remote(repo: &Repository, name: &str, new_name: &str) -> Result<(), git2::Error> {
+    if name == new_name {
+        return Ok(());
+    }
+    validate_remote(new_name, "-")?;
+    let problems = repo.remote_rename(name, new_name)?;
+    match problems.iter().flatten().next() {
+        Some(refspec) => Err(git2::Error::from_str(&format!(
+            "renamed, but the refspec {} was left unchanged",
+            refspec
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Delete the remote along with its remote-tracking branches.
+pub fn remove_remote(repo: &Repository, name: &str) -> Result<(), git2::Error> {
+    repo.remote_delete(name)
+}
+
+/// Set the fetch URL, and a separate push URL or `None` to push to the fetch URL.
+pub fn set_remote_urls(
+    repo: &Repository,
+    name: &str,
+    url: &str,
+    push_url: Option<&str>,
+) -> Result<(), git2::Error> {
+    validate_remote(name, url)?;
+    repo.remote_set_url(name, url)?;
+    repo.remote_set_pushurl(name, push_url.filter(|url| !url.is_empty()))
+}
+
+/// Fetch `name` with pruning on and return the remote-tracking branches that went away.
+pub fn prune_remote(
+    repo: &Repository,
+    name: &str,
+    progress: &mut dyn FnMut(TransferProgress),
+    credentials: &mut CredentialChain,
+) -> Result<Vec<String>, git2::Error> {
+    let before = tracking_branches(repo, name)?;
+    let config = repo.config()?;
+    let mut remote = repo.find_remote(name)?;
+    let mut options = fetch_options(&config, progress, credentials);
+    options.prune(FetchPrune::On);
+    remote.fetch(&[] as &[&str], Some(&mut options), None)?;
+
+    let after = tracking_branches(repo, name)?;
+    Ok(before
+        .into_iter()
+        .filter(|branch| !after.contains(branch))
+        .collect())
+}
+
+/// Clone `options.url` into `options.destination` and check out the chosen branch.
+pub fn clone(
+    options: &CloneOptions,
+    progress: &mut dyn FnMut(TransferProgress),
+    credentials: &mut CredentialChain,
+) -> Result<Repository, git2::Error> {
+    if options.url.trim().is_empty() {
+        return Err(git2::Error::from_str("the URL must not be empty"));
+    }
+    let config = Config::open_default()?;
+    let repo = {
+        let mut fetch = fetch_options(&config, progress, credentials);
+        if let Some(depth) = options.depth {
+            fetch.depth(depth.min(i32::MAX as u32) as i32);
+        }
+        let mut builder = RepoBuilder::new();
+        builder.fetch_options(fetch);
+        if let Some(branch) = &options.branch {
+            builder.branch(branch);
+        }
+        builder.clone(options.url.trim(), &options.destination)?
+    };
+
+    if options.recursive {
+        update_submodules(&repo, &config, progress, credentials)?;
+    }
+    Ok(repo)
+}
+
+/// The folder name `git clone` would pick for `url`.
+pub fn clone_name(url: &str) -> String {
+    let url = url.trim().trim_end_matches(['/', '\\']);
+    let name = url.rsplit(['/', '\\', ':']).next().unwrap_or_default();
+    name.strip_suffix(".git").unwrap_or(name).to_string()
+}
+
+fn update_submodules(
+    repo: &Repository,
+    config: &Config,
+    progress: &mut dyn FnMut(TransferProgress),
+    credentials: &mut CredentialChain,
+) -> Result<(), git2::Error> {
+    for mut submodule in repo.submodules()? {
+        {
+            let mut options = SubmoduleUpdateOptions::new();
+            options.fetch(fetch_options(config, progress, credentials));
+            submodule.update(true, Some(&mut options))?;
+        }
+        update_submodules(&submodule.open()?, config, progress, credentials)?;
+    }
+    Ok(())
+}
+
+/// Fetch options reporting progress and asking `credentials` for authentication.
+fn fetch_options<'a>(
+    config: &'a Config,
+    progress: &'a mut dyn FnMut(TransferProgress),
+    credentials: &'a mut CredentialChain,
+) -> FetchOptions<'a> {
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(|url, username, allowed| {
+        credentials.credentials(config, url, username, allowed)
+    });
+    callbacks.transfer_progress(|stats| {
+        progress(TransferProgress {
+            sending: false,
+            objects: stats.received_objects(),
+            total_objects: stats.total_objects(),
+            bytes: stats.received_bytes(),
+        });
+        true
+    });
+    let mut options = FetchOptions::new();
+    options.remote_callbacks(callbacks);
+    options
+}
+
+fn tracking_branches(repo: &Repository, remote: &str) -> Result<Vec<String>, git2::Error> {
+    let prefix = format!("{}/", remote);
+    let mut names = Vec::new();
+    for branch in repo.branches(Some(BranchType::Remote))? {
+        let (branch, _) = branch?;
+        if let Some(name) = branch.name()?
+            && name.starts_with(&prefix)
+        {
+            names.push(name.to_string());
+        }
+    }
+    Ok(names)
+}
+
+fn validate_remote(name: &str, url: &str) -> Result<(), git2::Error> {
+    if !git2::Remote::is_valid_name(name) {
+        return Err(git2::Error::from_str(&format!(
+            "'{}' is not a valid remote name",
+            name
+        )));
+    }
+    if url.trim().is_empty() {
+        return Err(git2::Error::from_str("the URL must not be empty"));
+    }
+    Ok(())
+}
+
+fn current_branch(repo: &Repository) -> Result<(String, String), git2::Error> {
+    let head = repo.head()?;
+    if !head.is_branch() {
+        return Err(git2::Error::from_str(
+            "HEAD is detached, check out a branch first",
+        ));
+    }
+    let refname = head.name().unwrap_or_default().to_string();
+    let branch = head.shorthand().unwrap_or_default().to_string();
+    Ok((refname, branch))
+}
+
+fn default_remote(repo: &Repository) -> Result<String, git2::Error> {
+    let remotes = repo.remotes()?;
+    let names: Vec<&str> = remotes.iter().flatten().collect();
+    if names.contains(&"origin") {
+        return Ok("origin".to_string());
+    }
+    match names.as_slice() {
+        [only] => Ok(only.to_string()),
+        [] => Err(git2::Error::from_str(
+            "this repository has no remotes, add one first",
+        )),
+        _ => Err(git2::Error::from_str(
+            "the current branch has no upstream and there is no remote named origin",
+        )),
+    }
+}
+
+fn no_upstream() -> git2::Error {
+    git2::Error::from_str("the current branch has no upstream branch, push it first")
+}
+
+fn explain_push_error(e: git2::Error, remote: &str, branch: &str) -> git2::Error {
+    let message = e.message().to_lowercase();
+    if e.code() == ErrorCode::NotFastForward
+        || message.contains("fast-forward")
+        || message.contains("fastforward")
+    {
+        git2::Error::from_str(&format!(
+            "push rejected: {}/{} has commits that are not in your branch. Pull to integrate them, then push again",
+            remote, branch
+        ))
+    } else {
+        e
+    }
+}
@@ -0,0 +1,291 @@
+use gpui::prelude::*;
+use gpui::{
+    AnyElement, Context, Entity, EventEmitter, IntoElement, MouseButton, Render, SharedString,
+    Window, div, px,
+};
+
+use crate::modal::{BUTTON_DANGER, BUTTON_NEUTRAL, BUTTON_PRIMARY, field_label, modal_button};
+use crate::refs::RemoteInfo;
+use crate::text_input::TextInput;
+
+#[derive(Clone)]
+pub enum RemoteManagerEvent {
+    Add {
+        name: String,
+        url: String,
+    },
+    /// Rename (when `new_name` differs from `name`) and set the URLs of a remote.
+    Update {
+        name: String,
+        new_name: String,
+        url: String,
+        push_url: Option<String>,
+    },
+    Remove(String),
+    Prune(String),
+    Close,
+}
+
+struct RemoteRow {
+    name: String,
+    new_name: Entity<TextInput>,
+    url: Entity<TextInput>,
+    push_url: Entity<TextInput>,
+}
+
+/// Lists the configured remotes with editable names and URLs.
+pub struct RemoteManager {
+    rows: Vec<RemoteRow>,
+    new_name: Entity<TextInput>,
+    new_url: Entity<TextInput>,
+    confirm_remove: Option<String>,
+    error: Option<String>,
+}
+
+impl EventEmitter<RemoteManagerEvent> for RemoteManager {}
+
+impl RemoteManager {
+    pub fn new(remotes: Vec<RemoteInfo>, cx: &mut Context<Self>) -> Self {
+        let mut manager = Self {
+            rows: Vec::new(),
+            new_name: cx.new(|cx| TextInput::new("Name, e.g. upstream", cx)),
+            new_url: cx.new(|cx| TextInput::new("URL or path", cx)),
+            confirm_remove: None,
+            error: None,
+        };
+        manager.set_remotes(remotes, cx);
+        manager
+    }
+
+    /// Replace the rows after the remotes changed; clears the add form and any error.
+    pub fn set_remotes(&mut self, remotes: Vec<RemoteInfo>, cx: &mut Context<Self>) {
+        self.rows = remotes
+            .into_iter()
+            .map(|remote| {
+                let input = |placeholder: &'static str, text: String, cx: &mut Context<Self>| {
+                    cx.new(|cx| {
+                        let mut input = TextInput::new(placeholder, cx);
+                        input.set_text(text, cx);
+                        input
+                    })
+                };
+                RemoteRow {
+                    new_name: input("Name", remote.name.clone(), cx),
+                    url: input("Fetch URL", remote.url, cx),
+                    push_url: input(
+                        "Push URL (same as fetch URL)",
+                        remote.push_url.unwrap_or_default(),
+                        cx,
+                    ),
+                    name: remote.name,
+                }
+            })
+            .collect();
+        self.new_name.update(cx, |input, cx| input.set_text("", cx));
+        self.new_url.update(cx, |input, cx| input.set_text("", cx));
+        self.confirm_remove = None;
+        self.error = None;
+        cx.notify();
+    }
+
+    pub fn set_error(&mut self, error: impl Into<String>, cx: &mut Context<Self>) {
+        self.error = Some(error.into());
+        cx.notify();
+    }
+
+    fn save(&mut self, index: usize, cx: &mut Context<Self>) {
+        let Some(row) = self.rows.get(index) else {
+            return;
+        };
+        let push_url = row.push_url.read(cx).text().trim().to_string();
+        cx.emit(RemoteManagerEvent::Update {
+            name: row.name.clone(),
+            new_name: row.new_name.read(cx).text().trim().to_string(),
+            url: row.url.read(cx).text().trim().to_string(),
+            push_url: (!push_url.is_empty()).then_some(push_url),
+        });
+    }
+
+    fn add(&mut self, cx: &mut Context<Self>) {
+        cx.emit(RemoteManagerEvent::Add {
+            name: self.new_name.read(cx).text().trim().to_string(),
+            url: self.new_url.read(cx).text().trim().to_string(),
+        });
+    }
+
+    fn render_row(&self, index: usize, row: &RemoteRow, cx: &mut Context<Self>) -> AnyElement {
+        let name = row.name.clone();
+        let confirming = self.confirm_remove.as_deref() == Some(name.as_str());
+
+        let remove = if confirming {
+            let name = name.clone();
+            modal_button(
+                SharedString::from(format!("remote_remove_confirm_{}", index)),
+                "Really remove",
+                BUTTON_DANGER,
+            )
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |_this, _event, _window, cx| {
+                    cx.emit(RemoteManagerEvent::Remove(name.clone()));
+                }),
+            )
+        } else {
+            let name = name.clone();
+            modal_button(
+                SharedString::from(format!("remote_remove_{}", index)),
+                "Remove",
+                BUTTON_NEUTRAL,
+            )
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _event, _window, cx| {
+                    this.confirm_remove = Some(name.clone());
+                    cx.notify();
+                }),
+            )
+        };
+
+        div()
+            .w_full()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .px(px(12.0))
+            .py(px(8.0))
+            .border_b_1()
+            .border_color(gpui::rgb(0x2A2A2A))
+            .child(field_label("Name"))
+            .child(row.new_name.clone())
+            .child(field_label("Fetch URL"))
+            .child(row.url.clone())
+            .child(field_label("Push URL"))
+            .child(row.push_url.clone())
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .gap_2()
+                    .pt(px(4.0))
+                    .child(
+                        modal_button(
+                            SharedString::from(format!("remote_save_{}", index)),
+                            "Save",
+                            BUTTON_PRIMARY,
+                        )
+                        .on_mouse_down(
+                            MouseButton::Left,
+                            cx.listener(move |this, _event, _window, cx| {
+                                this.save(index, cx);
+                            }),
+                        ),
+                    )
+                    .child(
+                        modal_button(
+                            SharedString::from(format!("remote_prune_{}", index)),
+                            "Prune stale branches",
+                            BUTTON_NEUTRAL,
+                        )
+                        .on_mouse_down(
+                            MouseButton::Left,
+                            cx.listener(move |_this, _event, _window, cx| {
+                                cx.emit(RemoteManagerEvent::Prune(name.clone()));
+                            }),
+                        ),
+                    )
+                    .child(remove),
+            )
+            .into_any()
+    }
+}
+
+impl Render for RemoteManager {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let rows = self
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(index, row)| self.render_row(index, row, cx))
+            .collect::<Vec<_>>();
+
+        div()
+            .size_full()
+            .flex()
+            .flex_col()
+            .bg(gpui::rgb(0x1E1E1E))
+            .child(
+                div()
+                    .w_full()
+                    .flex()
+                    .flex_row()
+                    .items_center()
+                    .justify_between()
+                    .px(px(12.0))
+                    .py(px(8.0))
+                    .border_b_1()
+                    .border_color(gpui::rgb(0x333333))
+                    .bg(gpui::rgb(0x252525))
+                    .child(
+                        div()
+                            .text_color(gpui::white())
+                            .font_weight(gpui::FontWeight::BOLD)
+                            .text_size(px(14.0))
+                            .child(format!("Remotes ({})", self.rows.len())),
+                    )
+                    .child(
+                        modal_button("remote_manager_close", "Close", BUTTON_NEUTRAL)
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(|_this, _event, _window, cx| {
+                                    cx.emit(RemoteManagerEvent::Close);
+                                }),
+                            ),
+                    ),
+            )
+            .when_some(self.error.clone(), |this, error| {
+                this.child(
+                    div()
+                        .px(px(12.0))
+                        .py(px(8.0))
+                        .text_color(gpui::rgb(0xE74C3C))
+                        .text_size(px(12.0))
+                        .child(error),
+                )
+            })
+            .child(
+                div()
+                    .id("remote-manager-rows")
+                    .flex_1()
+                    .flex()
+                    .flex_col()
+                    .overflow_y_scroll()
+                    .children(rows)
+                    .child(
+                        div()
+                            .w_full()
+                            .flex()
+                            .flex_col()
+                            .gap_1()
+                            .px(px(12.0))
+                            .py(px(8.0))
+                            .child(
+                                div()
+                                    .text_color(gpui::white())
+                                    .font_weight(gpui::FontWeight::BOLD)
+                                    .text_size(px(13.0))
+                                    .child("Add remote"),
+                            )
+                            .child(self.new_name.clone())
+                            .child(self.new_url.clone())
+                            .child(div().pt(px(4.0)).child(
+                                modal_button("remote_add", "Add", BUTTON_PRIMARY).on_mouse_down(
+                                    MouseButton::Left,
+                                    cx.listener(|this, _event, _window, cx| {
+                                        this.add(cx);
+                                    }),
+                                ),
+                            )),
+                    ),
+            )
+    }
+}
@@ -0,0 +1,158 @@
+use std::path::Path;
+
+use git2::build::CheckoutBuilder;
+use git2::{ApplyLocation, ApplyOptions, DiffOptions, Oid, Repository, Tree};
+
+/// One hunk of the difference between HEAD and the working tree for a file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WorkdirHunk {
+    pub header: String,
+    pub lines: Vec<String>,
+}
+
+fn path_diff<'a>(
+    repo: &'a Repository,
+    path: &str,
+    reverse: bool,
+) -> Result<git2::Diff<'a>, git2::Error> {
+    let head_tree = match repo.head() {
+        Ok(head) => Some(head.peel_to_tree()?),
+        Err(_) => None,
+    };
+    let mut options = DiffOptions::new();
+    options
+        .pathspec(path)
+        .disable_pathspec_match(true)
+        .include_untracked(true)
+        .show_untracked_content(true)
+        .reverse(reverse);
+    repo.diff_tree_to_workdir(head_tree.as_ref(), Some(&mut options))
+}
+
+/// Hunks of uncommitted changes to `path`, in file order.
+pub fn workdir_hunks(repo: &Repository, path: &str) -> Result<Vec<WorkdirHunk>, git2::Error> {
+    let diff = path_diff(repo, path, false)?;
+    let mut hunks: Vec<WorkdirHunk> = Vec::new();
+    diff.print(git2::DiffFormat::Patch, |_delta, hunk, line| {
+        let Some(hunk) = hunk else {
+            return true;
+        };
+        let header = String::from_utf8_lossy(hunk.header())
+            .trim_end()
+            .to_string();
+        if hunks.last().map(|last| &last.header) != Some(&header) {
+            hunks.push(WorkdirHunk {
+                header: header.clone(),
+                lines: Vec::new(),
+            });
+        }
+        if matches!(line.origin(), '+' | '-' | ' ')
+            && let Some(last) = hunks.last_mut()
+        {
+            let content = String::from_utf8_lossy(line.content());
+            last.lines.push(format!(
+                "{}{}",
+                line.origin(),
+                content.trim_end_matches('\n')
+            ));
+        }
+        true
+    })?;
+    Ok(hunks)
+}
+
+/// Throw away all uncommitted changes to `path`, in the index and the working tree.
+pub fn discard_file(repo: &Repository, path: &str) -> Result<(), git2::Error> {
+    let tree = match repo.head() {
+        Ok(head) => Some(head.peel_to_tree()?),
+        Err(_) => None,
+    };
+    checkout_path(repo, tree.as_ref(), path)
+}
+
+/// Throw away hunk `hunk_index` of `workdir_hunks(repo, path)` in the working tree.
+pub fn discard_hunk(repo: &Repository, path: &str, hunk_index: usize) -> Result<(), git2::Error> {
+    let count = workdir_hunks(repo, path)?.len();
+    if hunk_index >= count {
+        return Err(git2::Error::from_str("the hunk no longer exists"));
+    }
+
+    // apply the reversed diff, keeping only the chosen hunk
+    let diff = path_diff(repo, path, true)?;
+    let mut current = 0;
+    let mut options = ApplyOptions::new();
+    options.hunk_callback(|_hunk| {
+        let keep = current == hunk_index;
+        current += 1;
+        keep
+    });
+    repo.apply(&diff, ApplyLocation::WorkDir, Some(&mut options))
+}
+
+/// Check `path` out of `commit`, or out of its first parent when `before` is set, into
+/// the index and working tree. Checking out keeps the file mode, symlinks and filters
+/// such as autocrlf; a path missing from that commit is deleted.
+pub fn restore_file(
+    repo: &Repository,
+    commit: Oid,
+    before: bool,
+    path: &str,
+) -> Result<(), git2::Error> {
+    let commit = repo.find_commit(commit)?;
+    let tree = match before {
+        // the parent of a root commit is the empty tree
+        true if commit.parent_count() == 0 => None,
+        true => Some(commit.parent(0)?.tree()?),
+        false => Some(commit.tree()?),
+    };
+    checkout_path(repo, tree.as_ref(), path)
+}
+
+/// Make `path` in the index and working tree match `tree`, deleting it when `tree` is
+/// `None` or does not have it.
+fn checkout_path(repo: &Repository, tree: Option<&Tree>, path: &str) -> Result<(), git2::Error> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("cannot restore files in a bare repository"))?;
+    let entry = match tree {
+        Some(tree) => match tree.get_path(Path::new(path)) {
+            Ok(entry) => Some(entry),
+            Err(e) if e.code() == git2::ErrorCode::NotFound => None,
+            Err(e) => return Err(e),
+        },
+        None => None,
+    };
+
+    match (tree, entry) {
+        (Some(tree), Some(entry)) => {
+            let mut checkout = CheckoutBuilder::new();
+            checkout.path(path).disable_pathspec_match(true).force();
+            repo.checkout_tree(tree.as_object(), Some(&mut checkout))?;
+
+            // stage the entry as it is in the tree, whatever the filters wrote on disk
+            let mut index = repo.index()?;
+            let mut staged = index
+                .get_path(Path::new(path), 0)
+                .ok_or_else(|| git2::Error::from_str(&format!("{} was not checked out", path)))?;
+            staged.id = entry.id();
+            staged.mode = entry.filemode() as u32;
+            index.add(&staged)?;
+            index.write()
+        }
+        _ => {
+            let full_path = workdir.join(path);
+            if full_path.symlink_metadata().is_ok() {
+                std::fs::remove_file(&full_path).map_err(io_error)?;
+            }
+            let mut index = repo.index()?;
+            if index.get_path(Path::new(path), 0).is_some() {
+                index.remove_path(Path::new(path))?;
+            }
+            index.write()
+        }
+    }
+}
+
+fn io_error(e: std::io::Error) -> git2::Error {
+    git2::Error::from_str(&e.to_string())
+}
@@ -0,0 +1,296 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Per-repository settings, in the git dir so they stay out of the working tree.
+pub const REPO_FILE: &str = "gpig.toml";
+
+/// Limits and layout, read from the user file and then the repository file, whose
+/// keys win. Keys left out keep their defaults.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub history: HistorySettings,
+    pub layout: LayoutSettings,
+    pub diff: DiffLimits,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistorySettings {
+    /// Commits walked for the graph.
+    pub limit_rows: usize,
+    pub sort: SortOrder,
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        Self {
+            limit_rows: 100,
+            sort: SortOrder::TopoDate,
+        }
+    }
+}
+
+/// Order of the graph rows.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SortOrder {
+    /// Parents after children, newest first otherwise.
+    #[default]
+    TopoDate,
+    Topo,
+    Date,
+}
+
+impl SortOrder {
+    pub const ALL: [SortOrder; 3] = [SortOrder::TopoDate, SortOrder::Topo, SortOrder::Date];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortOrder::TopoDate => "Topological, then date",
+            SortOrder::Topo => "Topological",
+            SortOrder::Date => "Commit date",
+        }
+    }
+
+    pub fn git_sort(self) -> git2::Sort {
+        match self {
+            SortOrder::TopoDate => git2::Sort::TOPOLOGICAL | git2::Sort::TIME,
+            SortOrder::Topo => git2::Sort::TOPOLOGICAL,
+            SortOrder::Date => git2::Sort::TIME,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutSettings {
+    /// Horizontal distance between lanes, in pixels.
+    pub lane_width: f32,
+    /// Height of one commit row, in pixels.
+    pub row_height: f32,
+}
+
+impl Default for LayoutSettings {
+    fn default() -> Self {
+        Self {
+            lane_width: 15.0,
+            row_height: 20.0,
+        }
+    }
+}
+
+/// Caps that keep huge commits from exhausting memory.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiffLimits {
+    /// Larger files are treated as binary and not diffed.
+    pub max_file_size_bytes: usize,
+    /// Files listed in a whole-commit diff.
+    pub max_files: usize,
+    pub max_lines_per_file: usize,
+    /// Lines of a whole-commit diff.
+    pub max_total_lines: usize,
+    /// Lines of a single file diff.
+    pub max_file_diff_lines: usize,
+}
+
+impl Default for DiffLimits {
+    fn default() -> Self {
+        Self {
+            max_file_size_bytes: 10 * 1024 * 1024,
+            max_files: 10,
+            max_lines_per_file: 25,
+            max_total_lines: 100,
+            max_file_diff_lines: 200,
+        }
+    }
+}
+
+impl DiffLimits {
+    /// A zero limit would hide every diff, so each must be at least 1.
+    pub fn validate(&self) -> Result<(), String> {
+        let limits = [
+            ("max_file_size_bytes", self.max_file_size_bytes),
+            ("max_files", self.max_files),
+            ("max_lines_per_file", self.max_lines_per_file),
+            ("max_total_lines", self.max_total_lines),
+            ("max_file_diff_lines", self.max_file_diff_lines),
+        ];
+        match limits.into_iter().find(|(_, value)| *value == 0) {
+            Some((key, _)) => Err(format!("diff.{} must be at least 1", key)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Settings {
+    /// `settings.toml` in the user's config directory.
+    pub fn user_file() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("gpig").join("settings.toml"))
+    }
+
+    /// `gpig.toml` in the repository's `git_dir`.
+    pub fn repo_file(git_dir: &Path) -> PathBuf {
+        git_dir.join(REPO_FILE)
+    }
+
+    /// Merge `files` in order, later keys winning. Missing files are skipped; a file
+    /// that does not parse or holds unusable values is an error naming it.
+    pub fn load(files: &[PathBuf]) -> Result<Settings, String> {
+        let mut merged = toml::Table::new();
+        for file in files {
+            let Ok(text) = std::fs::read_to_string(file) else {
+                continue;
+            };
+            let table: toml::Table =
+                toml::from_str(&text).map_err(|e| format!("{}: {}", file.display(), e))?;
+            merge(&mut merged, table);
+        }
+        let settings: Settings = toml::Value::Table(merged)
+            .try_into()
+            .map_err(|e: toml::de::Error| e.to_string())?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.history.limit_rows == 0 {
+            return Err("history.limit_rows must be at least 1".to_string());
+        }
+        if !self.layout.lane_width.is_finite() || self.layout.lane_width <= 0.0 {
+            return Err("layout.lane_width must be a positive number".to_string());
+        }
+        if !self.layout.row_height.is_finite() || self.layout.row_height <= 0.0 {
+            return Err("layout.row_height must be a positive number".to_string());
+        }
+        self.diff.validate()
+    }
+
+    /// Write every key to `file`.
+    pub fn save(&self, file: &Path) -> std::io::Result<()> {
+        write_table(file, &to_table(self))
+    }
+
+    /// Write only the keys that differ from `base`, so the file keeps following the
+    /// user settings for everything else.
+    pub fn save_overrides(&self, base: &Settings, file: &Path) -> std::io::Result<()> {
+        write_table(file, &difference(to_table(self), &to_table(base)))
+    }
+}
+
+/// The numeric settings the settings form edits as text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SettingField {
+    LimitRows,
+    LaneWidth,
+    RowHeight,
+    MaxFileSizeBytes,
+    MaxFiles,
+    MaxLinesPerFile,
+    MaxTotalLines,
+    MaxFileDiffLines,
+}
+
+impl SettingField {
+    pub const ALL: [SettingField; 8] = [
+        SettingField::LimitRows,
+        SettingField::LaneWidth,
+        SettingField::RowHeight,
+        SettingField::MaxFileSizeBytes,
+        SettingField::MaxFiles,
+        SettingField::MaxLinesPerFile,
+        SettingField::MaxTotalLines,
+        SettingField::MaxFileDiffLines,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SettingField::LimitRows => "Commits in the graph",
+            SettingField::LaneWidth => "Lane width (px)",
+            SettingField::RowHeight => "Row height (px)",
+            SettingField::MaxFileSizeBytes => "Largest file to diff (bytes)",
+            SettingField::MaxFiles => "Files in a commit diff",
+            SettingField::MaxLinesPerFile => "Lines per file in a commit diff",
+            SettingField::MaxTotalLines => "Lines in a commit diff",
+            SettingField::MaxFileDiffLines => "Lines in a file diff",
+        }
+    }
+
+    pub fn value(self, settings: &Settings) -> String {
+        match self {
+            SettingField::LimitRows => settings.history.limit_rows.to_string(),
+            SettingField::LaneWidth => settings.layout.lane_width.to_string(),
+            SettingField::RowHeight => settings.layout.row_height.to_string(),
+            SettingField::MaxFileSizeBytes => settings.diff.max_file_size_bytes.to_string(),
+            SettingField::MaxFiles => settings.diff.max_files.to_string(),
+            SettingField::MaxLinesPerFile => settings.diff.max_lines_per_file.to_string(),
+            SettingField::MaxTotalLines => settings.diff.max_total_lines.to_string(),
+            SettingField::MaxFileDiffLines => settings.diff.max_file_diff_lines.to_string(),
+        }
+    }
+
+    /// Parse `text` into this field of `settings`.
+    pub fn apply(self, settings: &mut Settings, text: &str) -> Result<(), String> {
+        let text = text.trim();
+        let invalid = || format!("{}: '{}' is not a valid number", self.label(), text);
+        let count = || text.parse::<usize>().map_err(|_| invalid());
+        let pixels = || text.parse::<f32>().map_err(|_| invalid());
+        match self {
+            SettingField::LimitRows => settings.history.limit_rows = count()?,
+            SettingField::LaneWidth => settings.layout.lane_width = pixels()?,
+            SettingField::RowHeight => settings.layout.row_height = pixels()?,
+            SettingField::MaxFileSizeBytes => settings.diff.max_file_size_bytes = count()?,
+            SettingField::MaxFiles => settings.diff.max_files = count()?,
+            SettingField::MaxLinesPerFile => settings.diff.max_lines_per_file = count()?,
+            SettingField::MaxTotalLines => settings.diff.max_total_lines = count()?,
+            SettingField::MaxFileDiffLines => settings.diff.max_file_diff_lines = count()?,
+        }
+        Ok(())
+    }
+}
+
+fn to_table(settings: &Settings) -> toml::Table {
+    toml::Table::try_from(settings).unwrap_or_default()
+}
+
+fn write_table(file: &Path, table: &toml::Table) -> std::io::Result<()> {
+    if let Some(dir) = file.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let text = toml::to_string(table).map_err(std::io::Error::other)?;
+    std::fs::write(file, text)
+}
+
+fn merge(into: &mut toml::Table, from: toml::Table) {
+    for (key, value) in from {
+        match (into.get_mut(&key), value) {
+            (Some(toml::Value::Table(existing)), toml::Value::Table(table)) => {
+                merge(existing, table)
+            }
+            (_, value) => {
+                into.insert(key, value);
+            }
+        }
+    }
+}
+
+fn difference(table: toml::Table, base: &toml::Table) -> toml::Table {
+    let mut out = toml::Table::new();
+    for (key, value) in table {
+        match (value, base.get(&key)) {
+            (toml::Value::Table(table), Some(toml::Value::Table(base))) => {
+                let nested = difference(table, base);
+                if !nested.is_empty() {
+                    out.insert(key, toml::Value::Table(nested));
+                }
+            }
+            (value, Some(base)) if &value == base => {}
+            (value, _) => {
+                out.insert(key, value);
+            }
+        }
+    }
+    out
+}
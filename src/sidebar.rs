@@ -0,0 +1,392 @@
+use std::collections::HashSet;
+
+use git2::Oid;
+use gpui::prelude::*;
+use gpui::{
+    AnyElement, Context, EventEmitter, InteractiveElement, IntoElement, MouseButton, ParentElement,
+    Render, SharedString, StatefulInteractiveElement, Styled, Window, div, px,
+};
+
+use crate::refs::{BranchInfo, RepoRefs};
+use crate::remote::SyncAction;
+
+const SIDEBAR_WIDTH: f32 = 220.0;
+const COLLAPSED_WIDTH: f32 = 24.0;
+
+#[derive(Clone)]
+pub struct RefSelected {
+    pub oid: Oid,
+}
+
+/// The "+" in the Stashes header was clicked.
+#[derive(Clone)]
+pub struct StashSaveRequested;
+
+/// A fetch, pull or push button was clicked.
+#[derive(Clone)]
+pub struct SyncRequested(pub SyncAction);
+
+/// "Manage" in the Remotes header was clicked.
+#[derive(Clone)]
+pub struct ManageRemotesRequested;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SidebarSection {
+    Branches,
+    Remotes,
+    Tags,
+    Stashes,
+}
+
+pub struct Sidebar {
+    refs: RepoRefs,
+    is_open: bool,
+    collapsed: HashSet<SidebarSection>,
+}
+
+impl EventEmitter<RefSelected> for Sidebar {}
+impl EventEmitter<StashSaveRequested> for Sidebar {}
+impl EventEmitter<SyncRequested> for Sidebar {}
+impl EventEmitter<ManageRemotesRequested> for Sidebar {}
+
+impl Sidebar {
+    pub fn new() -> Self {
+        Self {
+            refs: RepoRefs::default(),
+            is_open: true,
+            collapsed: HashSet::new(),
+        }
+    }
+
+    pub fn set_refs(&mut self, refs: RepoRefs, cx: &mut Context<Self>) {
+        self.refs = refs;
+        cx.notify();
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    fn toggle_open(&mut self, cx: &mut Context<Self>) {
+        self.is_open = !self.is_open;
+        cx.notify();
+    }
+
+    fn toggle_section(&mut self, section: SidebarSection, cx: &mut Context<Self>) {
+        if !self.collapsed.remove(&section) {
+            self.collapsed.insert(section);
+        }
+        cx.notify();
+    }
+
+    fn format_date(time: git2::Time) -> String {
+        chrono::DateTime::from_timestamp(time.seconds(), 0)
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .unwrap_or_default()
+    }
+
+    fn render_section_header(
+        &self,
+        section: SidebarSection,
+        title: &str,
+        count: usize,
+        actions: Vec<AnyElement>,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let arrow = if self.collapsed.contains(&section) {
+            "▸"
+        } else {
+            "▾"
+        };
+
+        div()
+            .w_full()
+            .flex()
+            .flex_row()
+            .gap_2()
+            .px(px(8.0))
+            .py(px(6.0))
+            .text_color(gpui::rgb(0xCCCCCC))
+            .text_size(px(12.0))
+            .font_weight(gpui::FontWeight::BOLD)
+            .cursor_pointer()
+            .hover(|style| style.bg(gpui::rgb(0x2A2A2A)))
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _event, _window, cx| {
+                    this.toggle_section(section, cx);
+                }),
+            )
+            .child(arrow)
+            .child(div().flex_1().child(format!("{} ({})", title, count)))
+            .children(actions)
+            .into_any()
+    }
+
+    /// A small button in a section header that emits `event` without toggling the section.
+    fn render_header_action<E: Clone + 'static>(
+        &self,
+        label: &'static str,
+        event: E,
+        cx: &mut Context<Self>,
+    ) -> AnyElement
+    where
+        Self: EventEmitter<E>,
+    {
+        div()
+            .px(px(4.0))
+            .rounded(px(3.0))
+            .text_color(gpui::rgb(0x888888))
+            .font_weight(gpui::FontWeight::NORMAL)
+            .hover(|style| style.bg(gpui::rgb(0x444444)))
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |_this, _event, _window, cx| {
+                    cx.stop_propagation();
+                    cx.emit(event.clone());
+                }),
+            )
+            .child(label)
+            .into_any()
+    }
+
+    fn render_item(
+        &self,
+        label: SharedString,
+        detail: Option<String>,
+        oid: Oid,
+        indent: f32,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        div()
+            .w_full()
+            .flex()
+            .flex_row()
+            .justify_between()
+            .gap_2()
+            .pl(px(indent))
+            .pr(px(8.0))
+            .py(px(3.0))
+            .text_size(px(12.0))
+            .cursor_pointer()
+            .hover(|style| style.bg(gpui::rgb(0x2A2A2A)))
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |_this, _event, _window, cx| {
+                    cx.emit(RefSelected { oid });
+                }),
+            )
+            .child(
+                div()
+                    .overflow_hidden()
+                    .whitespace_nowrap()
+                    .text_color(gpui::rgb(0xCCCCCC))
+                    .child(label),
+            )
+            .when_some(detail, |this, detail| {
+                this.child(
+                    div()
+                        .flex_none()
+                        .whitespace_nowrap()
+                        .text_color(gpui::rgb(0x777777))
+                        .text_size(px(10.0))
+                        .child(detail),
+                )
+            })
+            .into_any()
+    }
+
+    fn render_branch(
+        &self,
+        branch: &BranchInfo,
+        indent: f32,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let label = if branch.is_head {
+            format!("● {}", branch.name)
+        } else {
+            branch.name.clone()
+        };
+
+        let mut detail = Vec::new();
+        if branch.upstream.is_some() {
+            if branch.ahead > 0 {
+                detail.push(format!("↑{}", branch.ahead));
+            }
+            if branch.behind > 0 {
+                detail.push(format!("↓{}", branch.behind));
+            }
+        }
+        detail.push(Self::format_date(branch.last_commit));
+
+        self.render_item(label.into(), Some(detail.join(" ")), branch.oid, indent, cx)
+    }
+}
+
+impl Default for Sidebar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Render for Sidebar {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if !self.is_open {
+            return div()
+                .id("sidebar")
+                .w(px(COLLAPSED_WIDTH))
+                .h_full()
+                .flex()
+                .flex_col()
+                .items_center()
+                .py(px(6.0))
+                .bg(gpui::rgb(0x1E1E1E))
+                .border_r_1()
+                .border_color(gpui::rgb(0x333333))
+                .text_color(gpui::rgb(0x888888))
+                .cursor_pointer()
+                .hover(|style| style.bg(gpui::rgb(0x2A2A2A)))
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(|this, _event, _window, cx| {
+                        this.toggle_open(cx);
+                    }),
+                )
+                .child("»");
+        }
+
+        let mut children = Vec::new();
+
+        children.push(self.render_section_header(
+            SidebarSection::Branches,
+            "Branches",
+            self.refs.local_branches.len(),
+            Vec::new(),
+            cx,
+        ));
+        if !self.collapsed.contains(&SidebarSection::Branches) {
+            for branch in &self.refs.local_branches {
+                children.push(self.render_branch(branch, 20.0, cx));
+            }
+        }
+
+        let actions = vec![
+            self.render_header_action("Fetch", SyncRequested(SyncAction::Fetch(None)), cx),
+            self.render_header_action("Pull", SyncRequested(SyncAction::Pull), cx),
+            self.render_header_action("Push", SyncRequested(SyncAction::Push), cx),
+            self.render_header_action("Manage", ManageRemotesRequested, cx),
+        ];
+        children.push(self.render_section_header(
+            SidebarSection::Remotes,
+            "Remotes",
+            self.refs.remotes.len(),
+            actions,
+            cx,
+        ));
+        if !self.collapsed.contains(&SidebarSection::Remotes) {
+            for remote in &self.refs.remotes {
+                let fetch = SyncRequested(SyncAction::Fetch(Some(remote.name.clone())));
+                children.push(
+                    div()
+                        .flex()
+                        .flex_row()
+                        .justify_between()
+                        .pl(px(20.0))
+                        .pr(px(8.0))
+                        .py(px(3.0))
+                        .text_size(px(12.0))
+                        .text_color(gpui::rgb(0x969696))
+                        .child(remote.name.clone())
+                        .child(self.render_header_action("Fetch", fetch, cx))
+                        .into_any(),
+                );
+                for branch in &remote.branches {
+                    children.push(self.render_branch(branch, 32.0, cx));
+                }
+            }
+        }
+
+        children.push(self.render_section_header(
+            SidebarSection::Tags,
+            "Tags",
+            self.refs.tags.len(),
+            Vec::new(),
+            cx,
+        ));
+        if !self.collapsed.contains(&SidebarSection::Tags) {
+            for tag in &self.refs.tags {
+                children.push(self.render_item(tag.name.clone().into(), None, tag.oid, 20.0, cx));
+            }
+        }
+
+        children.push(self.render_section_header(
+            SidebarSection::Stashes,
+            "Stashes",
+            self.refs.stashes.len(),
+            vec![self.render_header_action("+", StashSaveRequested, cx)],
+            cx,
+        ));
+        if !self.collapsed.contains(&SidebarSection::Stashes) {
+            for stash in &self.refs.stashes {
+                children.push(self.render_item(
+                    format!("stash@{{{}}}", stash.index).into(),
+                    Some(stash.message.clone()),
+                    stash.oid,
+                    20.0,
+                    cx,
+                ));
+            }
+        }
+
+        div()
+            .id("sidebar")
+            .w(px(SIDEBAR_WIDTH))
+            .h_full()
+            .flex()
+            .flex_col()
+            .bg(gpui::rgb(0x1E1E1E))
+            .border_r_1()
+            .border_color(gpui::rgb(0x333333))
+            .child(
+                div()
+                    .w_full()
+                    .flex()
+                    .flex_row()
+                    .justify_between()
+                    .items_center()
+                    .px(px(8.0))
+                    .py(px(6.0))
+                    .border_b_1()
+                    .border_color(gpui::rgb(0x333333))
+                    .bg(gpui::rgb(0x252525))
+                    .text_color(gpui::white())
+                    .text_size(px(13.0))
+                    .font_weight(gpui::FontWeight::BOLD)
+                    .child("Refs")
+                    .child(
+                        div()
+                            .text_color(gpui::rgb(0x888888))
+                            .px(px(4.0))
+                            .cursor_pointer()
+                            .rounded(px(4.0))
+                            .hover(|style| style.bg(gpui::rgb(0x444444)))
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(|this, _event, _window, cx| {
+                                    this.toggle_open(cx);
+                                }),
+                            )
+                            .child("«"),
+                    ),
+            )
+            .child(
+                div()
+                    .id("sidebar-refs")
+                    .flex_1()
+                    .flex()
+                    .flex_col()
+                    .overflow_y_scroll()
+                    .children(children),
+            )
+    }
+}
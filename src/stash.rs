@@ -0,0 +1,71 @@
+use git2::{ErrorCode, Oid, Repository, StashApplyOptions, StashFlags};
+
+use crate::operations::{self, OperationKind, conflicted_paths};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StashOutcome {
+    Applied,
+    /// The stash was applied with conflicts in these paths and kept in the list.
+    Conflicts(Vec<String>),
+}
+
+/// Stash the working tree and index, optionally with untracked files.
+pub fn save(
+    repo: &mut Repository,
+    message: Option<&str>,
+    include_untracked: bool,
+) -> Result<Oid, git2::Error> {
+    let signature = repo.signature()?;
+    let flags = if include_untracked {
+        StashFlags::INCLUDE_UNTRACKED
+    } else {
+        StashFlags::DEFAULT
+    };
+    match repo.stash_save2(&signature, message.filter(|m| !m.is_empty()), Some(flags)) {
+        Err(e) if e.code() == ErrorCode::NotFound => {
+            Err(git2::Error::from_str("there are no local changes to stash"))
+        }
+        result => result,
+    }
+}
+
+/// Apply stash `index` on top of the working tree and keep it in the list.
+pub fn apply(repo: &mut Repository, index: usize) -> Result<StashOutcome, git2::Error> {
+    repo.stash_apply(index, Some(&mut StashApplyOptions::new()))?;
+    outcome(repo)
+}
+
+/// Apply stash `index` and drop it, unless applying it left conflicts.
+pub fn pop(repo: &mut Repository, index: usize) -> Result<StashOutcome, git2::Error> {
+    let outcome = apply(repo, index)?;
+    if outcome == StashOutcome::Applied {
+        repo.stash_drop(index)?;
+    }
+    Ok(outcome)
+}
+
+pub fn drop(repo: &mut Repository, index: usize) -> Result<(), git2::Error> {
+    repo.stash_drop(index)
+}
+
+/// Position of the stash commit `oid` in the stash list.
+pub fn index_of(repo: &mut Repository, oid: Oid) -> Result<Option<usize>, git2::Error> {
+    let mut found = None;
+    repo.stash_foreach(|index, _, stash_oid| {
+        if *stash_oid == oid {
+            found = Some(index);
+        }
+        found.is_none()
+    })?;
+    Ok(found)
+}
+
+fn outcome(repo: &Repository) -> Result<StashOutcome, git2::Error> {
+    let index = repo.index()?;
+    if index.has_conflicts() {
+        operations::record_operation(repo, OperationKind::StashApply)?;
+        Ok(StashOutcome::Conflicts(conflicted_paths(&index)?))
+    } else {
+        Ok(StashOutcome::Applied)
+    }
+}
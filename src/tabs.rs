@@ -0,0 +1,298 @@
+use std::path::{Path, PathBuf};
+
+use git2::Repository;
+use gpui::prelude::*;
+use gpui::{
+    AnyElement, App, AppContext, Context, Entity, InteractiveElement, IntoElement, KeyBinding,
+    MouseButton, ParentElement, Render, Styled, Window, div, px,
+};
+
+use crate::actions::{CloseTab, NextTab, OpenFile, OpenSettings, PreviousTab};
+use crate::cli;
+use crate::garph::Garph;
+use crate::recent::RecentRepos;
+use crate::settings::Settings;
+use crate::workspace::{OpenRepository, Workspace};
+
+pub fn key_bindings() -> Vec<KeyBinding> {
+    vec![
+        KeyBinding::new("ctrl-o", OpenFile, None),
+        KeyBinding::new("ctrl-,", OpenSettings, None),
+        KeyBinding::new("ctrl-tab", NextTab, None),
+        KeyBinding::new("ctrl-pagedown", NextTab, None),
+        KeyBinding::new("ctrl-shift-tab", PreviousTab, None),
+        KeyBinding::new("ctrl-pageup", PreviousTab, None),
+        KeyBinding::new("ctrl-w", CloseTab, None),
+    ]
+}
+
+/// Per-user files every tab shares; `None` keeps that state in memory.
+#[derive(Clone, Debug, Default)]
+pub struct UserFiles {
+    pub recent: Option<PathBuf>,
+    pub settings: Option<PathBuf>,
+}
+
+impl UserFiles {
+    /// The usual locations under the user's state and config directories.
+    pub fn locate() -> Self {
+        Self {
+            recent: RecentRepos::user_file(),
+            settings: Settings::user_file(),
+        }
+    }
+}
+
+/// One workspace per open repository, each with its own graph, selection and diff.
+pub struct Tabs {
+    tabs: Vec<Entity<Workspace>>,
+    active: usize,
+    /// Handed to every new tab so they share one recent list and the user settings.
+    files: UserFiles,
+}
+
+impl Tabs {
+    pub fn new(repo: Option<Repository>, files: UserFiles, cx: &mut Context<Self>) -> Self {
+        let mut tabs = Self {
+            tabs: Vec::new(),
+            active: 0,
+            files,
+        };
+        tabs.push(repo, cx);
+        tabs
+    }
+
+    pub fn len(&self) -> usize {
+        self.tabs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tabs.is_empty()
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    pub fn active(&self) -> &Entity<Workspace> {
+        &self.tabs[self.active]
+    }
+
+    /// Repository paths in tab order, `None` for a tab with nothing open.
+    pub fn repo_paths(&self, cx: &App) -> Vec<Option<PathBuf>> {
+        self.tabs
+            .iter()
+            .map(|tab| tab.read(cx).repo_path(cx))
+            .collect()
+    }
+
+    /// Show the repository enclosing `path`: switch to its tab when it is open already,
+    /// load it into the current tab when that one is empty, or open a new tab.
+    pub fn open(&mut self, path: &Path, cx: &mut Context<Self>) -> Result<(), String> {
+        let repo = cli::discover(path).map_err(|e| e.message().to_string())?;
+        let workdir = repo.workdir().unwrap_or(repo.path()).to_path_buf();
+        let wanted = workdir.canonicalize().ok();
+        let existing = self.repo_paths(cx).iter().position(|open| {
+            open.as_ref()
+                .is_some_and(|open| open.canonicalize().ok() == wanted)
+        });
+        if let Some(index) = existing {
+            self.activate(index, cx);
+            return Ok(());
+        }
+
+        let active = self.active().clone();
+        if active.read(cx).repo_path(cx).is_none() {
+            return active.update(cx, |workspace, cx| workspace.open_repository(&workdir, cx));
+        }
+        self.push(Some(repo), cx);
+        Ok(())
+    }
+
+    /// Open `path` as `open` does, reporting a failure in the current tab's notice bar.
+    pub fn open_or_notify(&mut self, path: &Path, cx: &mut Context<Self>) {
+        let active = self.active().clone();
+        self.open_reporting_to(path, &active, cx);
+    }
+
+    fn open_reporting_to(
+        &mut self,
+        path: &Path,
+        workspace: &Entity<Workspace>,
+        cx: &mut Context<Self>,
+    ) {
+        if let Err(e) = self.open(path, cx) {
+            workspace.update(cx, |workspace, cx| {
+                workspace.set_notice(format!("Failed to open {}: {}", path.display(), e), cx)
+            });
+        }
+    }
+
+    fn push(&mut self, repo: Option<Repository>, cx: &mut Context<Self>) {
+        let garph = cx.new(|_| Garph::new(repo));
+        let files = self.files.clone();
+        let workspace = cx.new(|cx| {
+            let mut workspace = Workspace::new(Some(garph), cx);
+            if let Some(file) = files.settings {
+                workspace.set_settings_file(file, cx);
+            }
+            if let Some(file) = files.recent {
+                workspace.set_recent_file(file, cx);
+            }
+            workspace
+        });
+        // repaint the tab titles when a tab changes repository
+        cx.observe(&workspace, |_, _, cx| cx.notify()).detach();
+        cx.subscribe(&workspace, |tabs, workspace, event: &OpenRepository, cx| {
+            tabs.open_reporting_to(&event.path, &workspace, cx)
+        })
+        .detach();
+        self.tabs.push(workspace);
+        self.active = self.tabs.len() - 1;
+        cx.notify();
+    }
+
+    pub fn activate(&mut self, index: usize, cx: &mut Context<Self>) {
+        if index >= self.tabs.len() || index == self.active {
+            return;
+        }
+        self.active = index;
+        self.active()
+            .clone()
+            .update(cx, |workspace, cx| workspace.reload_recent(cx));
+        cx.notify();
+    }
+
+    pub fn next(&mut self, cx: &mut Context<Self>) {
+        self.activate((self.active + 1) % self.tabs.len(), cx);
+    }
+
+    pub fn previous(&mut self, cx: &mut Context<Self>) {
+        self.activate((self.active + self.tabs.len() - 1) % self.tabs.len(), cx);
+    }
+
+    /// Close a tab; closing the last one leaves an empty tab.
+    pub fn close(&mut self, index: usize, cx: &mut Context<Self>) {
+        if index >= self.tabs.len() {
+            return;
+        }
+        self.tabs.remove(index);
+        if self.tabs.is_empty() {
+            self.push(None, cx);
+            return;
+        }
+        if self.active > index || self.active == self.tabs.len() {
+            self.active -= 1;
+        }
+        cx.notify();
+    }
+
+    fn render_tab(&self, index: usize, title: String, cx: &mut Context<Self>) -> AnyElement {
+        let active = index == self.active;
+        div()
+            .id(("repo_tab", index))
+            .flex()
+            .items_center()
+            .gap_2()
+            .h_full()
+            .px(px(12.0))
+            .border_r_1()
+            .border_color(gpui::rgb(0x333333))
+            .text_size(px(12.0))
+            .cursor_pointer()
+            .when(active, |this| {
+                this.bg(gpui::rgb(0x2a2a2a)).text_color(gpui::white())
+            })
+            .when(!active, |this| {
+                this.text_color(gpui::rgb(0x888888))
+                    .hover(|style| style.bg(gpui::rgb(0x222222)))
+            })
+            .child(title)
+            .child(
+                div()
+                    .id(("repo_tab_close", index))
+                    .px(px(4.0))
+                    .rounded(px(3.0))
+                    .hover(|style| style.bg(gpui::rgb(0x444444)))
+                    .child("×")
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |this, _event, _window, cx| {
+                            cx.stop_propagation();
+                            this.close(index, cx);
+                        }),
+                    ),
+            )
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _event, _window, cx| {
+                    this.activate(index, cx);
+                }),
+            )
+            .into_any_element()
+    }
+}
+
+impl Render for Tabs {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let titles: Vec<String> = self
+            .repo_paths(cx)
+            .into_iter()
+            .map(|path| match path {
+                Some(path) => path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| path.display().to_string()),
+                None => "No repository".to_string(),
+            })
+            .collect();
+        let mut tabs = Vec::with_capacity(titles.len());
+        for (index, title) in titles.into_iter().enumerate() {
+            tabs.push(self.render_tab(index, title, cx));
+        }
+
+        div()
+            .size_full()
+            .flex()
+            .flex_col()
+            .bg(gpui::rgb(0x1a1a1a))
+            .on_action(cx.listener(|this, _: &NextTab, _window, cx| this.next(cx)))
+            .on_action(cx.listener(|this, _: &OpenSettings, window, cx| {
+                this.active()
+                    .clone()
+                    .update(cx, |workspace, cx| workspace.open_settings_form(window, cx))
+            }))
+            .on_action(cx.listener(|this, _: &PreviousTab, _window, cx| this.previous(cx)))
+            .on_action(cx.listener(|this, _: &CloseTab, _window, cx| this.close(this.active, cx)))
+            .child(
+                div()
+                    .id("repo_tabs")
+                    .w_full()
+                    .h(px(28.0))
+                    .flex()
+                    .flex_shrink_0()
+                    .overflow_x_scroll()
+                    .border_b_1()
+                    .border_color(gpui::rgb(0x333333))
+                    .children(tabs)
+                    .child(
+                        div()
+                            .id("repo_tab_new")
+                            .flex()
+                            .items_center()
+                            .px(px(10.0))
+                            .text_color(gpui::rgb(0x888888))
+                            .cursor_pointer()
+                            .hover(|style| style.text_color(gpui::white()))
+                            .child("+")
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(|_this, _event, window, cx| {
+                                    window.dispatch_action(Box::new(OpenFile), cx);
+                                }),
+                            ),
+                    ),
+            )
+            .child(div().flex_1().min_h_0().child(self.active().clone()))
+    }
+}
@@ -0,0 +1,240 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use futures::channel::mpsc;
+use git2::Repository;
+use notify::event::EventKind;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::operations;
+
+/// How long the repository has to stay quiet before a burst of changes is reported.
+pub const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// The longest a change is held back while writes keep coming, e.g. during a checkout.
+pub const MAX_WAIT: Duration = Duration::from_secs(2);
+
+/// Files in `.git` that come and go with a merge, cherry-pick, revert or rebase.
+const OPERATION_FILES: &[&str] = &[
+    "MERGE_HEAD",
+    "CHERRY_PICK_HEAD",
+    "REVERT_HEAD",
+    "REBASE_HEAD",
+    "SQUASH_MSG",
+    operations::OPERATION_FILE,
+];
+const OPERATION_DIRS: &[&str] = &["rebase-merge", "rebase-apply"];
+
+/// Which parts of the repository changed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WatchChange {
+    /// HEAD, the branch it is on, tags or stashes; the graph is stale.
+    pub graph: bool,
+    /// Local branches, tags or stashes; the sidebar is stale.
+    pub refs: bool,
+    /// Remotes or their tracking branches; the sidebar and the remote manager are stale.
+    pub remotes: bool,
+    /// An operation started or stopped; the conflict view is stale.
+    pub operation: bool,
+    /// The index or the working tree; the uncommitted changes are stale.
+    pub worktree: bool,
+    /// A settings file; the limits and layout are stale.
+    pub settings: bool,
+}
+
+impl WatchChange {
+    pub fn any(&self) -> bool {
+        self.graph || self.refs || self.remotes || self.operation || self.worktree || self.settings
+    }
+
+    fn merge(&mut self, other: WatchChange) {
+        self.graph |= other.graph;
+        self.refs |= other.refs;
+        self.remotes |= other.remotes;
+        self.operation |= other.operation;
+        self.worktree |= other.worktree;
+        self.settings |= other.settings;
+    }
+}
+
+/// Tells what a changed path means for the views.
+pub struct Classifier {
+    repo: Repository,
+    git_dir: PathBuf,
+    workdir: Option<PathBuf>,
+    settings_files: Vec<PathBuf>,
+}
+
+impl Classifier {
+    pub fn new(repo: Repository, settings_files: &[PathBuf]) -> Self {
+        Self {
+            git_dir: canonical(repo.path()),
+            workdir: repo.workdir().map(canonical),
+            settings_files: settings_files.iter().map(|file| canonical(file)).collect(),
+            repo,
+        }
+    }
+
+    pub fn classify(&self, path: &Path) -> WatchChange {
+        let mut change = WatchChange::default();
+        if self.settings_files.iter().any(|file| file == path) {
+            change.settings = true;
+        }
+        if let Ok(relative) = path.strip_prefix(&self.git_dir) {
+            change.merge(self.classify_git(relative));
+        } else if let Some(relative) = self
+            .workdir
+            .as_ref()
+            .and_then(|workdir| path.strip_prefix(workdir).ok())
+            && !relative.as_os_str().is_empty()
+            && !relative.starts_with(".git")
+            && !self.repo.is_path_ignored(relative).unwrap_or(false)
+        {
+            change.worktree = true;
+        }
+        change
+    }
+
+    fn classify_git(&self, relative: &Path) -> WatchChange {
+        let mut change = WatchChange::default();
+        // lock files are renamed over the real ones, which report the change
+        if relative.extension().is_some_and(|ext| ext == "lock") {
+            return change;
+        }
+        let name = relative.to_string_lossy();
+        match name.as_ref() {
+            "HEAD" => {
+                change.graph = true;
+                change.refs = true;
+            }
+            // any ref may have been packed
+            "packed-refs" => {
+                change.graph = true;
+                change.refs = true;
+                change.remotes = true;
+            }
+            // remotes and upstreams are configured here
+            "config" => {
+                change.refs = true;
+                change.remotes = true;
+            }
+            "index" => change.worktree = true,
+            name if name.starts_with("refs/remotes/") => change.remotes = true,
+            name if name.starts_with("refs/heads/") => {
+                change.refs = true;
+                change.graph = self.head_branch().as_deref() == Some(name);
+            }
+            name if name.starts_with("refs/") => {
+                change.graph = true;
+                change.refs = true;
+            }
+            _ => {}
+        }
+        let top = relative.components().next().map(|top| top.as_os_str());
+        if OPERATION_FILES
+            .iter()
+            .any(|file| top == Some(file.as_ref()))
+            || OPERATION_DIRS.iter().any(|dir| top == Some(dir.as_ref()))
+        {
+            change.operation = true;
+        }
+        change
+    }
+
+    /// The ref HEAD points at, read from disk since it may just have moved.
+    fn head_branch(&self) -> Option<String> {
+        let head = std::fs::read_to_string(self.git_dir.join("HEAD")).ok()?;
+        Some(head.strip_prefix("ref: ")?.trim().to_string())
+    }
+}
+
+/// Watch the repository at `git_dir`, its working tree and `settings_files`, sending
+/// what each file event changed. Events stop once the watcher is dropped.
+pub fn watch(
+    git_dir: &Path,
+    settings_files: &[PathBuf],
+) -> Result<(RecommendedWatcher, mpsc::UnboundedReceiver<WatchChange>), notify::Error> {
+    let repo = Repository::open(git_dir).map_err(|e| notify::Error::generic(e.message()))?;
+    let classifier = Classifier::new(repo, settings_files);
+    let mut roots: Vec<PathBuf> = classifier.workdir.iter().cloned().collect();
+    if !roots
+        .iter()
+        .any(|root| classifier.git_dir.starts_with(root))
+    {
+        roots.push(classifier.git_dir.clone());
+    }
+    // settings files may not exist yet, so watch the folders they would be created in
+    let folders: Vec<PathBuf> = classifier
+        .settings_files
+        .iter()
+        .filter_map(|file| file.parent())
+        .filter(|folder| folder.is_dir() && !roots.iter().any(|root| folder.starts_with(root)))
+        .map(Path::to_path_buf)
+        .collect();
+
+    let (sender, receiver) = mpsc::unbounded();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        // reads, including gpig's own, change nothing
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+        let mut change = WatchChange::default();
+        for path in &event.paths {
+            change.merge(classifier.classify(path));
+        }
+        if change.any() {
+            let _ = sender.unbounded_send(change);
+        }
+    })?;
+    for root in &roots {
+        watcher.watch(root, RecursiveMode::Recursive)?;
+    }
+    for folder in &folders {
+        watcher.watch(folder, RecursiveMode::NonRecursive)?;
+    }
+    Ok((watcher, receiver))
+}
+
+/// `path` as file events name it; a file that does not exist yet goes by its folder.
+fn canonical(path: &Path) -> PathBuf {
+    if let Ok(path) = std::fs::canonicalize(path) {
+        return path;
+    }
+    match (path.parent().map(std::fs::canonicalize), path.file_name()) {
+        (Some(Ok(folder)), Some(name)) => folder.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Holds changes back until the repository has been quiet for `DEBOUNCE`, so a burst of
+/// writes from a checkout or a rebase refreshes once, but never longer than `MAX_WAIT`.
+#[derive(Debug, Default)]
+pub struct Debouncer {
+    pending: WatchChange,
+    /// When the oldest pending change arrived.
+    since: Option<Instant>,
+}
+
+impl Debouncer {
+    pub fn add(&mut self, change: WatchChange, now: Instant) {
+        if change.any() {
+            self.pending.merge(change);
+            self.since.get_or_insert(now);
+        }
+    }
+
+    /// How long to wait for another change before reporting the pending ones, `None`
+    /// while nothing is pending.
+    pub fn wait(&self, now: Instant) -> Option<Duration> {
+        let since = self.since?;
+        Some(DEBOUNCE.min((since + MAX_WAIT).saturating_duration_since(now)))
+    }
+
+    pub fn take(&mut self) -> WatchChange {
+        self.since = None;
+        std::mem::take(&mut self.pending)
+    }
+}
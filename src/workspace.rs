@@ -1,57 +1,2929 @@
+use std::path::{Path, PathBuf};
+
+use futures::StreamExt;
+use futures::channel::mpsc;
+use futures::future::{self, Either};
 use gpui::prelude::*;
 use gpui::{
-    AnyElement, AppContext, Context, Entity, EventEmitter, InteractiveElement, IntoElement,
-    MouseButton, ParentElement, Render, Styled, Window, div, px,
+    AnyElement, App, AppContext, Context, Entity, EventEmitter, Focusable, InteractiveElement,
+    IntoElement, MouseButton, ParentElement, Render, Styled, Task, Window, div, px,
+};
+
+use crate::actions::{OpenFile, Quit};
+use crate::conflict_view::{ConflictView, ConflictViewEvent};
+use crate::credentials::{CredentialAnswer, CredentialCache, CredentialChain, CredentialRequest};
+use crate::error::GpigError;
+use crate::garph::{
+    ChangedFile, CommitContextMenu, CommitOutsideGraph, CommitSelected, Garph, RecentRepoAction,
+    RepoPathChanged,
+};
+use crate::init::{self, GitignoreTemplate, InitOptions};
+use crate::menu::{DropdownEvent, MenuBar};
+use crate::modal::{
+    BUTTON_DANGER, BUTTON_NEUTRAL, BUTTON_PRIMARY, checkbox, field_label, modal_button,
+    render_modal,
+};
+use crate::operations::{
+    self, CommitOperation, ConflictState, MergeMode, MergePreview, OperationKind, OperationOutcome,
+    ResetMode,
+};
+use crate::rebase::{self, RebaseOutcome, RebaseProgress};
+use crate::rebase_planner::{RebasePlanner, RebasePlannerEvent};
+use crate::recent::{RecentRepo, RecentRepos};
+use crate::remote::{self, CloneOptions, PullOutcome, PushOutcome, SyncAction, TransferProgress};
+use crate::remote_manager::{RemoteManager, RemoteManagerEvent};
+use crate::restore::WorkdirHunk;
+use crate::settings::{SettingField, Settings, SortOrder};
+use crate::sidebar::{
+    ManageRemotesRequested, RefSelected, Sidebar, StashSaveRequested, SyncRequested,
 };
+use crate::stash::StashOutcome;
+use crate::text_input::TextInput;
+use crate::title::{QuitClicked, TitleBar};
+use crate::watcher::{self, Debouncer, WatchChange};
+
+struct MergeForm {
+    oid: git2::Oid,
+    mode: MergeMode,
+    preview: Result<MergePreview, String>,
+}
+
+struct ResetForm {
+    oid: git2::Oid,
+    mode: ResetMode,
+    changes: Vec<String>,
+}
+
+/// What the network half of a fetch, pull or push produced.
+enum SyncDone {
+    Fetched(Vec<String>),
+    Pulled(PullOutcome),
+    Pushed(PushOutcome),
+    Pruned(Vec<String>),
+    Cloned(PathBuf),
+}
+
+enum SyncMessage {
+    Progress(TransferProgress),
+    /// A pull is rebasing onto the fetched upstream.
+    Rebase(RebaseProgress),
+    Prompt(
+        CredentialRequest,
+        std::sync::mpsc::Sender<Option<CredentialAnswer>>,
+    ),
+}
+
+struct CredentialForm {
+    request: CredentialRequest,
+    username: Entity<TextInput>,
+    secret: Entity<TextInput>,
+    remember: bool,
+    reply: std::sync::mpsc::Sender<Option<CredentialAnswer>>,
+}
+
+/// Asks for the repository at `path` to be shown: in its tab when one has it open,
+/// otherwise in a new tab, so this one keeps its repository and background work.
+#[derive(Clone, Debug)]
+pub struct OpenRepository {
+    pub path: PathBuf,
+}
+
+/// Whether a notice reports a finished action or a failure.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoticeLevel {
+    Info,
+    Error,
+}
+
+struct SyncStatus {
+    label: String,
+    progress: Option<TransferProgress>,
+    running: bool,
+}
+
+struct DiscardForm {
+    path: String,
+    hunks: Vec<WorkdirHunk>,
+    error: Option<String>,
+}
+
+struct RestoreForm {
+    file: ChangedFile,
+    /// The commit the file is restored from, or from before.
+    commit: git2::Oid,
+    before: bool,
+    error: Option<String>,
+}
+
+struct CloneForm {
+    url: Entity<TextInput>,
+    destination: Entity<TextInput>,
+    branch: Entity<TextInput>,
+    depth: Entity<TextInput>,
+    recursive: bool,
+    error: Option<String>,
+}
+
+struct InitForm {
+    path: Entity<TextInput>,
+    branch: Entity<TextInput>,
+    bare: bool,
+    gitignore: Option<GitignoreTemplate>,
+    error: Option<String>,
+}
+
+struct SettingsForm {
+    inputs: Vec<(SettingField, Entity<TextInput>)>,
+    sort: SortOrder,
+    /// Save to the repository's `gpig.toml` instead of the user file.
+    for_repo: bool,
+    error: Option<String>,
+}
+
+struct StashForm {
+    message: Entity<TextInput>,
+    include_untracked: bool,
+    error: Option<String>,
+}
+
+struct TagForm {
+    oid: git2::Oid,
+    name: Entity<TextInput>,
+    message: Entity<TextInput>,
+    annotated: bool,
+    error: Option<String>,
+}
+
+pub struct Workspace {
+    dock: Option<Entity<Garph>>,
+    title_bar: Entity<TitleBar>,
+    menu_bar: Entity<MenuBar>,
+    sidebar: Entity<Sidebar>,
+    selected_commit: Option<CommitSelected>,
+    changed_files: Vec<ChangedFile>,
+    /// Why the files of the selected commit could not be listed.
+    files_error: Option<String>,
+    selected_file: Option<usize>,
+    file_diff: Option<String>,
+    active_pane: ActivePane,
+    loading_diff: bool,
+    loading_files: bool,
+    /// Background work for the selected commit and file; replacing a task cancels it.
+    files_task: Option<Task<()>>,
+    diff_task: Option<Task<()>>,
+    current_commit_oid: Option<git2::Oid>,
+    context_menu: Option<CommitContextMenu>,
+    tag_form: Option<TagForm>,
+    merge_form: Option<MergeForm>,
+    reset_form: Option<ResetForm>,
+    stash_form: Option<StashForm>,
+    clone_form: Option<CloneForm>,
+    init_form: Option<InitForm>,
+    discard_form: Option<DiscardForm>,
+    restore_form: Option<RestoreForm>,
+    conflict_view: Option<Entity<ConflictView>>,
+    rebase_planner: Option<Entity<RebasePlanner>>,
+    remote_manager: Option<Entity<RemoteManager>>,
+    notice: Option<(NoticeLevel, String)>,
+    sync_status: Option<SyncStatus>,
+    sync_task: Option<Task<()>>,
+    /// A rebase running on a background thread, shown like a sync.
+    rebase_status: Option<SyncStatus>,
+    rebase_task: Option<Task<()>>,
+    /// Reports changes made to the repository outside gpig.
+    watch_task: Option<Task<()>>,
+    /// Uncommitted changes for the working tree row, `None` until loaded.
+    uncommitted: Option<Vec<String>>,
+    uncommitted_task: Option<Task<()>>,
+    credential_form: Option<CredentialForm>,
+    credential_cache: CredentialCache,
+    recent: RecentRepos,
+    /// Where `recent` is saved; `None` keeps it in memory only.
+    recent_file: Option<PathBuf>,
+    recent_menu_open: bool,
+    /// User settings merged with the repository's.
+    settings: Settings,
+    /// The user settings file; `None` uses only the repository's.
+    settings_file: Option<PathBuf>,
+    settings_form: Option<SettingsForm>,
+    // pane: Vec<Entity<AnyElement>>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ActivePane {
+    Dock,
+    Content,
+}
+
+impl Workspace {
+    pub fn new(dock: Option<Entity<Garph>>, cx: &mut Context<Self>) -> Self {
+        let dock_clone = dock.clone();
+
+        let menu_bar = cx.new(|_| MenuBar::new());
+        let title_bar = cx.new(|_| TitleBar::new("Dark Pig Git"));
+        let sidebar = cx.new(|_| Sidebar::new());
+
+        cx.subscribe(&sidebar, Self::on_ref_selected).detach();
+        cx.subscribe(&sidebar, Self::on_stash_save_requested)
+            .detach();
+        cx.subscribe(&sidebar, Self::on_sync_requested).detach();
+        cx.subscribe(&sidebar, Self::on_manage_remotes_requested)
+            .detach();
+        if let Some(dock) = &dock {
+            cx.subscribe(dock, Self::on_repo_path_changed).detach();
+            cx.subscribe(dock, Self::on_commit_selected).detach();
+            cx.subscribe(dock, Self::on_commit_context_menu).detach();
+            cx.subscribe(dock, Self::on_recent_repo_action).detach();
+            cx.subscribe(dock, Self::on_commit_outside_graph).detach();
+        }
+
+        let mut workspace = Self {
+            dock: dock_clone,
+            title_bar,
+            menu_bar,
+            sidebar,
+            selected_commit: None,
+            changed_files: Vec::new(),
+            files_error: None,
+            selected_file: None,
+            file_diff: None,
+            active_pane: ActivePane::Content,
+            loading_diff: false,
+            loading_files: false,
+            files_task: None,
+            diff_task: None,
+            current_commit_oid: None,
+            context_menu: None,
+            tag_form: None,
+            merge_form: None,
+            reset_form: None,
+            stash_form: None,
+            clone_form: None,
+            init_form: None,
+            discard_form: None,
+            restore_form: None,
+            conflict_view: None,
+            rebase_planner: None,
+            remote_manager: None,
+            notice: None,
+            sync_status: None,
+            sync_task: None,
+            rebase_status: None,
+            rebase_task: None,
+            watch_task: None,
+            uncommitted: None,
+            uncommitted_task: None,
+            credential_form: None,
+            credential_cache: CredentialCache::new(),
+            recent: RecentRepos::default(),
+            recent_file: None,
+            recent_menu_open: false,
+            settings: Settings::default(),
+            settings_file: None,
+            settings_form: None,
+        };
+        workspace.reload_settings(cx);
+        workspace.refresh_refs(cx);
+        workspace.refresh_conflict_state(cx);
+        workspace.refresh_uncommitted(cx);
+        workspace.start_watching(cx);
+        workspace
+    }
+
+    /// Watch the repository for file events and refresh what changed on disk once they
+    /// settle.
+    fn start_watching(&mut self, cx: &mut Context<Self>) {
+        self.watch_task = None;
+        let Some(git_dir) = self.dock.as_ref().and_then(|dock| dock.read(cx).git_dir()) else {
+            return;
+        };
+        let (watcher, mut changes) = match watcher::watch(&git_dir, &self.settings_files(cx)) {
+            Ok(watch) => watch,
+            Err(e) => {
+                self.set_notice(format!("Failed to watch the repository: {}", e), cx);
+                return;
+            }
+        };
+        let executor = cx.background_executor().clone();
+        self.watch_task = Some(cx.spawn(async move |this, cx| {
+            // the events stop when the watcher is dropped with this task
+            let _watcher = watcher;
+            let mut debouncer = Debouncer::default();
+            loop {
+                let change = match debouncer.wait(executor.now()) {
+                    None => changes.next().await,
+                    Some(wait) => {
+                        match future::select(executor.timer(wait), changes.next()).await {
+                            Either::Left(_) => {
+                                let settled = debouncer.take();
+                                if this
+                                    .update(cx, |this, cx| this.on_repo_changed(settled, cx))
+                                    .is_err()
+                                {
+                                    return;
+                                }
+                                continue;
+                            }
+                            Either::Right((change, _)) => change,
+                        }
+                    }
+                };
+                let Some(change) = change else {
+                    return;
+                };
+                debouncer.add(change, executor.now());
+            }
+        }));
+    }
+
+    /// Refresh the views a change on disk affects, keeping the selected commit while it exists.
+    fn on_repo_changed(&mut self, change: WatchChange, cx: &mut Context<Self>) {
+        if change.settings {
+            self.reload_settings(cx);
+        }
+        if change.graph {
+            if let Some(dock) = &self.dock {
+                dock.update(cx, |garph, cx| garph.refresh(cx));
+            }
+            if let (Some(dock), Some(commit)) = (&self.dock, &self.selected_commit)
+                && !dock.read(cx).has_commit(commit.oid)
+            {
+                self.clear_selection(cx);
+            }
+        }
+        if change.refs || change.remotes {
+            self.refresh_refs(cx);
+        }
+        if change.remotes {
+            self.refresh_remote_manager(cx);
+        }
+        if change.worktree {
+            self.refresh_uncommitted(cx);
+        }
+        // a background rebase or pull shows its own outcome once it finishes
+        let busy = self.rebase_task.is_some() || self.sync_task.is_some();
+        if (change.operation || change.worktree) && !busy {
+            self.refresh_conflict_state(cx);
+        }
+    }
+
+    /// Load the uncommitted changes for the working tree row in the background.
+    fn refresh_uncommitted(&mut self, cx: &mut Context<Self>) {
+        let Some(git_dir) = self.dock.as_ref().and_then(|dock| dock.read(cx).git_dir()) else {
+            self.uncommitted = None;
+            self.uncommitted_task = None;
+            return;
+        };
+        let changes = cx.background_executor().spawn(async move {
+            git2::Repository::open(&git_dir)
+                .and_then(|repo| operations::uncommitted_changes(&repo))
+                .ok()
+        });
+        self.uncommitted_task = Some(cx.spawn(async move |this, cx| {
+            let changes = changes.await;
+            this.update(cx, |this, cx| {
+                this.uncommitted = changes;
+                this.uncommitted_task = None;
+                cx.notify();
+            })
+            .ok();
+        }));
+    }
+
+    fn render_worktree_row(&self) -> Option<impl IntoElement> {
+        let changes = self.uncommitted.as_ref()?;
+        let summary = match changes.len() {
+            0 => "Working tree clean".to_string(),
+            1 => "1 uncommitted change".to_string(),
+            count => format!("{} uncommitted changes", count),
+        };
+        Some(
+            div()
+                .flex()
+                .flex_col()
+                .px(px(12.0))
+                .py(px(6.0))
+                .bg(gpui::rgb(0x252525))
+                .border_b_1()
+                .border_color(gpui::rgb(0x333333))
+                .child(
+                    div()
+                        .text_size(px(12.0))
+                        .text_color(if changes.is_empty() {
+                            gpui::rgb(0x888888)
+                        } else {
+                            gpui::rgb(0xF1C40F)
+                        })
+                        .child(summary),
+                )
+                .children(changes.iter().take(3).map(|change| {
+                    div()
+                        .text_size(px(11.0))
+                        .text_color(gpui::rgb(0x969696))
+                        .child(change.clone())
+                }))
+                .when(changes.len() > 3, |this| {
+                    this.child(
+                        div()
+                            .text_size(px(11.0))
+                            .text_color(gpui::rgb(0x666666))
+                            .child(format!("and {} more", changes.len() - 3)),
+                    )
+                }),
+        )
+    }
+
+    /// Uncommitted changes as of the last refresh, as `"M path"` lines.
+    pub fn uncommitted(&self) -> Option<&[String]> {
+        self.uncommitted.as_deref()
+    }
+
+    fn refresh_refs(&mut self, cx: &mut Context<Self>) {
+        let Some(dock) = &self.dock else {
+            return;
+        };
+        let refs = match dock.read(cx).list_refs() {
+            Ok(refs) => refs,
+            Err(e) => {
+                self.set_notice(format!("Failed to list refs: {}", e), cx);
+                Default::default()
+            }
+        };
+        self.sidebar
+            .update(cx, |sidebar, cx| sidebar.set_refs(refs, cx));
+    }
+
+    fn on_repo_path_changed(
+        &mut self,
+        _garph: Entity<Garph>,
+        _event: &RepoPathChanged,
+        cx: &mut Context<Self>,
+    ) {
+        self.clear_selection(cx);
+        self.remote_manager = None;
+        self.refresh_refs(cx);
+        self.refresh_conflict_state(cx);
+        self.uncommitted = None;
+        self.refresh_uncommitted(cx);
+        self.reload_settings(cx);
+        self.start_watching(cx);
+        self.remember_current_repo(cx);
+    }
+
+    /// Load the recent repositories from `file`, dropping the ones that are gone, and
+    /// record the repository already open.
+    pub fn set_recent_file(&mut self, file: PathBuf, cx: &mut Context<Self>) {
+        self.recent_file = Some(file);
+        self.update_recent(
+            |recent| {
+                recent.prune_missing();
+            },
+            cx,
+        );
+        self.remember_current_repo(cx);
+    }
+
+    pub fn recent(&self) -> Vec<RecentRepo> {
+        self.recent.entries()
+    }
+
+    pub fn notice(&self) -> Option<&str> {
+        self.notice.as_ref().map(|(_, notice)| notice.as_str())
+    }
+
+    pub fn notice_level(&self) -> Option<NoticeLevel> {
+        self.notice.as_ref().map(|(level, _)| *level)
+    }
+
+    pub fn dock(&self) -> Option<&Entity<Garph>> {
+        self.dock.as_ref()
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Use `file` for the user settings and watch it for edits.
+    pub fn set_settings_file(&mut self, file: PathBuf, cx: &mut Context<Self>) {
+        self.settings_file = Some(file);
+        self.reload_settings(cx);
+        self.start_watching(cx);
+    }
+
+    /// The user file, then the repository file whose keys win.
+    fn settings_files(&self, cx: &App) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = self.settings_file.iter().cloned().collect();
+        files.extend(self.repo_settings_file(cx));
+        files
+    }
+
+    /// The settings file kept in the git dir of the repository shown.
+    fn repo_settings_file(&self, cx: &App) -> Option<PathBuf> {
+        let git_dir = self.dock.as_ref()?.read(cx).git_dir()?;
+        Some(Settings::repo_file(&git_dir))
+    }
+
+    /// Read the settings files again and hand the result to the graph. A broken file
+    /// keeps the settings in use.
+    pub fn reload_settings(&mut self, cx: &mut Context<Self>) {
+        match Settings::load(&self.settings_files(cx)) {
+            Ok(settings) => {
+                if settings == self.settings {
+                    return;
+                }
+                self.settings = settings.clone();
+                if let Some(dock) = &self.dock {
+                    dock.update(cx, |garph, cx| garph.set_settings(settings, cx));
+                }
+                cx.notify();
+            }
+            Err(e) => self.set_notice(format!("Settings not applied: {}", e), cx),
+        }
+    }
+
+    pub fn open_settings_form(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let for_repo = self.settings_file.is_none()
+            || self
+                .repo_settings_file(cx)
+                .is_some_and(|file| file.exists());
+        let inputs = SettingField::ALL
+            .into_iter()
+            .map(|field| (field, cx.new(|cx| TextInput::new(field.label(), cx))))
+            .collect::<Vec<_>>();
+        if let Some((_, first)) = inputs.first() {
+            window.focus(&first.focus_handle(cx));
+        }
+        self.settings_form = Some(SettingsForm {
+            inputs,
+            sort: SortOrder::default(),
+            for_repo,
+            error: None,
+        });
+        self.fill_settings_form(cx);
+    }
+
+    /// The settings that apply at the form's scope, before the form's edits.
+    fn scope_settings(&self, for_repo: bool, cx: &App) -> Result<Settings, String> {
+        if for_repo {
+            Settings::load(&self.settings_files(cx))
+        } else {
+            Settings::load(&self.settings_file.iter().cloned().collect::<Vec<_>>())
+        }
+    }
+
+    fn fill_settings_form(&mut self, cx: &mut Context<Self>) {
+        let Some(for_repo) = self.settings_form.as_ref().map(|form| form.for_repo) else {
+            return;
+        };
+        let settings = self
+            .scope_settings(for_repo, cx)
+            .unwrap_or_else(|_| self.settings.clone());
+        let Some(form) = &mut self.settings_form else {
+            return;
+        };
+        form.sort = settings.history.sort;
+        for (field, input) in &form.inputs {
+            input.update(cx, |input, cx| input.set_text(field.value(&settings), cx));
+        }
+        cx.notify();
+    }
+
+    fn submit_settings_form(&mut self, cx: &mut Context<Self>) {
+        let Some(form) = &self.settings_form else {
+            return;
+        };
+        let for_repo = form.for_repo;
+        let mut settings = Settings::default();
+        settings.history.sort = form.sort;
+        let result = form
+            .inputs
+            .iter()
+            .try_for_each(|(field, input)| field.apply(&mut settings, &input.read(cx).text()))
+            .and_then(|_| settings.validate())
+            .and_then(|_| self.save_settings(&settings, for_repo, cx));
+        match result {
+            Ok(file) => {
+                self.settings_form = None;
+                self.reload_settings(cx);
+                self.set_notice(format!("Saved settings to {}", file.display()), cx);
+            }
+            Err(e) => {
+                if let Some(form) = &mut self.settings_form {
+                    form.error = Some(e);
+                }
+            }
+        }
+        cx.notify();
+    }
+
+    /// Write `settings` to the user file, or just the keys differing from the user
+    /// settings to the repository file.
+    fn save_settings(
+        &self,
+        settings: &Settings,
+        for_repo: bool,
+        cx: &App,
+    ) -> Result<PathBuf, String> {
+        let (file, result) = if for_repo {
+            let Some(file) = self.repo_settings_file(cx) else {
+                return Err("Open a repository to give it its own settings".to_string());
+            };
+            let base = self.scope_settings(false, cx)?;
+            let result = settings.save_overrides(&base, &file);
+            (file, result)
+        } else {
+            let Some(file) = self.settings_file.clone() else {
+                return Err("There is no user settings file".to_string());
+            };
+            let result = settings.save(&file);
+            (file, result)
+        };
+        result
+            .map(|_| file.clone())
+            .map_err(|e| format!("Failed to write {}: {}", file.display(), e))
+    }
+
+    fn render_settings_form(&self, form: &SettingsForm, cx: &mut Context<Self>) -> AnyElement {
+        let scope = |id: &'static str, label: &'static str, for_repo: bool| {
+            checkbox(id, label, form.for_repo == for_repo).on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _event, _window, cx| {
+                    if let Some(form) = &mut this.settings_form
+                        && form.for_repo != for_repo
+                    {
+                        form.for_repo = for_repo;
+                        form.error = None;
+                        this.fill_settings_form(cx);
+                    }
+                }),
+            )
+        };
+        let body = div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .child(field_label("Save for"))
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .gap_4()
+                    .child(scope("settings_scope_user", "All repositories", false))
+                    .child(scope("settings_scope_repo", "This repository", true)),
+            )
+            .children(form.inputs.iter().map(|(field, input)| {
+                div()
+                    .flex()
+                    .flex_row()
+                    .items_center()
+                    .gap_2()
+                    .child(div().w(px(200.0)).child(field_label(field.label())))
+                    .child(div().flex_1().child(input.clone()))
+            }))
+            .child(field_label("Graph order"))
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .gap_4()
+                    .children(SortOrder::ALL.into_iter().map(|sort| {
+                        checkbox(
+                            gpui::SharedString::from(format!("settings_sort_{:?}", sort)),
+                            sort.label(),
+                            form.sort == sort,
+                        )
+                        .on_mouse_down(
+                            MouseButton::Left,
+                            cx.listener(move |this, _event, _window, cx| {
+                                if let Some(form) = &mut this.settings_form {
+                                    form.sort = sort;
+                                }
+                                cx.notify();
+                            }),
+                        )
+                    })),
+            )
+            .when_some(form.error.clone(), |this, error| {
+                this.child(
+                    div()
+                        .text_color(gpui::rgb(0xE74C3C))
+                        .text_size(px(12.0))
+                        .child(error),
+                )
+            });
+
+        render_modal(
+            "Settings",
+            body,
+            vec![
+                modal_button("settings_cancel", "Cancel", BUTTON_NEUTRAL)
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _event, _window, cx| {
+                            this.settings_form = None;
+                            cx.notify();
+                        }),
+                    )
+                    .into_any(),
+                modal_button("settings_submit", "Save", BUTTON_PRIMARY)
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _event, _window, cx| {
+                            this.submit_settings_form(cx);
+                        }),
+                    )
+                    .into_any(),
+            ],
+        )
+    }
+
+    /// Working tree of the repository shown, or its git dir when bare.
+    pub fn repo_path(&self, cx: &App) -> Option<PathBuf> {
+        self.dock.as_ref()?.read(cx).workdir()
+    }
+
+    /// Pick up entries other tabs have saved since.
+    pub fn reload_recent(&mut self, cx: &mut Context<Self>) {
+        self.update_recent(|_| {}, cx);
+    }
+
+    fn remember_current_repo(&mut self, cx: &mut Context<Self>) {
+        let Some(path) = self.repo_path(cx) else {
+            return;
+        };
+        let now = chrono::Utc::now().timestamp();
+        self.update_recent(|recent| recent.record(&path, now), cx);
+    }
+
+    /// Apply `change` to the latest saved list, write it out and show it on the empty
+    /// screen. Reading first keeps edits from other tabs.
+    fn update_recent(&mut self, change: impl FnOnce(&mut RecentRepos), cx: &mut Context<Self>) {
+        if let Some(file) = &self.recent_file {
+            self.recent = RecentRepos::load(file);
+        }
+        change(&mut self.recent);
+        if let Some(file) = &self.recent_file
+            && let Err(e) = self.recent.save(file)
+        {
+            self.set_notice(format!("Failed to save recent repositories: {}", e), cx);
+        }
+        let entries = self.recent.entries();
+        if let Some(dock) = &self.dock {
+            dock.update(cx, |garph, cx| garph.set_recent(entries, cx));
+        }
+        cx.notify();
+    }
+
+    fn on_recent_repo_action(
+        &mut self,
+        _garph: Entity<Garph>,
+        event: &RecentRepoAction,
+        cx: &mut Context<Self>,
+    ) {
+        match event {
+            RecentRepoAction::Open(path) => self.open_recent(path, cx),
+            RecentRepoAction::Pin(path, pinned) => {
+                self.update_recent(|recent| recent.set_pinned(path, *pinned), cx)
+            }
+            RecentRepoAction::Remove(path) => self.update_recent(|recent| recent.remove(path), cx),
+        }
+    }
+
+    /// Show a recent repository in its own tab, forgetting it when it has disappeared.
+    pub fn open_recent(&mut self, path: &Path, cx: &mut Context<Self>) {
+        if !path.exists() {
+            self.update_recent(|recent| recent.remove(path), cx);
+            self.set_notice(
+                format!(
+                    "{} no longer exists and was removed from the recent list",
+                    path.display()
+                ),
+                cx,
+            );
+            return;
+        }
+        cx.emit(OpenRepository {
+            path: path.to_path_buf(),
+        });
+    }
+
+    fn clear_selection(&mut self, cx: &mut Context<Self>) {
+        self.changed_files.clear();
+        self.files_error = None;
+        self.selected_file = None;
+        self.file_diff = None;
+        self.current_commit_oid = None;
+        self.files_task = None;
+        self.diff_task = None;
+        self.loading_files = false;
+        self.loading_diff = false;
+        self.set_selected_commit(None, cx);
+    }
+
+    /// Show the conflict view when the repository is stopped inside an operation.
+    fn refresh_conflict_state(&mut self, cx: &mut Context<Self>) {
+        let Some(dock) = &self.dock else {
+            return;
+        };
+        match dock.read(cx).conflict_state() {
+            Ok(Some(state)) => self.show_conflicts(state, cx),
+            Ok(None) => self.conflict_view = None,
+            Err(_) => self.conflict_view = None,
+        }
+        cx.notify();
+    }
+
+    fn show_conflicts(&mut self, state: ConflictState, cx: &mut Context<Self>) {
+        if let Some(view) = &self.conflict_view {
+            view.update(cx, |view, cx| view.set_state(state, cx));
+        } else {
+            let view = cx.new(|_| ConflictView::new(state));
+            cx.subscribe(&view, Self::on_conflict_view_event).detach();
+            self.conflict_view = Some(view);
+        }
+        cx.notify();
+    }
+
+    fn on_conflict_view_event(
+        &mut self,
+        view: Entity<ConflictView>,
+        event: &ConflictViewEvent,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(dock) = self.dock.clone() else {
+            return;
+        };
+        match event {
+            ConflictViewEvent::Continue if view.read(cx).kind() == OperationKind::Rebase => {
+                self.run_rebase(
+                    rebase::continue_rebase,
+                    move |this, result, cx| this.handle_rebase_view_result(&view, result, cx),
+                    cx,
+                );
+            }
+            ConflictViewEvent::Skip => {
+                self.run_rebase(
+                    rebase::skip_rebase,
+                    move |this, result, cx| this.handle_rebase_view_result(&view, result, cx),
+                    cx,
+                );
+            }
+            ConflictViewEvent::Continue if view.read(cx).kind() == OperationKind::StashApply => {
+                // nothing to commit; the stash stays in the list until dropped
+                self.refresh_conflict_state(cx);
+                if self.conflict_view.is_some() {
+                    view.update(cx, |view, cx| {
+                        view.set_error("Resolve all conflicted files first", cx)
+                    });
+                } else {
+                    self.refresh_repo_views(cx);
+                    self.set_notice("Stash applied. It was kept; drop it once you are done", cx);
+                }
+            }
+            ConflictViewEvent::Continue => match dock.read(cx).continue_operation() {
+                Ok(oid) => {
+                    self.conflict_view = None;
+                    self.refresh_repo_views(cx);
+                    dock.update(cx, |garph, cx| garph.select_commit(oid, cx));
+                }
+                Err(e) => view.update(cx, |view, cx| view.set_error(e.to_string(), cx)),
+            },
+            ConflictViewEvent::Abort => match dock.read(cx).abort_operation() {
+                Ok(()) => {
+                    self.conflict_view = None;
+                    self.refresh_repo_views(cx);
+                }
+                Err(e) => view.update(cx, |view, cx| view.set_error(e.to_string(), cx)),
+            },
+            ConflictViewEvent::OpenPath(path) => match dock.read(cx).load_conflict(path) {
+                Ok(file) => view.update(cx, |view, cx| view.set_file(file, cx)),
+                Err(e) => view.update(cx, |view, cx| view.set_error(e.to_string(), cx)),
+            },
+            ConflictViewEvent::MarkResolved(file) => match dock.read(cx).mark_resolved(file) {
+                Ok(()) => self.refresh_conflict_state(cx),
+                Err(e) => view.update(cx, |view, cx| view.set_error(e.to_string(), cx)),
+            },
+        }
+        cx.notify();
+    }
+
+    /// Like `handle_rebase_result`, but reports failures inside the conflict view.
+    fn handle_rebase_view_result(
+        &mut self,
+        view: &Entity<ConflictView>,
+        result: Result<RebaseOutcome, GpigError>,
+        cx: &mut Context<Self>,
+    ) {
+        match result {
+            Err(e) => view.update(cx, |view, cx| view.set_error(e.to_string(), cx)),
+            result => self.handle_rebase_result(result, cx),
+        }
+    }
+
+    /// Report a failure in the notice bar.
+    pub fn set_notice(&mut self, notice: impl Into<String>, cx: &mut Context<Self>) {
+        self.notice = Some((NoticeLevel::Error, notice.into()));
+        cx.notify();
+    }
+
+    /// Report something that went as asked in the notice bar.
+    fn set_info(&mut self, notice: impl Into<String>, cx: &mut Context<Self>) {
+        self.notice = Some((NoticeLevel::Info, notice.into()));
+        cx.notify();
+    }
+
+    fn run_commit_operation(
+        &mut self,
+        operation: CommitOperation,
+        oid: git2::Oid,
+        mainline: Option<u32>,
+        cx: &mut Context<Self>,
+    ) {
+        self.context_menu = None;
+        let Some(dock) = &self.dock else {
+            return;
+        };
+
+        let result = match operation {
+            CommitOperation::CherryPick => dock.read(cx).cherry_pick(&oid, mainline),
+            CommitOperation::Revert => dock.read(cx).revert(&oid, mainline),
+        };
+        self.handle_operation_result(operation.kind(), result, cx);
+    }
+
+    /// Select the new commit on success or hand over to the conflict view.
+    fn handle_operation_result(
+        &mut self,
+        kind: OperationKind,
+        result: Result<OperationOutcome, GpigError>,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(dock) = self.dock.clone() else {
+            return;
+        };
+        match result {
+            Ok(OperationOutcome::Committed(new_oid)) => {
+                self.refresh_repo_views(cx);
+                dock.update(cx, |garph, cx| garph.select_commit(new_oid, cx));
+            }
+            Ok(OperationOutcome::Conflicts(paths)) => {
+                self.refresh_repo_views(cx);
+                self.show_conflicts(
+                    ConflictState {
+                        kind,
+                        paths,
+                        progress: None,
+                    },
+                    cx,
+                );
+            }
+            Err(e) => self.set_notice(format!("{} failed: {}", kind.label(), e), cx),
+        }
+        cx.notify();
+    }
+
+    fn rebase_onto(&mut self, oid: git2::Oid, cx: &mut Context<Self>) {
+        self.context_menu = None;
+        self.run_rebase(
+            move |repo, progress| rebase::rebase_onto(repo, oid, progress),
+            Self::handle_rebase_result,
+            cx,
+        );
+    }
+
+    /// Run a rebase step on the background executor, showing its progress like a sync,
+    /// and hand the outcome to `done`.
+    fn run_rebase(
+        &mut self,
+        work: impl FnOnce(
+            &git2::Repository,
+            &mut dyn FnMut(RebaseProgress),
+        ) -> Result<RebaseOutcome, git2::Error>
+        + Send
+        + 'static,
+        done: impl FnOnce(&mut Self, Result<RebaseOutcome, GpigError>, &mut Context<Self>) + 'static,
+        cx: &mut Context<Self>,
+    ) {
+        if self.rebase_task.is_some() {
+            self.set_notice("Another rebase is still running", cx);
+            return;
+        }
+        let Some(git_dir) = self.dock.as_ref().and_then(|dock| dock.read(cx).git_dir()) else {
+            return;
+        };
+
+        self.rebase_status = Some(SyncStatus {
+            label: "Rebasing".to_string(),
+            progress: None,
+            running: true,
+        });
+        let (sender, mut receiver) = mpsc::unbounded();
+        let work = cx.background_executor().spawn(async move {
+            let repo = git2::Repository::open(&git_dir)?;
+            work(&repo, &mut |progress| {
+                let _ = sender.unbounded_send(progress);
+            })
+        });
+        self.rebase_task = Some(cx.spawn(async move |this, cx| {
+            while let Some(progress) = receiver.next().await {
+                let _ = this.update(cx, |this, cx| {
+                    if let Some(status) = &mut this.rebase_status {
+                        status.label = format!("Rebasing, {}", progress.label());
+                    }
+                    cx.notify();
+                });
+            }
+            let result = work.await.map_err(GpigError::from);
+            let _ = this.update(cx, |this, cx| {
+                this.rebase_task = None;
+                this.rebase_status = None;
+                done(this, result, cx);
+            });
+        }));
+        cx.notify();
+    }
+
+    /// Show the rewritten branch once a rebase finishes, or the conflicts it stopped on.
+    fn handle_rebase_result(
+        &mut self,
+        result: Result<RebaseOutcome, GpigError>,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(dock) = self.dock.clone() else {
+            return;
+        };
+        match result {
+            Ok(RebaseOutcome::Finished { head, applied }) => {
+                self.conflict_view = None;
+                self.refresh_repo_views(cx);
+                dock.update(cx, |garph, cx| garph.select_commit(head, cx));
+                self.set_info(
+                    format!("Rebase finished, applied {} commit(s)", applied),
+                    cx,
+                );
+            }
+            Ok(RebaseOutcome::Stopped { progress, paths }) => {
+                self.refresh_repo_views(cx);
+                self.show_conflicts(
+                    ConflictState {
+                        kind: OperationKind::Rebase,
+                        paths,
+                        progress: Some(progress),
+                    },
+                    cx,
+                );
+            }
+            Err(e) => self.set_notice(format!("Rebase failed: {}", e), cx),
+        }
+        cx.notify();
+    }
+
+    fn open_rebase_planner(&mut self, base: git2::Oid, cx: &mut Context<Self>) {
+        self.context_menu = None;
+        let Some(dock) = &self.dock else {
+            return;
+        };
+        match dock.read(cx).plan_rebase(&base) {
+            Ok(steps) if steps.is_empty() => {
+                self.set_info("No commits above this one to rebase", cx)
+            }
+            Ok(steps) => {
+                let planner = cx.new(|cx| RebasePlanner::new(base, steps, cx));
+                cx.subscribe(&planner, Self::on_rebase_planner_event)
+                    .detach();
+                self.rebase_planner = Some(planner);
+            }
+            Err(e) => self.set_notice(format!("Interactive rebase failed: {}", e), cx),
+        }
+        cx.notify();
+    }
+
+    fn on_rebase_planner_event(
+        &mut self,
+        planner: Entity<RebasePlanner>,
+        event: &RebasePlannerEvent,
+        cx: &mut Context<Self>,
+    ) {
+        match event {
+            RebasePlannerEvent::Cancel => self.rebase_planner = None,
+            RebasePlannerEvent::Start { base, steps } => {
+                let (base, steps) = (*base, steps.clone());
+                self.run_rebase(
+                    move |repo, progress| {
+                        rebase::start_interactive_rebase(repo, base, &steps, progress)
+                    },
+                    move |this, result, cx| match result {
+                        Err(e) => {
+                            planner.update(cx, |planner, cx| planner.set_error(e.to_string(), cx))
+                        }
+                        result => {
+                            this.rebase_planner = None;
+                            this.handle_rebase_result(result, cx);
+                        }
+                    },
+                    cx,
+                );
+            }
+        }
+        cx.notify();
+    }
+
+    fn open_merge_form(&mut self, oid: git2::Oid, cx: &mut Context<Self>) {
+        self.context_menu = None;
+        let Some(dock) = &self.dock else {
+            return;
+        };
+        let preview = dock.read(cx).preview_merge(&oid).map_err(|e| e.to_string());
+        let mode = match &preview {
+            Ok(preview) if preview.can_fast_forward => MergeMode::FastForwardOnly,
+            _ => MergeMode::NoFastForward,
+        };
+        self.merge_form = Some(MergeForm { oid, mode, preview });
+        cx.notify();
+    }
+
+    fn submit_merge_form(&mut self, cx: &mut Context<Self>) {
+        let (Some(form), Some(dock)) = (self.merge_form.take(), &self.dock) else {
+            return;
+        };
+        let kind = match form.mode {
+            MergeMode::Squash => OperationKind::Squash,
+            _ => OperationKind::Merge,
+        };
+        let result = dock.read(cx).merge(&form.oid, form.mode);
+        self.handle_operation_result(kind, result, cx);
+    }
+
+    fn on_stash_save_requested(
+        &mut self,
+        _sidebar: Entity<Sidebar>,
+        _event: &StashSaveRequested,
+        cx: &mut Context<Self>,
+    ) {
+        if self.dock.is_none() {
+            return;
+        }
+        let message = cx.new(|cx| TextInput::new("Stash message (optional)", cx));
+        self.stash_form = Some(StashForm {
+            message,
+            include_untracked: false,
+            error: None,
+        });
+        cx.notify();
+    }
+
+    fn submit_stash_form(&mut self, cx: &mut Context<Self>) {
+        let (Some(form), Some(dock)) = (&mut self.stash_form, &self.dock) else {
+            return;
+        };
+        let message = form.message.read(cx).text();
+        match dock
+            .read(cx)
+            .save_stash(Some(message.trim()), form.include_untracked)
+        {
+            Ok(_) => {
+                self.stash_form = None;
+                self.refresh_repo_views(cx);
+            }
+            Err(e) => form.error = Some(format!("Failed to stash: {}", e)),
+        }
+        cx.notify();
+    }
+
+    fn run_stash_operation(&mut self, index: usize, pop: bool, cx: &mut Context<Self>) {
+        self.context_menu = None;
+        let Some(dock) = &self.dock else {
+            return;
+        };
+        let result = if pop {
+            dock.read(cx).pop_stash(index)
+        } else {
+            dock.read(cx).apply_stash(index)
+        };
+        match result {
+            Ok(StashOutcome::Applied) => self.refresh_repo_views(cx),
+            Ok(StashOutcome::Conflicts(paths)) => {
+                self.refresh_repo_views(cx);
+                self.show_conflicts(
+                    ConflictState {
+                        kind: OperationKind::StashApply,
+                        paths,
+                        progress: None,
+                    },
+                    cx,
+                );
+                if pop {
+                    self.set_notice("The stash had conflicts and was kept", cx);
+                }
+            }
+            Err(e) => self.set_notice(format!("Stash apply failed: {}", e), cx),
+        }
+        cx.notify();
+    }
+
+    fn drop_stash(&mut self, index: usize, cx: &mut Context<Self>) {
+        self.context_menu = None;
+        if let Some(dock) = &self.dock
+            && let Err(e) = dock.read(cx).drop_stash(index)
+        {
+            self.set_notice(format!("Failed to drop stash: {}", e), cx);
+        }
+        self.refresh_repo_views(cx);
+    }
+
+    fn render_stash_form(&self, form: &StashForm, cx: &mut Context<Self>) -> AnyElement {
+        let body = div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .child(field_label("Message"))
+            .child(form.message.clone())
+            .child(
+                checkbox(
+                    "stash_include_untracked",
+                    "Include untracked files",
+                    form.include_untracked,
+                )
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(|this, _event, _window, cx| {
+                        if let Some(form) = &mut this.stash_form {
+                            form.include_untracked = !form.include_untracked;
+                        }
+                        cx.notify();
+                    }),
+                ),
+            )
+            .when_some(form.error.clone(), |this, error| {
+                this.child(
+                    div()
+                        .text_color(gpui::rgb(0xE74C3C))
+                        .text_size(px(12.0))
+                        .child(error),
+                )
+            });
+
+        render_modal(
+            "Stash changes",
+            body,
+            vec![
+                modal_button("stash_cancel", "Cancel", BUTTON_NEUTRAL)
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _event, _window, cx| {
+                            this.stash_form = None;
+                            cx.notify();
+                        }),
+                    )
+                    .into_any(),
+                modal_button("stash_save", "Stash", BUTTON_PRIMARY)
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _event, _window, cx| {
+                            this.submit_stash_form(cx);
+                        }),
+                    )
+                    .into_any(),
+            ],
+        )
+    }
+
+    fn open_clone_form(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let url = cx.new(|cx| TextInput::new("URL or local path", cx));
+        window.focus(&url.focus_handle(cx));
+        self.clone_form = Some(CloneForm {
+            url,
+            destination: cx.new(|cx| TextInput::new("Folder to clone into", cx)),
+            branch: cx.new(|cx| TextInput::new("Default branch", cx)),
+            depth: cx.new(|cx| TextInput::new("Full history", cx)),
+            recursive: true,
+            error: None,
+        });
+        cx.notify();
+    }
+
+    /// Pick the parent folder; the clone goes into a subfolder named after the URL.
+    fn browse_clone_destination(&mut self, cx: &mut Context<Self>) {
+        let Some(form) = &self.clone_form else {
+            return;
+        };
+        let Some(parent) = rfd::FileDialog::new().pick_folder() else {
+            return;
+        };
+        let name = remote::clone_name(&form.url.read(cx).text());
+        let destination = if name.is_empty() {
+            parent
+        } else {
+            parent.join(name)
+        };
+        form.destination.update(cx, |input, cx| {
+            input.set_text(destination.display().to_string(), cx)
+        });
+    }
+
+    fn submit_clone_form(&mut self, cx: &mut Context<Self>) {
+        let Some(form) = &mut self.clone_form else {
+            return;
+        };
+        let url = form.url.read(cx).text().trim().to_string();
+        let destination = form.destination.read(cx).text().trim().to_string();
+        let branch = form.branch.read(cx).text().trim().to_string();
+        let depth = form.depth.read(cx).text().trim().to_string();
+
+        let depth = match depth.parse::<u32>() {
+            _ if depth.is_empty() => None,
+            Ok(depth) if depth > 0 => Some(depth),
+            _ => {
+                form.error = Some("Depth must be a positive number".to_string());
+                cx.notify();
+                return;
+            }
+        };
+        if url.is_empty() || destination.is_empty() {
+            form.error = Some("Enter a URL and a destination folder".to_string());
+            cx.notify();
+            return;
+        }
+
+        let options = CloneOptions {
+            url,
+            destination: PathBuf::from(destination),
+            branch: (!branch.is_empty()).then_some(branch),
+            depth,
+            recursive: form.recursive,
+        };
+        self.clone_form = None;
+        self.run_sync(SyncAction::Clone(options), cx);
+    }
+
+    fn render_clone_form(&self, form: &CloneForm, cx: &mut Context<Self>) -> AnyElement {
+        let body = div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .child(field_label("Source"))
+            .child(form.url.clone())
+            .child(field_label("Destination"))
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .gap_2()
+                    .child(div().flex_1().child(form.destination.clone()))
+                    .child(
+                        modal_button("clone_browse", "Browse…", BUTTON_NEUTRAL).on_mouse_down(
+                            MouseButton::Left,
+                            cx.listener(|this, _event, _window, cx| {
+                                this.browse_clone_destination(cx);
+                            }),
+                        ),
+                    ),
+            )
+            .child(field_label("Branch"))
+            .child(form.branch.clone())
+            .child(field_label("Depth"))
+            .child(form.depth.clone())
+            .child(
+                checkbox(
+                    "clone_recursive",
+                    "Clone submodules recursively",
+                    form.recursive,
+                )
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(|this, _event, _window, cx| {
+                        if let Some(form) = &mut this.clone_form {
+                            form.recursive = !form.recursive;
+                        }
+                        cx.notify();
+                    }),
+                ),
+            )
+            .when_some(form.error.clone(), |this, error| {
+                this.child(
+                    div()
+                        .text_color(gpui::rgb(0xE74C3C))
+                        .text_size(px(12.0))
+                        .child(error),
+                )
+            });
+
+        render_modal(
+            "Clone repository",
+            body,
+            vec![
+                modal_button("clone_cancel", "Cancel", BUTTON_NEUTRAL)
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _event, _window, cx| {
+                            this.clone_form = None;
+                            cx.notify();
+                        }),
+                    )
+                    .into_any(),
+                modal_button("clone_submit", "Clone", BUTTON_PRIMARY)
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _event, _window, cx| {
+                            this.submit_clone_form(cx);
+                        }),
+                    )
+                    .into_any(),
+            ],
+        )
+    }
+
+    fn open_init_form(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let path = cx.new(|cx| TextInput::new("Folder for the new repository", cx));
+        window.focus(&path.focus_handle(cx));
+        self.init_form = Some(InitForm {
+            path,
+            branch: cx.new(|cx| {
+                let mut input = TextInput::new("Initial branch", cx);
+                input.set_text(init::default_branch(), cx);
+                input
+            }),
+            bare: false,
+            gitignore: Some(GitignoreTemplate::General),
+            error: None,
+        });
+        cx.notify();
+    }
+
+    fn browse_init_path(&mut self, cx: &mut Context<Self>) {
+        let Some(form) = &self.init_form else {
+            return;
+        };
+        if let Some(folder) = rfd::FileDialog::new().pick_folder() {
+            form.path.update(cx, |input, cx| {
+                input.set_text(folder.display().to_string(), cx)
+            });
+        }
+    }
+
+    fn submit_init_form(&mut self, cx: &mut Context<Self>) {
+        let Some(form) = &mut self.init_form else {
+            return;
+        };
+        let path = form.path.read(cx).text().trim().to_string();
+        if path.is_empty() {
+            form.error = Some("Choose a folder for the repository".to_string());
+            cx.notify();
+            return;
+        }
+        let options = InitOptions {
+            path: PathBuf::from(path),
+            initial_branch: form.branch.read(cx).text().trim().to_string(),
+            bare: form.bare,
+            gitignore: form.gitignore.filter(|_| !form.bare),
+        };
+        let result = init::init(&options)
+            .map_err(|e| e.to_string())
+            .and_then(|_| self.open_repository(&options.path, cx));
+        match result {
+            Ok(()) => {
+                self.init_form = None;
+                self.set_info(
+                    format!("Initialized a repository in {}", options.path.display()),
+                    cx,
+                );
+            }
+            Err(e) => {
+                if let Some(form) = &mut self.init_form {
+                    form.error = Some(e);
+                }
+            }
+        }
+        cx.notify();
+    }
+
+    fn render_init_form(&self, form: &InitForm, cx: &mut Context<Self>) -> AnyElement {
+        let body = div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .child(field_label("Folder"))
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .gap_2()
+                    .child(div().flex_1().child(form.path.clone()))
+                    .child(
+                        modal_button("init_browse", "Browse…", BUTTON_NEUTRAL).on_mouse_down(
+                            MouseButton::Left,
+                            cx.listener(|this, _event, _window, cx| {
+                                this.browse_init_path(cx);
+                            }),
+                        ),
+                    ),
+            )
+            .child(field_label("Initial branch"))
+            .child(form.branch.clone())
+            .child(
+                checkbox("init_bare", "Bare repository (no working tree)", form.bare)
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _event, _window, cx| {
+                            if let Some(form) = &mut this.init_form {
+                                form.bare = !form.bare;
+                            }
+                            cx.notify();
+                        }),
+                    ),
+            )
+            .when(!form.bare, |this| {
+                this.child(field_label("Starter .gitignore")).child(
+                    div().flex().flex_row().gap_4().children(
+                        GitignoreTemplate::ALL.into_iter().map(|template| {
+                            checkbox(
+                                gpui::SharedString::from(format!("init_gitignore_{:?}", template)),
+                                template.label(),
+                                form.gitignore == Some(template),
+                            )
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(move |this, _event, _window, cx| {
+                                    if let Some(form) = &mut this.init_form {
+                                        form.gitignore = if form.gitignore == Some(template) {
+                                            None
+                                        } else {
+                                            Some(template)
+                                        };
+                                    }
+                                    cx.notify();
+                                }),
+                            )
+                        }),
+                    ),
+                )
+            })
+            .when_some(form.error.clone(), |this, error| {
+                this.child(
+                    div()
+                        .text_color(gpui::rgb(0xE74C3C))
+                        .text_size(px(12.0))
+                        .child(error),
+                )
+            });
+
+        render_modal(
+            "Init repository",
+            body,
+            vec![
+                modal_button("init_cancel", "Cancel", BUTTON_NEUTRAL)
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _event, _window, cx| {
+                            this.init_form = None;
+                            cx.notify();
+                        }),
+                    )
+                    .into_any(),
+                modal_button("init_submit", "Create", BUTTON_PRIMARY)
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _event, _window, cx| {
+                            this.submit_init_form(cx);
+                        }),
+                    )
+                    .into_any(),
+            ],
+        )
+    }
+
+    /// Load the repository at `path` into the graph, like File → Open.
+    pub fn open_repository(&mut self, path: &Path, cx: &mut Context<Self>) -> Result<(), String> {
+        let Some(dock) = &self.dock else {
+            return Err("No graph to show the repository in".to_string());
+        };
+        let path = path.display().to_string();
+        dock.update(cx, |garph, cx| {
+            garph.update_repo(&path).map_err(|e| e.to_string())?;
+            cx.emit(RepoPathChanged { path });
+            Ok(())
+        })
+    }
+
+    fn on_sync_requested(
+        &mut self,
+        _sidebar: Entity<Sidebar>,
+        event: &SyncRequested,
+        cx: &mut Context<Self>,
+    ) {
+        self.run_sync(event.0.clone(), cx);
+    }
+
+    /// Run `action` on a background thread, reporting progress in the status area. A pull
+    /// also merges or rebases the fetched upstream there.
+    fn run_sync(&mut self, action: SyncAction, cx: &mut Context<Self>) {
+        if self.sync_task.is_some() {
+            self.set_notice("Another fetch, pull or push is still running", cx);
+            return;
+        }
+        // a clone is the only action that needs no open repository
+        let git_dir = self.dock.as_ref().and_then(|dock| dock.read(cx).git_dir());
+        if git_dir.is_none() && !matches!(action, SyncAction::Clone(_)) {
+            return;
+        }
+
+        self.sync_status = Some(SyncStatus {
+            label: action.label(),
+            progress: None,
+            running: true,
+        });
+        let (sender, mut receiver) = mpsc::unbounded();
+        let prompt_sender = sender.clone();
+        let mut credentials = CredentialChain::new()
+            .with_cache(self.credential_cache.clone())
+            .with_prompt(move |request| {
+                // block the network thread until the dialog is answered
+                let (reply, answer) = std::sync::mpsc::channel();
+                prompt_sender
+                    .unbounded_send(SyncMessage::Prompt(request, reply))
+                    .ok()?;
+                answer.recv().ok().flatten()
+            });
+        let network_action = action.clone();
+        let work = cx.background_executor().spawn(async move {
+            let mut progress = |progress| {
+                let _ = sender.unbounded_send(SyncMessage::Progress(progress));
+            };
+            let credentials = &mut credentials;
+            if let SyncAction::Clone(options) = &network_action {
+                return remote::clone(options, &mut progress, credentials)
+                    .map(|_| SyncDone::Cloned(options.destination.clone()))
+                    .map_err(|e| e.to_string());
+            }
+            let git_dir = git_dir.ok_or_else(|| "No repository loaded".to_string())?;
+            let repo = git2::Repository::open(&git_dir).map_err(|e| e.to_string())?;
+            match network_action {
+                SyncAction::Fetch(name) => {
+                    remote::fetch(&repo, name.as_deref(), &mut progress, credentials)
+                        .map(SyncDone::Fetched)
+                }
+                SyncAction::Pull => {
+                    let mut rebase_progress = |progress| {
+                        let _ = sender.unbounded_send(SyncMessage::Rebase(progress));
+                    };
+                    remote::pull(&repo, &mut progress, &mut rebase_progress, credentials)
+                        .map(SyncDone::Pulled)
+                }
+                SyncAction::Push => {
+                    remote::push(&repo, &mut progress, credentials).map(SyncDone::Pushed)
+                }
+                SyncAction::Prune(name) => {
+                    remote::prune_remote(&repo, &name, &mut progress, credentials)
+                        .map(SyncDone::Pruned)
+                }
+                SyncAction::Clone(_) => unreachable!("clones are handled above"),
+            }
+            .map_err(|e| e.to_string())
+        });
+
+        self.sync_task = Some(cx.spawn(async move |this, cx| {
+            while let Some(message) = receiver.next().await {
+                let _ = this.update(cx, |this, cx| {
+                    match message {
+                        SyncMessage::Progress(progress) => {
+                            if let Some(status) = &mut this.sync_status {
+                                status.progress = Some(progress);
+                            }
+                        }
+                        SyncMessage::Rebase(progress) => {
+                            if let Some(status) = &mut this.sync_status {
+                                status.label = format!("Pulling, {}", progress.label());
+                                status.progress = None;
+                            }
+                        }
+                        SyncMessage::Prompt(request, reply) => {
+                            this.open_credential_form(request, reply, cx)
+                        }
+                    }
+                    cx.notify();
+                });
+            }
+            let result = work.await;
+            let _ = this.update(cx, |this, cx| this.finish_sync(action, result, cx));
+        }));
+        cx.notify();
+    }
+
+    fn open_credential_form(
+        &mut self,
+        request: CredentialRequest,
+        reply: std::sync::mpsc::Sender<Option<CredentialAnswer>>,
+        cx: &mut Context<Self>,
+    ) {
+        let username = cx.new(|cx| {
+            let mut input = TextInput::new("Username", cx);
+            if let CredentialRequest::UserPass {
+                username: Some(username),
+                ..
+            } = &request
+            {
+                input.set_text(username.clone(), cx);
+            }
+            input
+        });
+        let placeholder = match request {
+            CredentialRequest::UserPass { .. } => "Password or token",
+            CredentialRequest::Passphrase { .. } => "Passphrase",
+        };
+        let secret = cx.new(|cx| {
+            let mut input = TextInput::new(placeholder, cx);
+            input.set_masked(true, cx);
+            input
+        });
+        // a form left open from an earlier prompt counts as cancelled
+        if let Some(form) = self.credential_form.take() {
+            let _ = form.reply.send(None);
+        }
+        self.credential_form = Some(CredentialForm {
+            request,
+            username,
+            secret,
+            remember: false,
+            reply,
+        });
+        cx.notify();
+    }
+
+    fn answer_credential_form(&mut self, submit: bool, cx: &mut Context<Self>) {
+        let Some(form) = self.credential_form.take() else {
+            return;
+        };
+        let answer = submit.then(|| CredentialAnswer {
+            username: form.username.read(cx).text(),
+            secret: form.secret.read(cx).text(),
+            remember: form.remember,
+        });
+        let _ = form.reply.send(answer);
+        cx.notify();
+    }
+
+    fn render_credential_form(&self, form: &CredentialForm, cx: &mut Context<Self>) -> AnyElement {
+        let asks_username = matches!(form.request, CredentialRequest::UserPass { .. });
+        let body = div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .when(asks_username, |this| {
+                this.child(field_label("Username"))
+                    .child(form.username.clone())
+                    .child(field_label("Password"))
+            })
+            .when(!asks_username, |this| this.child(field_label("Passphrase")))
+            .child(form.secret.clone())
+            .child(
+                checkbox(
+                    "credential_remember",
+                    "Remember until gpig quits",
+                    form.remember,
+                )
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(|this, _event, _window, cx| {
+                        if let Some(form) = &mut this.credential_form {
+                            form.remember = !form.remember;
+                        }
+                        cx.notify();
+                    }),
+                ),
+            );
+
+        render_modal(
+            form.request.title(),
+            body,
+            vec![
+                modal_button("credential_cancel", "Cancel", BUTTON_NEUTRAL)
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _event, _window, cx| {
+                            this.answer_credential_form(false, cx);
+                        }),
+                    )
+                    .into_any(),
+                modal_button("credential_submit", "Continue", BUTTON_PRIMARY)
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _event, _window, cx| {
+                            this.answer_credential_form(true, cx);
+                        }),
+                    )
+                    .into_any(),
+            ],
+        )
+    }
+
+    fn finish_sync(
+        &mut self,
+        action: SyncAction,
+        result: Result<SyncDone, String>,
+        cx: &mut Context<Self>,
+    ) {
+        self.sync_task = None;
+        self.sync_status = None;
+        if self.dock.is_none() {
+            return;
+        }
+
+        let label = match result {
+            Err(e) => {
+                self.set_notice(format!("{} failed: {}", action.label(), e), cx);
+                None
+            }
+            Ok(SyncDone::Pulled(outcome)) => self.finish_pull(outcome, cx),
+            Ok(SyncDone::Fetched(remotes)) if remotes.is_empty() => {
+                Some("There are no remotes to fetch".to_string())
+            }
+            Ok(SyncDone::Fetched(remotes)) => Some(format!("Fetched {}", remotes.join(", "))),
+            Ok(SyncDone::Pruned(branches)) if branches.is_empty() => {
+                Some("No stale remote-tracking branches".to_string())
+            }
+            Ok(SyncDone::Pruned(branches)) => Some(format!("Pruned {}", branches.join(", "))),
+            Ok(SyncDone::Cloned(path)) => {
+                let label = format!("Cloned into {}", path.display());
+                cx.emit(OpenRepository { path });
+                Some(label)
+            }
+            Ok(SyncDone::Pushed(outcome)) => Some(format!(
+                "Pushed {} to {}{}",
+                outcome.branch,
+                outcome.remote,
+                if outcome.set_upstream {
+                    " and set it as upstream"
+                } else {
+                    ""
+                }
+            )),
+        };
+        self.sync_status = label.map(|label| SyncStatus {
+            label,
+            progress: None,
+            running: false,
+        });
+        self.refresh_repo_views(cx);
+        cx.notify();
+    }
+
+    /// Hand a pull's merge or rebase to the usual result handling; returns a status line.
+    fn finish_pull(&mut self, outcome: PullOutcome, cx: &mut Context<Self>) -> Option<String> {
+        match outcome {
+            PullOutcome::UpToDate => Some("Already up to date".to_string()),
+            PullOutcome::FastForwarded(oid) => {
+                if let Some(dock) = &self.dock {
+                    dock.update(cx, |garph, cx| garph.select_commit(oid, cx));
+                }
+                Some(format!("Fast-forwarded to {}", &oid.to_string()[..7]))
+            }
+            PullOutcome::Merged(outcome) => {
+                let status = match &outcome {
+                    OperationOutcome::Committed(_) => "Pulled with a merge",
+                    OperationOutcome::Conflicts(_) => "Pull stopped on merge conflicts",
+                };
+                self.handle_operation_result(OperationKind::Merge, Ok(outcome), cx);
+                Some(status.to_string())
+            }
+            PullOutcome::Rebased(outcome) => {
+                let status = match &outcome {
+                    RebaseOutcome::Finished { .. } => None,
+                    RebaseOutcome::Stopped { .. } => {
+                        Some("Pull stopped on rebase conflicts".to_string())
+                    }
+                };
+                self.handle_rebase_result(Ok(outcome), cx);
+                status
+            }
+        }
+    }
+
+    fn render_sync_status(&self, status: &SyncStatus, cx: &mut Context<Self>) -> AnyElement {
+        let detail = status
+            .progress
+            .as_ref()
+            .map(|progress| progress.label())
+            .unwrap_or_default();
+
+        div()
+            .w_full()
+            .flex()
+            .flex_row()
+            .items_center()
+            .gap_2()
+            .px(px(12.0))
+            .py(px(6.0))
+            .border_t_1()
+            .border_color(gpui::rgb(0x333333))
+            .bg(gpui::rgb(0x252525))
+            .text_color(gpui::rgb(0xCCCCCC))
+            .text_size(px(12.0))
+            .when(status.running, |this| {
+                this.child(div().text_color(gpui::rgb(0x4A90D9)).child("⟳"))
+            })
+            .child(status.label.clone())
+            .child(div().flex_1().text_color(gpui::rgb(0x888888)).child(detail))
+            .when(!status.running, |this| {
+                this.child(
+                    div()
+                        .text_color(gpui::rgb(0x888888))
+                        .px(px(6.0))
+                        .cursor_pointer()
+                        .hover(|style| style.bg(gpui::rgb(0x444444)))
+                        .rounded(px(4.0))
+                        .child("✕")
+                        .on_mouse_down(
+                            MouseButton::Left,
+                            cx.listener(|this, _event, _window, cx| {
+                                this.sync_status = None;
+                                cx.notify();
+                            }),
+                        ),
+                )
+            })
+            .into_any()
+    }
+
+    fn open_discard_form(&mut self, path: String, cx: &mut Context<Self>) {
+        let Some(dock) = &self.dock else {
+            return;
+        };
+        match dock.read(cx).workdir_hunks(&path) {
+            Ok(hunks) => {
+                self.discard_form = Some(DiscardForm {
+                    path,
+                    hunks,
+                    error: None,
+                })
+            }
+            Err(e) => self.set_notice(format!("Discard failed: {}", e), cx),
+        }
+        cx.notify();
+    }
+
+    /// Discard one hunk, or the whole file when `hunk_index` is `None`.
+    fn submit_discard(&mut self, hunk_index: Option<usize>, cx: &mut Context<Self>) {
+        let (Some(form), Some(dock)) = (&mut self.discard_form, &self.dock) else {
+            return;
+        };
+        let result = match hunk_index {
+            Some(index) => dock.read(cx).discard_hunk(&form.path, index),
+            None => dock.read(cx).discard_file(&form.path),
+        };
+        let result = result.and_then(|()| dock.read(cx).workdir_hunks(&form.path));
+        match result {
+            Ok(hunks) if hunks.is_empty() => self.discard_form = None,
+            Ok(hunks) => {
+                form.hunks = hunks;
+                form.error = None;
+            }
+            Err(e) => form.error = Some(format!("Discard failed: {}", e)),
+        }
+        cx.notify();
+    }
+
+    fn render_discard_form(&self, form: &DiscardForm, cx: &mut Context<Self>) -> AnyElement {
+        let body = div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .child(field_label(format!("Uncommitted changes to {}", form.path)))
+            .when(form.hunks.is_empty(), |this| {
+                this.child(
+                    div()
+                        .text_color(gpui::rgb(0x888888))
+                        .text_size(px(12.0))
+                        .child("There are no uncommitted changes to this file"),
+                )
+            })
+            .child(
+                div()
+                    .id("discard_hunks")
+                    .max_h(px(320.0))
+                    .overflow_y_scroll()
+                    .flex()
+                    .flex_col()
+                    .gap_2()
+                    .children(form.hunks.iter().enumerate().map(|(index, hunk)| {
+                        div()
+                            .flex()
+                            .flex_col()
+                            .border_1()
+                            .border_color(gpui::rgb(0x333333))
+                            .rounded(px(4.0))
+                            .child(
+                                div()
+                                    .flex()
+                                    .flex_row()
+                                    .items_center()
+                                    .justify_between()
+                                    .px(px(6.0))
+                                    .py(px(4.0))
+                                    .bg(gpui::rgb(0x252525))
+                                    .child(
+                                        div()
+                                            .text_color(gpui::rgb(0x888888))
+                                            .text_size(px(11.0))
+                                            .font_family("monospace")
+                                            .child(hunk.header.clone()),
+                                    )
+                                    .child(
+                                        modal_button(
+                                            gpui::SharedString::from(format!(
+                                                "discard_hunk_{}",
+                                                index
+                                            )),
+                                            "Discard hunk",
+                                            BUTTON_DANGER,
+                                        )
+                                        .on_mouse_down(
+                                            MouseButton::Left,
+                                            cx.listener(move |this, _event, _window, cx| {
+                                                this.submit_discard(Some(index), cx);
+                                            }),
+                                        ),
+                                    ),
+                            )
+                            .child(
+                                div()
+                                    .flex()
+                                    .flex_col()
+                                    .px(px(6.0))
+                                    .py(px(4.0))
+                                    .font_family("monospace")
+                                    .text_size(px(12.0))
+                                    .children(hunk.lines.iter().map(|line| {
+                                        let color = match line.chars().next() {
+                                            Some('+') => 0x2ECC71,
+                                            Some('-') => 0xE74C3C,
+                                            _ => 0xCCCCCC,
+                                        };
+                                        div().text_color(gpui::rgb(color)).child(line.clone())
+                                    })),
+                            )
+                    })),
+            )
+            .when_some(form.error.clone(), |this, error| {
+                this.child(
+                    div()
+                        .text_color(gpui::rgb(0xE74C3C))
+                        .text_size(px(12.0))
+                        .child(error),
+                )
+            });
+
+        let mut buttons = vec![
+            modal_button("discard_cancel", "Cancel", BUTTON_NEUTRAL)
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(|this, _event, _window, cx| {
+                        this.discard_form = None;
+                        cx.notify();
+                    }),
+                )
+                .into_any(),
+        ];
+        if !form.hunks.is_empty() {
+            buttons.push(
+                modal_button("discard_file", "Discard all changes", BUTTON_DANGER)
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _event, _window, cx| {
+                            this.submit_discard(None, cx);
+                        }),
+                    )
+                    .into_any(),
+            );
+        }
+
+        render_modal("Discard changes", body, buttons)
+    }
+
+    fn open_restore_form(&mut self, file: ChangedFile, cx: &mut Context<Self>) {
+        let Some(commit) = self.current_commit_oid else {
+            return;
+        };
+        self.restore_form = Some(RestoreForm {
+            before: file.new_oid.is_none(),
+            file,
+            commit,
+            error: None,
+        });
+        cx.notify();
+    }
+
+    fn submit_restore_form(&mut self, cx: &mut Context<Self>) {
+        let (Some(form), Some(dock)) = (&mut self.restore_form, &self.dock) else {
+            return;
+        };
+        match dock
+            .read(cx)
+            .restore_file(&form.commit, form.before, &form.file.path)
+        {
+            Ok(()) => {
+                let path = form.file.path.clone();
+                self.restore_form = None;
+                self.set_info(format!("Restored {}", path), cx);
+            }
+            Err(e) => form.error = Some(format!("Restore failed: {}", e)),
+        }
+        cx.notify();
+    }
+
+    fn render_restore_form(&self, form: &RestoreForm, cx: &mut Context<Self>) -> AnyElement {
+        let short_oid = self
+            .current_commit_oid
+            .map(|oid| oid.to_string()[..7].to_string())
+            .unwrap_or_default();
+        let options = [
+            (
+                false,
+                format!("As of commit {}", short_oid),
+                form.file.new_oid,
+            ),
+            (
+                true,
+                format!("Before commit {}", short_oid),
+                form.file.old_oid,
+            ),
+        ];
+        let blob = if form.before {
+            form.file.old_oid
+        } else {
+            form.file.new_oid
+        };
+
+        let body = div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .child(field_label(format!("Restore {}", form.file.path)))
+            .children(options.into_iter().map(|(before, label, oid)| {
+                let label = match oid {
+                    Some(_) => label,
+                    None => format!("{} (file does not exist)", label),
+                };
+                checkbox(
+                    gpui::SharedString::from(format!("restore_before_{}", before)),
+                    label,
+                    form.before == before,
+                )
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |this, _event, _window, cx| {
+                        if let Some(form) = &mut this.restore_form {
+                            form.before = before;
+                        }
+                        cx.notify();
+                    }),
+                )
+            }))
+            .child(
+                div()
+                    .text_color(gpui::rgb(0xE74C3C))
+                    .text_size(px(12.0))
+                    .child(if blob.is_some() {
+                        "Uncommitted changes to this file will be overwritten"
+                    } else {
+                        "The file will be deleted from the working tree and the index"
+                    }),
+            )
+            .when_some(form.error.clone(), |this, error| {
+                this.child(
+                    div()
+                        .text_color(gpui::rgb(0xE74C3C))
+                        .text_size(px(12.0))
+                        .child(error),
+                )
+            });
+
+        render_modal(
+            "Restore file",
+            body,
+            vec![
+                modal_button("restore_cancel", "Cancel", BUTTON_NEUTRAL)
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _event, _window, cx| {
+                            this.restore_form = None;
+                            cx.notify();
+                        }),
+                    )
+                    .into_any(),
+                modal_button("restore_submit", "Restore", BUTTON_DANGER)
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _event, _window, cx| {
+                            this.submit_restore_form(cx);
+                        }),
+                    )
+                    .into_any(),
+            ],
+        )
+    }
+
+    fn open_reset_form(&mut self, oid: git2::Oid, cx: &mut Context<Self>) {
+        self.context_menu = None;
+        let Some(dock) = &self.dock else {
+            return;
+        };
+        match dock.read(cx).uncommitted_changes() {
+            Ok(changes) => {
+                self.reset_form = Some(ResetForm {
+                    oid,
+                    mode: ResetMode::Mixed,
+                    changes,
+                })
+            }
+            Err(e) => self.set_notice(format!("Reset failed: {}", e), cx),
+        }
+        cx.notify();
+    }
+
+    fn submit_reset_form(&mut self, cx: &mut Context<Self>) {
+        let (Some(form), Some(dock)) = (self.reset_form.take(), self.dock.clone()) else {
+            return;
+        };
+        let old_head = dock.read(cx).head_oid();
+        match dock.read(cx).reset(&form.oid, form.mode) {
+            Ok(()) => {
+                self.conflict_view = None;
+                self.refresh_repo_views(cx);
+                self.refresh_conflict_state(cx);
+                dock.update(cx, |garph, cx| garph.select_commit(form.oid, cx));
+                if let Some(old_head) = old_head {
+                    self.set_notice(
+                        format!(
+                            "Reset to {}. The previous HEAD {} is in ORIG_HEAD and the reflog",
+                            &form.oid.to_string()[..7],
+                            &old_head.to_string()[..7]
+                        ),
+                        cx,
+                    );
+                }
+            }
+            Err(e) => self.set_notice(format!("Reset failed: {}", e), cx),
+        }
+        cx.notify();
+    }
+
+    fn render_reset_form(&self, form: &ResetForm, cx: &mut Context<Self>) -> AnyElement {
+        let short_oid = form.oid.to_string()[..7].to_string();
+        let is_hard = form.mode == ResetMode::Hard;
+
+        let body = div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .child(field_label(format!(
+                "Move the current branch to {}",
+                short_oid
+            )))
+            .children(ResetMode::ALL.into_iter().map(|mode| {
+                checkbox(
+                    gpui::SharedString::from(format!("reset_mode_{:?}", mode)),
+                    mode.label(),
+                    form.mode == mode,
+                )
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |this, _event, _window, cx| {
+                        if let Some(form) = &mut this.reset_form {
+                            form.mode = mode;
+                        }
+                        cx.notify();
+                    }),
+                )
+            }))
+            .when(is_hard && form.changes.is_empty(), |this| {
+                this.child(
+                    div()
+                        .text_color(gpui::rgb(0x2ECC71))
+                        .text_size(px(12.0))
+                        .child("No uncommitted changes will be lost"),
+                )
+            })
+            .when(is_hard && !form.changes.is_empty(), |this| {
+                this.child(
+                    div()
+                        .text_color(gpui::rgb(0xE74C3C))
+                        .text_size(px(12.0))
+                        .child(format!(
+                            "These {} uncommitted change(s) will be lost:",
+                            form.changes.len()
+                        )),
+                )
+                .child(
+                    div()
+                        .id("reset_lost_changes")
+                        .max_h(px(160.0))
+                        .overflow_y_scroll()
+                        .flex()
+                        .flex_col()
+                        .font_family("monospace")
+                        .text_size(px(12.0))
+                        .text_color(gpui::rgb(0xCCCCCC))
+                        .children(
+                            form.changes
+                                .iter()
+                                .map(|change| div().child(change.clone())),
+                        ),
+                )
+            });
+
+        let (label, color) = if is_hard {
+            ("Discard changes and reset", BUTTON_DANGER)
+        } else {
+            ("Reset", BUTTON_PRIMARY)
+        };
+
+        render_modal(
+            "Reset current branch",
+            body,
+            vec![
+                modal_button("reset_cancel", "Cancel", BUTTON_NEUTRAL)
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _event, _window, cx| {
+                            this.reset_form = None;
+                            cx.notify();
+                        }),
+                    )
+                    .into_any(),
+                modal_button("reset_submit", label, color)
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _event, _window, cx| {
+                            this.submit_reset_form(cx);
+                        }),
+                    )
+                    .into_any(),
+            ],
+        )
+    }
+
+    fn render_merge_form(&self, form: &MergeForm, cx: &mut Context<Self>) -> AnyElement {
+        let short_oid = form.oid.to_string()[..7].to_string();
+
+        let (summary, summary_color, has_conflicts) = match &form.preview {
+            Ok(preview) if preview.up_to_date => {
+                ("Already up to date".to_string(), 0x888888, false)
+            }
+            Ok(preview) if preview.can_fast_forward => (
+                "Can fast-forward, no conflicts".to_string(),
+                0x2ECC71,
+                false,
+            ),
+            Ok(preview) if preview.conflicts.is_empty() => {
+                ("Merges cleanly, no conflicts".to_string(), 0x2ECC71, false)
+            }
+            Ok(preview) => (
+                format!(
+                    "Conflicts expected in {} file(s): {}",
+                    preview.conflicts.len(),
+                    preview.conflicts.join(", ")
+                ),
+                0xF39C12,
+                true,
+            ),
+            Err(e) => (format!("Cannot check merge: {}", e), 0xE74C3C, false),
+        };
+
+        let body = div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .child(field_label(format!(
+                "Merge commit {} into the current branch",
+                short_oid
+            )))
+            .children(MergeMode::ALL.into_iter().map(|mode| {
+                checkbox(
+                    gpui::SharedString::from(format!("merge_mode_{:?}", mode)),
+                    mode.label(),
+                    form.mode == mode,
+                )
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |this, _event, _window, cx| {
+                        if let Some(form) = &mut this.merge_form {
+                            form.mode = mode;
+                        }
+                        cx.notify();
+                    }),
+                )
+            }))
+            .child(
+                div()
+                    .text_color(gpui::rgb(summary_color))
+                    .text_size(px(12.0))
+                    .child(summary),
+            );
+
+        let merge_label = if has_conflicts {
+            "Merge anyway"
+        } else {
+            "Merge"
+        };
+
+        render_modal(
+            "Merge into current branch",
+            body,
+            vec![
+                modal_button("merge_cancel", "Cancel", BUTTON_NEUTRAL)
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _event, _window, cx| {
+                            this.merge_form = None;
+                            cx.notify();
+                        }),
+                    )
+                    .into_any(),
+                modal_button("merge_submit", merge_label, BUTTON_PRIMARY)
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _event, _window, cx| {
+                            this.submit_merge_form(cx);
+                        }),
+                    )
+                    .into_any(),
+            ],
+        )
+    }
+
+    fn on_commit_context_menu(
+        &mut self,
+        _garph: Entity<Garph>,
+        event: &CommitContextMenu,
+        cx: &mut Context<Self>,
+    ) {
+        self.context_menu = Some(event.clone());
+        cx.notify();
+    }
+
+    fn close_context_menu(&mut self, cx: &mut Context<Self>) {
+        if self.context_menu.take().is_some() {
+            cx.notify();
+        }
+    }
+
+    /// Redraw the graph and sidebar after the repository was changed.
+    fn refresh_repo_views(&mut self, cx: &mut Context<Self>) {
+        if let Some(dock) = &self.dock {
+            dock.update(cx, |garph, cx| garph.refresh(cx));
+        }
+        self.refresh_refs(cx);
+        self.refresh_remote_manager(cx);
+    }
+
+    fn on_manage_remotes_requested(
+        &mut self,
+        _sidebar: Entity<Sidebar>,
+        _event: &ManageRemotesRequested,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(dock) = &self.dock else {
+            return;
+        };
+        let remotes = match dock.read(cx).list_refs() {
+            Ok(refs) => refs.remotes,
+            Err(e) => {
+                self.set_notice(format!("Failed to list remotes: {}", e), cx);
+                return;
+            }
+        };
+        if let Some(manager) = &self.remote_manager {
+            manager.update(cx, |manager, cx| manager.set_remotes(remotes, cx));
+        } else {
+            let manager = cx.new(|cx| RemoteManager::new(remotes, cx));
+            cx.subscribe(&manager, Self::on_remote_manager_event)
+                .detach();
+            self.remote_manager = Some(manager);
+        }
+        cx.notify();
+    }
+
+    /// Reload the remote list shown by the remote manager, if it is open.
+    fn refresh_remote_manager(&mut self, cx: &mut Context<Self>) {
+        let (Some(dock), Some(manager)) = (&self.dock, &self.remote_manager) else {
+            return;
+        };
+        if let Ok(refs) = dock.read(cx).list_refs() {
+            manager.update(cx, |manager, cx| manager.set_remotes(refs.remotes, cx));
+        }
+    }
+
+    fn on_remote_manager_event(
+        &mut self,
+        manager: Entity<RemoteManager>,
+        event: &RemoteManagerEvent,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(dock) = self.dock.clone() else {
+            return;
+        };
+        let result = match event {
+            RemoteManagerEvent::Close => {
+                self.remote_manager = None;
+                cx.notify();
+                return;
+            }
+            RemoteManagerEvent::Prune(name) => {
+                self.run_sync(SyncAction::Prune(name.clone()), cx);
+                return;
+            }
+            RemoteManagerEvent::Add { name, url } => dock.read(cx).add_remote(name, url),
+            RemoteManagerEvent::Update {
+                name,
+                new_name,
+                url,
+                push_url,
+            } => {
+                let garph = dock.read(cx);
+                garph
+                    .set_remote_urls(name, url, push_url.as_deref())
+                    .and_then(|_| {
+                        if new_name != name {
+                            garph.rename_remote(name, new_name)
+                        } else {
+                            Ok(())
+                        }
+                    })
+            }
+            RemoteManagerEvent::Remove(name) => dock.read(cx).remove_remote(name),
+        };
+        match result {
+            Ok(()) => self.refresh_repo_views(cx),
+            Err(e) => manager.update(cx, |manager, cx| manager.set_error(e.to_string(), cx)),
+        }
+        cx.notify();
+    }
+
+    fn open_tag_form(&mut self, oid: git2::Oid, window: &mut Window, cx: &mut Context<Self>) {
+        let name = cx.new(|cx| TextInput::new("Tag name, e.g. v1.2.0", cx));
+        let message = cx.new(|cx| TextInput::new("Tag message", cx));
+        window.focus(&name.focus_handle(cx));
+
+        self.context_menu = None;
+        self.tag_form = Some(TagForm {
+            oid,
+            name,
+            message,
+            annotated: false,
+            error: None,
+        });
+        cx.notify();
+    }
+
+    fn submit_tag_form(&mut self, cx: &mut Context<Self>) {
+        let (Some(form), Some(dock)) = (&mut self.tag_form, &self.dock) else {
+            return;
+        };
+
+        let name = form.name.read(cx).text().trim().to_string();
+        let message = form.message.read(cx).text();
+        if name.is_empty() {
+            form.error = Some("Tag name is required".to_string());
+            cx.notify();
+            return;
+        }
+
+        let message = form.annotated.then_some(message.as_str());
+        let result = dock.read(cx).create_tag(&form.oid, &name, message);
+        match result {
+            Ok(()) => {
+                self.tag_form = None;
+                self.refresh_repo_views(cx);
+            }
+            Err(e) => form.error = Some(format!("Failed to create tag: {}", e)),
+        }
+        cx.notify();
+    }
+
+    fn delete_tag(&mut self, name: &str, cx: &mut Context<Self>) {
+        self.context_menu = None;
+        if let Some(dock) = &self.dock
+            && let Err(e) = dock.read(cx).delete_tag(name)
+        {
+            self.set_notice(format!("Failed to delete tag {}: {}", name, e), cx);
+        }
+        self.refresh_repo_views(cx);
+    }
+
+    fn render_context_menu(&self, menu: &CommitContextMenu, cx: &mut Context<Self>) -> AnyElement {
+        let oid = menu.oid;
+        let tags = self
+            .dock
+            .as_ref()
+            .map(|dock| dock.read(cx).tags_for(&oid))
+            .unwrap_or_default();
+        let parents = self
+            .dock
+            .as_ref()
+            .map(|dock| dock.read(cx).parents_of(&oid))
+            .unwrap_or_default();
 
-use crate::actions::{OpenFile, Quit};
-use crate::garph::{ChangedFile, CommitSelected, Garph};
-use crate::menu::{DropdownEvent, MenuBar};
-use crate::title::{QuitClicked, TitleBar};
+        // merge commits need a mainline parent to diff against
+        let mainlines: Vec<(Option<u32>, String)> = if parents.len() > 1 {
+            parents
+                .iter()
+                .enumerate()
+                .map(|(i, parent)| {
+                    let mainline = i as u32 + 1;
+                    (
+                        Some(mainline),
+                        format!(" (mainline {}: {})", mainline, &parent.to_string()[..7]),
+                    )
+                })
+                .collect()
+        } else {
+            vec![(None, String::new())]
+        };
 
-pub struct Dock;
-pub struct Pane;
-pub struct Workspace {
-    dock: Option<Entity<Garph>>,
-    title_bar: Entity<TitleBar>,
-    menu_bar: Entity<MenuBar>,
-    selected_commit: Option<CommitSelected>,
-    changed_files: Vec<ChangedFile>,
-    selected_file: Option<usize>,
-    file_diff: Option<String>,
-    active_pane: ActivePane,
-    loading_diff: bool,
-    current_commit_oid: Option<git2::Oid>,
-    // pane: Vec<Entity<AnyElement>>,
-}
+        let item = |id: gpui::SharedString, label: String| {
+            div()
+                .id(id)
+                .text_color(gpui::white())
+                .text_size(px(13.0))
+                .px(px(16.0))
+                .py(px(6.0))
+                .hover(|style| style.bg(gpui::rgb(0x333333)))
+                .child(label)
+        };
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-pub enum ActivePane {
-    Dock,
-    Content,
-}
+        if let Some(index) = menu.stash {
+            return div()
+                .id("stash_context_menu")
+                .absolute()
+                .top(menu.position.y)
+                .left(menu.position.x)
+                .min_w(px(200.0))
+                .bg(gpui::rgb(0x1a1a1a))
+                .border_1()
+                .border_color(gpui::rgb(0x333333))
+                .shadow_lg()
+                .on_mouse_down(MouseButton::Left, |_event, _window, cx| {
+                    cx.stop_propagation();
+                })
+                .child(
+                    item("menu_stash_apply".into(), "Apply stash".into()).on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |this, _event, _window, cx| {
+                            this.run_stash_operation(index, false, cx);
+                        }),
+                    ),
+                )
+                .child(
+                    item("menu_stash_pop".into(), "Pop stash".into()).on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |this, _event, _window, cx| {
+                            this.run_stash_operation(index, true, cx);
+                        }),
+                    ),
+                )
+                .child(div().h(px(1.0)).bg(gpui::rgb(0x333333)))
+                .child(
+                    item("menu_stash_drop".into(), "Drop stash".into()).on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |this, _event, _window, cx| {
+                            this.drop_stash(index, cx);
+                        }),
+                    ),
+                )
+                .into_any();
+        }
 
-impl Workspace {
-    pub fn new(dock: Option<Entity<Garph>>, cx: &mut Context<Self>) -> Self {
-        let dock_clone = dock.clone();
+        div()
+            .id("commit_context_menu")
+            .absolute()
+            .top(menu.position.y)
+            .left(menu.position.x)
+            .min_w(px(200.0))
+            .bg(gpui::rgb(0x1a1a1a))
+            .border_1()
+            .border_color(gpui::rgb(0x333333))
+            .shadow_lg()
+            .on_mouse_down(MouseButton::Left, |_event, _window, cx| {
+                cx.stop_propagation();
+            })
+            .children(mainlines.iter().map(|(mainline, suffix)| {
+                let mainline = *mainline;
+                item(
+                    format!("menu_cherry_pick_{:?}", mainline).into(),
+                    format!("Cherry-pick onto current branch{}", suffix),
+                )
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |this, _event, _window, cx| {
+                        this.run_commit_operation(CommitOperation::CherryPick, oid, mainline, cx);
+                    }),
+                )
+            }))
+            .children(mainlines.iter().map(|(mainline, suffix)| {
+                let mainline = *mainline;
+                item(
+                    format!("menu_revert_{:?}", mainline).into(),
+                    format!("Revert commit{}", suffix),
+                )
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |this, _event, _window, cx| {
+                        this.run_commit_operation(CommitOperation::Revert, oid, mainline, cx);
+                    }),
+                )
+            }))
+            .child(
+                item("menu_merge".into(), "Merge into current branch…".into()).on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |this, _event, _window, cx| {
+                        this.open_merge_form(oid, cx);
+                    }),
+                ),
+            )
+            .child(
+                item(
+                    "menu_rebase".into(),
+                    "Rebase current branch onto this commit".into(),
+                )
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |this, _event, _window, cx| {
+                        this.rebase_onto(oid, cx);
+                    }),
+                ),
+            )
+            .child(
+                item(
+                    "menu_rebase_interactive".into(),
+                    "Interactive rebase from here…".into(),
+                )
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |this, _event, _window, cx| {
+                        this.open_rebase_planner(oid, cx);
+                    }),
+                ),
+            )
+            .child(
+                item("menu_reset".into(), "Reset current branch to here…".into()).on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |this, _event, _window, cx| {
+                        this.open_reset_form(oid, cx);
+                    }),
+                ),
+            )
+            .child(div().h(px(1.0)).bg(gpui::rgb(0x333333)))
+            .child(
+                item("menu_create_tag".into(), "Create tag…".into()).on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |this, _event, window, cx| {
+                        this.open_tag_form(oid, window, cx);
+                    }),
+                ),
+            )
+            .children(tags.into_iter().map(|tag| {
+                item(
+                    format!("menu_delete_tag_{}", tag).into(),
+                    format!("Delete tag '{}'", tag),
+                )
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |this, _event, _window, cx| {
+                        this.delete_tag(&tag, cx);
+                    }),
+                )
+            }))
+            .into_any()
+    }
 
-        let menu_bar = cx.new(|_| MenuBar::new());
-        let title_bar = cx.new(|_| TitleBar::new("Dark Pig Git"));
+    fn render_notice(
+        &self,
+        (level, notice): (NoticeLevel, String),
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let (background, color) = match level {
+            NoticeLevel::Info => (0x252525, 0xCCCCCC),
+            NoticeLevel::Error => (0x3A1E1E, 0xE74C3C),
+        };
+        div()
+            .w_full()
+            .flex()
+            .flex_row()
+            .items_center()
+            .justify_between()
+            .px(px(12.0))
+            .py(px(6.0))
+            .border_t_1()
+            .border_color(gpui::rgb(0x333333))
+            .bg(gpui::rgb(background))
+            .text_color(gpui::rgb(color))
+            .text_size(px(12.0))
+            .child(notice)
+            .child(
+                div()
+                    .text_color(gpui::rgb(0x888888))
+                    .px(px(6.0))
+                    .cursor_pointer()
+                    .hover(|style| style.bg(gpui::rgb(0x444444)))
+                    .rounded(px(4.0))
+                    .child("✕")
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _event, _window, cx| {
+                            this.notice = None;
+                            cx.notify();
+                        }),
+                    ),
+            )
+            .into_any()
+    }
 
-        Self {
-            dock: dock_clone,
-            title_bar,
-            menu_bar,
-            selected_commit: None,
-            changed_files: Vec::new(),
-            selected_file: None,
-            file_diff: None,
-            active_pane: ActivePane::Content,
-            loading_diff: false,
-            current_commit_oid: None,
+    fn render_tag_form(&self, form: &TagForm, cx: &mut Context<Self>) -> AnyElement {
+        let short_oid = form.oid.to_string()[..7].to_string();
+
+        let body = div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .child(field_label(format!("Commit {}", short_oid)))
+            .child(field_label("Name"))
+            .child(form.name.clone())
+            .child(
+                checkbox("tag_annotated", "Annotated tag", form.annotated).on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(|this, _event, _window, cx| {
+                        if let Some(form) = &mut this.tag_form {
+                            form.annotated = !form.annotated;
+                        }
+                        cx.notify();
+                    }),
+                ),
+            )
+            .when(form.annotated, |this| {
+                this.child(field_label("Message"))
+                    .child(form.message.clone())
+            })
+            .when_some(form.error.clone(), |this, error| {
+                this.child(
+                    div()
+                        .text_color(gpui::rgb(0xE74C3C))
+                        .text_size(px(12.0))
+                        .child(error),
+                )
+            });
+
+        render_modal(
+            "Create tag",
+            body,
+            vec![
+                modal_button("tag_cancel", "Cancel", BUTTON_NEUTRAL)
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _event, _window, cx| {
+                            this.tag_form = None;
+                            cx.notify();
+                        }),
+                    )
+                    .into_any(),
+                modal_button("tag_create", "Create", BUTTON_PRIMARY)
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _event, _window, cx| {
+                            this.submit_tag_form(cx);
+                        }),
+                    )
+                    .into_any(),
+            ],
+        )
+    }
+
+    fn on_ref_selected(
+        &mut self,
+        _sidebar: Entity<Sidebar>,
+        event: &RefSelected,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(dock) = &self.dock {
+            dock.update(cx, |garph, cx| garph.select_commit(event.oid, cx));
         }
     }
 
+    fn on_commit_outside_graph(
+        &mut self,
+        _garph: Entity<Garph>,
+        event: &CommitOutsideGraph,
+        cx: &mut Context<Self>,
+    ) {
+        self.set_info(
+            format!(
+                "{} is older than the {} commits shown; raise the limit in Settings to see it",
+                &event.oid.to_string()[..7],
+                event.rows
+            ),
+            cx,
+        );
+    }
+
     fn on_commit_selected(
         &mut self,
         garph: Entity<Garph>,
@@ -72,21 +2944,40 @@ impl Workspace {
         commit: &CommitSelected,
         cx: &mut Context<Self>,
     ) {
-        let files = garph.update(cx, |garph, _cx| {
-            match garph.get_changed_files(&commit.oid) {
-                Ok(files) => files,
-                Err(e) => {
-                    eprintln!("Failed to get changed files: {}", e);
-                    Vec::new()
-                }
-            }
-        });
-
-        self.changed_files = files;
+        self.changed_files = Vec::new();
+        self.files_error = None;
         self.selected_file = None;
         self.file_diff = None;
+        self.loading_diff = false;
+        self.diff_task = None;
         self.current_commit_oid = Some(commit.oid);
+
+        let Some(handle) = garph.read(cx).repo_handle() else {
+            self.loading_files = false;
+            self.files_task = None;
+            cx.notify();
+            return;
+        };
+        self.loading_files = true;
         cx.notify();
+
+        let oid = commit.oid;
+        let files = cx
+            .background_executor()
+            .spawn(async move { handle.open()?.changed_files(oid) });
+        self.files_task = Some(cx.spawn(async move |this, cx| {
+            let files = files.await;
+            this.update(cx, |this, cx| {
+                match files {
+                    Ok(files) => this.changed_files = files,
+                    Err(e) => this.files_error = Some(Self::describe_files_error(e)),
+                }
+                this.loading_files = false;
+                this.files_task = None;
+                cx.notify();
+            })
+            .ok();
+        }));
     }
 
     fn on_file_selected(
@@ -122,27 +3013,84 @@ impl Workspace {
             }
         };
 
-        let diff_content = garph.update(cx, |garph, _cx| {
-            match garph.compute_file_diff(&commit_oid, &file.path) {
-                Ok(diff) => diff,
-                Err(e) => format!("Failed to compute diff: {}", e),
-            }
-        });
+        let limits = self.settings.diff.clone();
+        let Some(handle) = garph.read(cx).repo_handle() else {
+            self.file_diff = Some("Open a repository to see diffs".to_string());
+            self.loading_diff = false;
+            cx.notify();
+            return;
+        };
+        let diff = cx
+            .background_executor()
+            .spawn(async move { handle.open()?.file_diff(commit_oid, &file.path, &limits) });
+        self.diff_task = Some(cx.spawn(async move |this, cx| {
+            let diff = diff.await;
+            this.update(cx, |this, cx| {
+                this.file_diff = Some(Self::describe_file_diff(diff));
+                this.loading_diff = false;
+                this.diff_task = None;
+                cx.notify();
+            })
+            .ok();
+        }));
+    }
 
-        self.file_diff = Some(diff_content);
-        self.loading_diff = false;
-        cx.notify();
+    fn describe_file_diff(diff: Result<String, GpigError>) -> String {
+        match diff {
+            Ok(diff) => diff,
+            Err(GpigError::BinaryFile { path, size }) => format!(
+                "{} is a binary file\nSize: {} bytes\n\nThere is no text diff to show",
+                path, size
+            ),
+            Err(GpigError::PathNotInDiff(path)) => format!(
+                "File '{}' not found in diff\nThis may be because:\n- The file path has changed\n- The file was renamed\n- The file is in a subdirectory",
+                path
+            ),
+            Err(GpigError::NoRepository) => "Open a repository to see diffs".to_string(),
+            Err(e) => format!("Failed to compute diff: {}", e),
+        }
+    }
+
+    fn describe_files_error(error: GpigError) -> String {
+        match error {
+            GpigError::NoRepository => "Open a repository to see its changes".to_string(),
+            e => format!("Failed to list changed files: {}", e),
+        }
     }
 
     fn on_back_to_file_list(&mut self, cx: &mut Context<Self>) {
         self.selected_file = None;
         self.file_diff = None;
         self.loading_diff = false;
+        self.diff_task = None;
         cx.notify();
     }
 
     fn render_file_list(&self, dock: &Entity<Garph>, cx: &mut Context<Self>) -> AnyElement {
-        if self.changed_files.is_empty() {
+        let file_action = |label: &'static str| {
+            div()
+                .ml(px(8.0))
+                .px(px(6.0))
+                .py(px(2.0))
+                .rounded(px(4.0))
+                .text_size(px(11.0))
+                .text_color(gpui::rgb(0x888888))
+                .hover(|style| style.bg(gpui::rgb(0x444444)))
+                .child(label)
+        };
+
+        if let Some(error) = &self.files_error {
+            div()
+                .flex()
+                .items_center()
+                .justify_center()
+                .size_full()
+                .px(px(12.0))
+                .bg(gpui::rgb(0x1E1E1E))
+                .text_color(gpui::rgb(0xE74C3C))
+                .child(error.clone())
+                .into_any()
+        } else if self.changed_files.is_empty() {
             div()
                 .flex()
                 .items_center()
@@ -150,7 +3098,11 @@ impl Workspace {
                 .size_full()
                 .bg(gpui::rgb(0x1E1E1E))
                 .text_color(gpui::rgb(0x888888))
-                .child("No files changed in this commit")
+                .child(if self.loading_files {
+                    "Loading changed files..."
+                } else {
+                    "No files changed in this commit"
+                })
                 .into_any()
         } else {
             let dock_for_file = dock.clone();
@@ -184,6 +3136,7 @@ impl Workspace {
                             let dock_for_file_clone = dock_for_file.clone();
                             let file_path = file.path.clone();
                             let status = file.status;
+                            let file = file.clone();
 
                             let status_color = match status {
                                 git2::Delta::Added => gpui::rgb(0x2ECC71),
@@ -241,8 +3194,22 @@ impl Workspace {
                                         .overflow_hidden()
                                         .whitespace_nowrap()
                                         .max_w(px(400.0))
-                                        .child(file_path),
+                                        .child(file_path.clone()),
                                 )
+                                .child(file_action("Discard…").on_mouse_down(
+                                    MouseButton::Left,
+                                    cx.listener(move |this, _event, _window, cx| {
+                                        cx.stop_propagation();
+                                        this.open_discard_form(file_path.clone(), cx);
+                                    }),
+                                ))
+                                .child(file_action("Restore…").on_mouse_down(
+                                    MouseButton::Left,
+                                    cx.listener(move |this, _event, _window, cx| {
+                                        cx.stop_propagation();
+                                        this.open_restore_form(file.clone(), cx);
+                                    }),
+                                ))
                                 .into_any()
                         })),
                 )
@@ -395,12 +3362,98 @@ impl Workspace {
         }
     }
 
+    /// Entries of File → Open Recent, each with pin and remove controls.
+    fn render_recent_menu(&self, cx: &mut Context<Self>) -> AnyElement {
+        let entries = self.recent.entries();
+        if entries.is_empty() {
+            return div()
+                .pl(px(28.0))
+                .pr(px(16.0))
+                .py(px(6.0))
+                .text_size(px(12.0))
+                .text_color(gpui::rgb(0x888888))
+                .child("No recent repositories")
+                .into_any();
+        }
+        div()
+            .flex()
+            .flex_col()
+            .children(entries.into_iter().enumerate().map(|(index, entry)| {
+                let open_path = entry.path.clone();
+                let pin_path = entry.path.clone();
+                let remove_path = entry.path.clone();
+                let pinned = entry.pinned;
+                div()
+                    .id(("menu_recent", index))
+                    .flex()
+                    .items_center()
+                    .gap_2()
+                    .pl(px(28.0))
+                    .pr(px(8.0))
+                    .py(px(6.0))
+                    .text_size(px(12.0))
+                    .hover(|style| style.bg(gpui::rgb(0x333333)))
+                    .child(div().flex_1().child(format!(
+                        "{}{}",
+                        if pinned { "📌 " } else { "" },
+                        entry.name()
+                    )))
+                    .child(
+                        div()
+                            .id(("menu_recent_pin", index))
+                            .px(px(4.0))
+                            .text_color(gpui::rgb(0x888888))
+                            .hover(|style| style.text_color(gpui::white()))
+                            .child(if pinned { "Unpin" } else { "Pin" })
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(move |this, _event, _window, cx| {
+                                    cx.stop_propagation();
+                                    this.update_recent(
+                                        |recent| recent.set_pinned(&pin_path, !pinned),
+                                        cx,
+                                    );
+                                }),
+                            ),
+                    )
+                    .child(
+                        div()
+                            .id(("menu_recent_remove", index))
+                            .px(px(4.0))
+                            .text_color(gpui::rgb(0x888888))
+                            .hover(|style| style.text_color(gpui::white()))
+                            .child("×")
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(move |this, _event, _window, cx| {
+                                    cx.stop_propagation();
+                                    this.update_recent(|recent| recent.remove(&remove_path), cx);
+                                }),
+                            ),
+                    )
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |this, _event, _window, cx| {
+                            cx.stop_propagation();
+                            this.menu_bar.update(cx, |menu_bar, cx| {
+                                menu_bar.close_dropdown(cx);
+                            });
+                            this.open_recent(&open_path, cx);
+                        }),
+                    )
+            }))
+            .into_any()
+    }
+
     fn on_dropdown_changed(
         &mut self,
         _menu_bar: Entity<MenuBar>,
         _event: &DropdownEvent,
         cx: &mut Context<Self>,
     ) {
+        if !self.menu_bar.read(cx).is_dropdown_open() {
+            self.recent_menu_open = false;
+        }
         cx.notify();
     }
 
@@ -420,6 +3473,27 @@ impl Workspace {
             .update(cx, |title_bar, _| title_bar.set_title(title));
     }
 
+    /// Files of the selected commit, empty while they load.
+    pub fn changed_files(&self) -> &[ChangedFile] {
+        &self.changed_files
+    }
+
+    /// Why the files of the selected commit could not be listed.
+    pub fn files_error(&self) -> Option<&str> {
+        self.files_error.as_deref()
+    }
+
+    pub fn file_diff(&self) -> Option<&str> {
+        self.file_diff.as_deref()
+    }
+
+    /// Show the diff of one of the changed files, as clicking it in the list does.
+    pub fn select_file(&mut self, file_index: usize, cx: &mut Context<Self>) {
+        if let Some(dock) = self.dock.clone() {
+            self.on_file_selected(file_index, dock, cx);
+        }
+    }
+
     pub fn set_selected_commit(&mut self, commit: Option<CommitSelected>, cx: &mut Context<Self>) {
         self.selected_commit = commit;
         cx.notify();
@@ -439,13 +3513,10 @@ impl Workspace {
 }
 
 impl EventEmitter<CommitSelected> for Workspace {}
+impl EventEmitter<OpenRepository> for Workspace {}
 
 impl Render for Workspace {
-    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        if let Some(dock) = &self.dock {
-            cx.subscribe(dock, Self::on_commit_selected).detach();
-        }
-
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         cx.subscribe(&self.menu_bar, Self::on_dropdown_changed)
             .detach();
         cx.subscribe(&self.title_bar, Self::on_quit_clicked)
@@ -454,6 +3525,7 @@ impl Render for Workspace {
         let dock = self.dock.clone().unwrap();
         let title_bar = self.title_bar.clone();
         let menu_bar = self.menu_bar.clone();
+        let sidebar = self.sidebar.clone();
 
         // let path_repo = window.use_state(cx, |_, cx| cx.new(|_| "".to_string()));
         // let repo = git2::Repository::open(&path_repo.read(cx).read(cx)).unwrap();
@@ -473,6 +3545,7 @@ impl Render for Workspace {
                         });
                         cx.notify();
                     }
+                    this.close_context_menu(cx);
                 }),
             )
             .child(title_bar)
@@ -482,6 +3555,7 @@ impl Render for Workspace {
                     .flex_1()
                     .flex()
                     .relative()
+                    .child(sidebar)
                     .child(
                         div()
                             .w(gpui::px(300.0))
@@ -504,7 +3578,10 @@ impl Render for Workspace {
                                     cx.notify();
                                 }),
                             )
-                            .child(dock.clone()),
+                            .flex()
+                            .flex_col()
+                            .children(self.render_worktree_row())
+                            .child(div().flex_1().min_h_0().child(dock.clone())),
                     )
                     .child(
                         div()
@@ -523,10 +3600,29 @@ impl Render for Workspace {
                                     cx.notify();
                                 }),
                             )
-                            .child(if self.selected_file.is_some() {
-                                self.render_file_diff(cx)
-                            } else {
-                                self.render_file_list(&dock, cx)
+                            .flex()
+                            .flex_col()
+                            .child(div().flex_1().min_h_0().child(
+                                if let Some(view) = &self.conflict_view {
+                                    view.clone().into_any_element()
+                                } else if let Some(planner) = &self.rebase_planner {
+                                    planner.clone().into_any_element()
+                                } else if let Some(manager) = &self.remote_manager {
+                                    manager.clone().into_any_element()
+                                } else if self.selected_file.is_some() {
+                                    self.render_file_diff(cx)
+                                } else {
+                                    self.render_file_list(&dock, cx)
+                                },
+                            ))
+                            .when_some(self.sync_status.as_ref(), |this, status| {
+                                this.child(self.render_sync_status(status, cx))
+                            })
+                            .when_some(self.rebase_status.as_ref(), |this, status| {
+                                this.child(self.render_sync_status(status, cx))
+                            })
+                            .when_some(self.notice.clone(), |this, notice| {
+                                this.child(self.render_notice(notice, cx))
                             }),
                     ),
             )
@@ -558,10 +3654,31 @@ impl Render for Workspace {
                                 .hover(|style| style.bg(gpui::rgb(0x333333)))
                                 .on_mouse_down(
                                     MouseButton::Left,
-                                    cx.listener(|this, _event, _window, cx| {
+                                    cx.listener(|this, _event, window, cx| {
+                                        this.menu_bar.update(cx, |menu_bar, cx| {
+                                            menu_bar.close_dropdown(cx);
+                                        });
+                                        this.open_clone_form(window, cx);
+                                        cx.notify();
+                                        cx.stop_propagation();
+                                    }),
+                                ),
+                        )
+                        .child(
+                            div()
+                                .id("menu_item_init")
+                                .text_color(gpui::white())
+                                .px(px(16.0))
+                                .py(px(8.0))
+                                .child("Init repository")
+                                .hover(|style| style.bg(gpui::rgb(0x333333)))
+                                .on_mouse_down(
+                                    MouseButton::Left,
+                                    cx.listener(|this, _event, window, cx| {
                                         this.menu_bar.update(cx, |menu_bar, cx| {
                                             menu_bar.close_dropdown(cx);
                                         });
+                                        this.open_init_form(window, cx);
                                         cx.notify();
                                         cx.stop_propagation();
                                     }),
@@ -589,6 +3706,30 @@ impl Render for Workspace {
                                     }),
                                 ),
                         )
+                        .child(
+                            div()
+                                .id("menu_item_open_recent")
+                                .text_color(gpui::white())
+                                .px(px(16.0))
+                                .py(px(8.0))
+                                .child(if self.recent_menu_open {
+                                    "Open Recent ▾"
+                                } else {
+                                    "Open Recent ▸"
+                                })
+                                .hover(|style| style.bg(gpui::rgb(0x333333)))
+                                .on_mouse_down(
+                                    MouseButton::Left,
+                                    cx.listener(|this, _event, _window, cx| {
+                                        this.recent_menu_open = !this.recent_menu_open;
+                                        cx.notify();
+                                        cx.stop_propagation();
+                                    }),
+                                ),
+                        )
+                        .when(self.recent_menu_open, |this| {
+                            this.child(self.render_recent_menu(cx))
+                        })
                         .child(
                             div()
                                 .id("menu_item_save")
@@ -608,6 +3749,25 @@ impl Render for Workspace {
                                     }),
                                 ),
                         )
+                        .child(
+                            div()
+                                .id("menu_item_settings")
+                                .text_color(gpui::white())
+                                .px(px(16.0))
+                                .py(px(8.0))
+                                .child("Settings…")
+                                .hover(|style| style.bg(gpui::rgb(0x333333)))
+                                .on_mouse_down(
+                                    MouseButton::Left,
+                                    cx.listener(|this, _event, window, cx| {
+                                        this.menu_bar.update(cx, |menu_bar, cx| {
+                                            menu_bar.close_dropdown(cx);
+                                        });
+                                        this.open_settings_form(window, cx);
+                                        cx.stop_propagation();
+                                    }),
+                                ),
+                        )
                         .child(
                             div()
                                 .id("menu_item_exit")
@@ -629,5 +3789,38 @@ impl Render for Workspace {
                         ),
                 )
             })
+            .when_some(self.context_menu.clone(), |this, menu| {
+                this.child(self.render_context_menu(&menu, cx))
+            })
+            .when_some(self.tag_form.as_ref(), |this, form| {
+                this.child(self.render_tag_form(form, cx))
+            })
+            .when_some(self.merge_form.as_ref(), |this, form| {
+                this.child(self.render_merge_form(form, cx))
+            })
+            .when_some(self.reset_form.as_ref(), |this, form| {
+                this.child(self.render_reset_form(form, cx))
+            })
+            .when_some(self.clone_form.as_ref(), |this, form| {
+                this.child(self.render_clone_form(form, cx))
+            })
+            .when_some(self.init_form.as_ref(), |this, form| {
+                this.child(self.render_init_form(form, cx))
+            })
+            .when_some(self.settings_form.as_ref(), |this, form| {
+                this.child(self.render_settings_form(form, cx))
+            })
+            .when_some(self.stash_form.as_ref(), |this, form| {
+                this.child(self.render_stash_form(form, cx))
+            })
+            .when_some(self.credential_form.as_ref(), |this, form| {
+                this.child(self.render_credential_form(form, cx))
+            })
+            .when_some(self.discard_form.as_ref(), |this, form| {
+                this.child(self.render_discard_form(form, cx))
+            })
+            .when_some(self.restore_form.as_ref(), |this, form| {
+                this.child(self.render_restore_form(form, cx))
+            })
     }
 }
@@ -0,0 +1,190 @@
+use gpig::backend::{Backend, Head, MemoryBackend, RepoHandle};
+use gpig::garph::Garph;
+use gpig::settings::DiffLimits;
+use gpig::workspace::Workspace;
+
+#[cfg(test)]
+mod test_backend {
+    use git2::{Delta, Oid};
+    use gpui::{AppContext, TestAppContext};
+
+    use super::*;
+
+    fn garph(backend: &MemoryBackend) -> Garph {
+        let mut garph = Garph::from_handle(RepoHandle::Memory(backend.clone()));
+        garph.recompute();
+        garph
+    }
+
+    #[test]
+    fn memory_backend_lays_out_branches_merges_and_tags() {
+        let backend = MemoryBackend::new("main");
+        let first = backend.commit("first", &[("a.txt", "a\n")]);
+        let side = backend.commit("side", &[("b.txt", "b\n")]);
+        backend.checkout(first);
+        let main = backend.commit("main", &[("a.txt", "aa\n")]);
+        let merge = backend.merge("merge side", side);
+        backend.tag("v1", first);
+
+        let garph = garph(&backend);
+
+        let rows: Vec<Oid> = garph.nodes().iter().map(|node| node.oid).collect();
+        assert_eq!(rows, vec![merge, main, side, first]);
+        assert_eq!(garph.nodes()[0].parents, vec![main, side]);
+        assert_eq!(garph.tags_for(&first), vec!["v1".to_string()]);
+        assert_eq!(garph.layout_error(), None);
+    }
+
+    #[test]
+    fn memory_backend_unborn_and_missing_parent() {
+        let backend = MemoryBackend::new("trunk");
+        assert_eq!(backend.resolve_head(), Head::Unborn("trunk".to_string()));
+        assert_eq!(garph(&backend).unborn_branch(), Some("trunk"));
+
+        let first = backend.commit("first", &[("a.txt", "a\n")]);
+        let second = backend.commit("second", &[("a.txt", "b\n")]);
+        backend.forget(first);
+
+        let garph = garph(&backend);
+        let rows: Vec<_> = garph
+            .nodes()
+            .iter()
+            .map(|node| (node.oid, node.truncated))
+            .collect();
+        assert_eq!(rows, vec![(second, true)]);
+    }
+
+    #[test]
+    fn memory_backend_changed_files_and_diffs() {
+        let backend = MemoryBackend::new("main");
+        backend.commit("first", &[("a.txt", "one\ntwo\nthree\n"), ("b.txt", "b\n")]);
+        let second = backend.commit("second", &[("a.txt", "one\n2\nthree\n"), ("c.bin", "\0")]);
+        let third = backend.remove("third", &["b.txt"]);
+
+        let files: Vec<_> = backend
+            .changed_files(second)
+            .unwrap()
+            .into_iter()
+            .map(|file| (file.path, file.status))
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                ("a.txt".to_string(), Delta::Modified),
+                ("c.bin".to_string(), Delta::Added),
+            ]
+        );
+        assert_eq!(
+            backend.changed_files(third).unwrap()[0].status,
+            Delta::Deleted
+        );
+
+        let diff = backend
+            .file_diff(second, "a.txt", &DiffLimits::default())
+            .unwrap();
+        assert!(diff.contains(" one\n-two\n+2\n three"), "{}", diff);
+        assert!(
+            backend
+                .file_diff(second, "c.bin", &DiffLimits::default())
+                .is_err()
+        );
+        assert!(
+            backend
+                .file_diff(second, "b.txt", &DiffLimits::default())
+                .is_err()
+        );
+    }
+
+    #[gpui::test]
+    fn sidebar_refs_come_from_the_backend(cx: &mut TestAppContext) {
+        let backend = MemoryBackend::new("main");
+        let first = backend.commit("first", &[("a.txt", "a\n")]);
+        let second = backend.commit("second", &[("a.txt", "b\n")]);
+        backend.branch("feature", first);
+        backend.tag("v2", second);
+        backend.tag("v1", first);
+        let garph = cx.new(|_| Garph::from_handle(RepoHandle::Memory(backend.clone())));
+
+        let refs = garph.read_with(cx, |garph, _| garph.list_refs()).unwrap();
+        let branches: Vec<_> = refs
+            .local_branches
+            .iter()
+            .map(|branch| (branch.name.as_str(), branch.oid, branch.is_head))
+            .collect();
+        assert_eq!(
+            branches,
+            vec![("feature", first, false), ("main", second, true)]
+        );
+        let tags: Vec<_> = refs.tags.iter().map(|tag| tag.name.as_str()).collect();
+        assert_eq!(tags, vec!["v1", "v2"]);
+
+        let workspace = cx.new(|cx| Workspace::new(Some(garph.clone()), cx));
+        cx.run_until_parked();
+        assert_eq!(
+            workspace.read_with(cx, |workspace, _| workspace.notice().map(String::from)),
+            None
+        );
+    }
+
+    #[gpui::test]
+    fn graph_layout_runs_in_the_background(cx: &mut TestAppContext) {
+        let backend = MemoryBackend::new("main");
+        backend.commit("first", &[("a.txt", "a\n")]);
+        backend.commit("second", &[("a.txt", "b\n")]);
+        let garph = cx.new(|_| Garph::from_handle(RepoHandle::Memory(backend.clone())));
+
+        garph.update(cx, |garph, cx| garph.refresh(cx));
+        assert!(garph.read_with(cx, |garph, _| garph.is_loading()));
+
+        cx.run_until_parked();
+        garph.read_with(cx, |garph, _| {
+            assert!(!garph.is_loading());
+            assert_eq!(garph.nodes().len(), 2);
+        });
+    }
+
+    #[gpui::test]
+    fn workspace_loads_changed_files_and_diff_for_the_selected_commit(cx: &mut TestAppContext) {
+        let backend = MemoryBackend::new("main");
+        backend.commit("first", &[("a.txt", "a\n")]);
+        let second = backend.commit("second", &[("a.txt", "b\n"), ("b.txt", "new\n")]);
+        let garph = cx.new(|_| Garph::from_handle(RepoHandle::Memory(backend.clone())));
+        let workspace = cx.new(|cx| Workspace::new(Some(garph.clone()), cx));
+
+        garph.update(cx, |garph, cx| garph.select_commit(second, cx));
+        cx.run_until_parked();
+        let paths: Vec<String> = workspace.read_with(cx, |workspace, _| {
+            workspace
+                .changed_files()
+                .iter()
+                .map(|file| file.path.clone())
+                .collect()
+        });
+        assert_eq!(paths, vec!["a.txt", "b.txt"]);
+
+        workspace.update(cx, |workspace, cx| workspace.select_file(0, cx));
+        cx.run_until_parked();
+        let diff = workspace.read_with(cx, |workspace, _| workspace.file_diff().map(String::from));
+        let diff = diff.unwrap();
+        assert!(diff.contains("-a\n+b"), "{}", diff);
+    }
+
+    #[gpui::test]
+    fn workspace_shows_why_changed_files_failed(cx: &mut TestAppContext) {
+        let backend = MemoryBackend::new("main");
+        let first = backend.commit("first", &[("a.txt", "a\n")]);
+        let second = backend.commit("second", &[("a.txt", "b\n")]);
+        backend.forget(first);
+        let garph = cx.new(|_| Garph::from_handle(RepoHandle::Memory(backend.clone())));
+        let workspace = cx.new(|cx| Workspace::new(Some(garph.clone()), cx));
+        cx.run_until_parked();
+
+        garph.update(cx, |garph, cx| garph.select_commit(second, cx));
+        cx.run_until_parked();
+        workspace.read_with(cx, |workspace, _| {
+            assert!(workspace.changed_files().is_empty());
+            let error = workspace.files_error().unwrap();
+            assert!(error.contains(&first.to_string()), "{}", error);
+        });
+    }
+}
@@ -0,0 +1,48 @@
+use gpig::error::GpigError;
+use gpig::fixture::Fixture;
+use gpig::garph::Garph;
+use gpig::settings::DiffLimits;
+
+#[cfg(test)]
+mod test_background {
+    use git2::{Oid, Repository};
+
+    use super::*;
+
+    #[test]
+    fn repo_handle_opens_the_repository_on_another_thread() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        Fixture::commit_file(&repo, "a.txt", "a\n", "first");
+        let oid = Fixture::commit_file(&repo, "a.txt", "b\n", "second");
+        let garph = Garph::new(Some(Repository::open(dir.path()).unwrap()));
+        let handle = garph.repo_handle().unwrap();
+
+        let (files, diff) = std::thread::spawn(move || {
+            let backend = handle.open().unwrap();
+            (
+                backend.changed_files(oid).unwrap(),
+                backend
+                    .file_diff(oid, "a.txt", &DiffLimits::default())
+                    .unwrap(),
+            )
+        })
+        .join()
+        .unwrap();
+
+        let paths: Vec<_> = files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(paths, vec!["a.txt"]);
+        assert_eq!(diff, garph.compute_file_diff(&oid, "a.txt").unwrap());
+        assert!(diff.contains("+b"), "{}", diff);
+    }
+
+    #[test]
+    fn no_repository_has_no_handle() {
+        let garph = Garph::new(None);
+        assert!(garph.repo_handle().is_none());
+        assert_eq!(
+            garph.get_changed_files(&Oid::zero()).err(),
+            Some(GpigError::NoRepository)
+        );
+    }
+}
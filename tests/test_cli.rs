@@ -0,0 +1,51 @@
+use gpig::cli::{self, Launch};
+use gpig::fixture::Fixture;
+
+#[cfg(test)]
+mod test_cli {
+    use std::ffi::OsString;
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<OsString> {
+        values.iter().map(OsString::from).collect()
+    }
+
+    fn open(path: &str, explicit: bool) -> Launch {
+        Launch::Open {
+            path: PathBuf::from(path),
+            explicit,
+        }
+    }
+
+    #[test]
+    fn argument_wins_over_env_and_current_directory() {
+        assert_eq!(
+            cli::parse(args(&["/work/repo"]), Some("/env/repo".into())),
+            open("/work/repo", true)
+        );
+        assert_eq!(
+            cli::parse(args(&[]), Some("/env/repo".into())),
+            open("/env/repo", true)
+        );
+        assert_eq!(cli::parse(args(&[]), Some("".into())), open(".", false));
+        assert_eq!(cli::parse(args(&[]), None), open(".", false));
+        assert_eq!(cli::parse(args(&["--help"]), None), Launch::Help);
+    }
+
+    #[test]
+    fn discover_finds_the_enclosing_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let _fixture = Fixture::new(dir.path()).commit("first", &[("src/deep/a.txt", "a\n")]);
+
+        let repo = cli::discover(&dir.path().join("src/deep")).unwrap();
+        assert_eq!(
+            repo.workdir().unwrap().canonicalize().unwrap(),
+            dir.path().canonicalize().unwrap()
+        );
+
+        let outside = tempfile::tempdir().unwrap();
+        assert!(cli::discover(outside.path()).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+use gpig::conflict::{self, ConflictChunk, Resolution};
+use gpig::fixture::Fixture;
+use gpig::operations::{self, MergeMode};
+
+#[cfg(test)]
+mod test_conflict {
+    use std::path::Path;
+
+    use git2::Repository;
+
+    use super::*;
+
+    /// Merge a branch that changed the middle line differently from main.
+    fn conflicted_repo(dir: &Path) -> Repository {
+        let repo = Fixture::init(dir);
+        let base = Fixture::commit_file(&repo, "a.txt", "one\ntwo\nthree\n", "base");
+        Fixture::checkout_branch(&repo, "feature", base);
+        let theirs = Fixture::commit_file(&repo, "a.txt", "one\nfeature\nthree\n", "feature");
+        Fixture::checkout_branch(&repo, "main", base);
+        Fixture::commit_file(&repo, "a.txt", "one\nmain\nthree\n", "main");
+        operations::merge(&repo, theirs, MergeMode::NoFastForward).unwrap();
+        repo
+    }
+
+    #[test]
+    fn parse_merge_output_splits_chunks() {
+        let text =
+            "one\n<<<<<<< ours\nmain\n||||||| base\ntwo\n=======\nfeature\n>>>>>>> theirs\nthree\n";
+
+        let chunks = conflict::parse_merge_output(text);
+
+        assert_eq!(
+            chunks,
+            vec![
+                ConflictChunk::Clean(vec!["one\n".to_string()]),
+                ConflictChunk::Conflict {
+                    base: vec!["two\n".to_string()],
+                    ours: vec!["main\n".to_string()],
+                    theirs: vec!["feature\n".to_string()],
+                    resolution: None,
+                },
+                ConflictChunk::Clean(vec!["three\n".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_merge_output_keeps_marker_like_text() {
+        let text = "Title\n=======\n<<<<<<< ours\nmain\n=======\n||||||| base\ntwo\n=======\nfeature\n>>>>>>> theirs\n<<<<<<< ours\nlost?\n";
+
+        let chunks = conflict::parse_merge_output(text);
+
+        assert_eq!(
+            chunks,
+            vec![
+                ConflictChunk::Clean(vec!["Title\n".to_string(), "=======\n".to_string()]),
+                ConflictChunk::Conflict {
+                    base: vec!["two\n".to_string()],
+                    ours: vec!["main\n".to_string(), "=======\n".to_string()],
+                    theirs: vec!["feature\n".to_string()],
+                    resolution: None,
+                },
+                ConflictChunk::Clean(vec!["<<<<<<< ours\n".to_string(), "lost?\n".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn load_conflict_reads_three_sides() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = conflicted_repo(dir.path());
+
+        let file = conflict::load_conflict(&repo, "a.txt").unwrap();
+
+        assert_eq!(file.conflict_indices().len(), 1);
+        let chunk = &file.chunks[file.conflict_indices()[0]];
+        let ConflictChunk::Conflict {
+            base, ours, theirs, ..
+        } = chunk
+        else {
+            panic!("expected a conflict chunk");
+        };
+        assert_eq!(base, &vec!["two\n".to_string()]);
+        assert_eq!(ours, &vec!["main\n".to_string()]);
+        assert_eq!(theirs, &vec!["feature\n".to_string()]);
+        assert_eq!(file.result_text(), None);
+    }
+
+    #[test]
+    fn load_conflict_refuses_text_that_is_not_utf8() {
+        let dir = tempfile::tempdir().unwrap();
+        let fixture = Fixture::new(dir.path())
+            .commit_bytes("base", &[("a.txt", b"caf\xe9\n")])
+            .branch("feature")
+            .commit_bytes("feature", &[("a.txt", b"caf\xe9 au lait\n")])
+            .checkout("main")
+            .commit_bytes("main", &[("a.txt", b"caf\xe9 noir\n")]);
+        let repo = fixture.repo();
+        operations::merge(repo, fixture.oid("feature"), MergeMode::NoFastForward).unwrap();
+
+        let error = conflict::load_conflict(repo, "a.txt").unwrap_err();
+
+        assert!(error.message().contains("not UTF-8"), "{}", error.message());
+        let on_disk = std::fs::read(dir.path().join("a.txt")).unwrap();
+        assert!(on_disk.windows(4).any(|w| w == b"caf\xe9"));
+    }
+
+    #[test]
+    fn mark_resolved_writes_result_and_clears_conflict() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = conflicted_repo(dir.path());
+        let mut file = conflict::load_conflict(&repo, "a.txt").unwrap();
+
+        assert!(conflict::mark_resolved(&repo, &file).is_err());
+
+        let index = file.conflict_indices()[0];
+        file.resolve(index, Resolution::Both);
+        conflict::mark_resolved(&repo, &file).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+            "one\nmain\nfeature\nthree\n"
+        );
+        assert!(!repo.index().unwrap().has_conflicts());
+        let state = operations::conflict_state(&repo).unwrap().unwrap();
+        assert!(state.paths.is_empty());
+    }
+
+    #[test]
+    fn custom_resolution_replaces_chunk() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = conflicted_repo(dir.path());
+        let mut file = conflict::load_conflict(&repo, "a.txt").unwrap();
+
+        let index = file.conflict_indices()[0];
+        file.resolve(index, Resolution::Custom(vec!["merged\n".to_string()]));
+
+        assert_eq!(file.result_text().as_deref(), Some("one\nmerged\nthree\n"));
+        assert!(conflict::load_conflict(&repo, "missing.txt").is_err());
+    }
+}
@@ -0,0 +1,227 @@
+use gpig::credentials::{
+    self, CredentialAnswer, CredentialCache, CredentialChain, CredentialRequest,
+};
+
+#[cfg(test)]
+mod test_credentials {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+
+    use git2::{Config, CredentialType, FetchOptions, RemoteCallbacks, Repository};
+
+    use super::*;
+
+    const URL: &str = "https://example.com/repo.git";
+
+    fn answer(username: &str, secret: &str, remember: bool) -> CredentialAnswer {
+        CredentialAnswer {
+            username: username.to_string(),
+            secret: secret.to_string(),
+            remember,
+        }
+    }
+
+    /// A config without any credential helper, isolated from the user's.
+    fn empty_config(dir: &Path) -> Config {
+        Config::open(&dir.join("config")).unwrap()
+    }
+
+    fn userpass(chain: &mut CredentialChain, config: &Config) -> Result<git2::Cred, git2::Error> {
+        chain.credentials(config, URL, None, CredentialType::USER_PASS_PLAINTEXT)
+    }
+
+    #[test]
+    fn prompt_answers_are_cached_for_the_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = empty_config(dir.path());
+        let cache = CredentialCache::new();
+        let mut chain = CredentialChain::new()
+            .with_key_files(Vec::new())
+            .with_cache(cache.clone())
+            .with_prompt(|_| Some(answer("bob", "secret", true)));
+
+        let cred = userpass(&mut chain, &config).unwrap();
+        assert_eq!(
+            cred.credtype(),
+            git2::CredentialType::USER_PASS_PLAINTEXT.bits()
+        );
+        assert_eq!(cache.len(), 1);
+
+        // a later operation uses the cache without asking
+        let mut chain = CredentialChain::new()
+            .with_cache(cache.clone())
+            .with_prompt(|_| panic!("the cached password should be used"));
+        userpass(&mut chain, &config).unwrap();
+
+        // asked again means the cached password was rejected
+        let mut chain = CredentialChain::new()
+            .with_cache(cache.clone())
+            .with_prompt(|_| None);
+        userpass(&mut chain, &config).unwrap();
+        let error = userpass(&mut chain, &config).err().unwrap();
+        assert_eq!(error.message(), "authentication cancelled");
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn credential_helper_is_tried_before_prompting() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = empty_config(dir.path());
+        config
+            .set_str(
+                "credential.helper",
+                "!f() { echo username=helper; echo password=from-helper; }; f",
+            )
+            .unwrap();
+        let asked = Arc::new(Mutex::new(0));
+        let counter = asked.clone();
+        let mut chain = CredentialChain::new().with_prompt(move |_| {
+            *counter.lock().unwrap() += 1;
+            Some(answer("bob", "secret", false))
+        });
+
+        let cred = userpass(&mut chain, &config).unwrap();
+        assert!(cred.has_username());
+        assert_eq!(*asked.lock().unwrap(), 0);
+
+        userpass(&mut chain, &config).unwrap();
+        assert_eq!(*asked.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn ssh_tries_agent_then_key_files_then_passphrase() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = empty_config(dir.path());
+        let key = dir.path().join("id_ed25519");
+        std::fs::write(&key, "not really a key").unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        let mut chain = CredentialChain::new()
+            .with_key_files(vec![dir.path().join("missing"), key.clone()])
+            .with_prompt(move |request| {
+                seen.lock().unwrap().push(request);
+                Some(answer("", "passphrase", false))
+            });
+        let mut ssh = || chain.credentials(&config, URL, Some("git"), CredentialType::SSH_KEY);
+
+        ssh().unwrap();
+        ssh().unwrap();
+        assert!(requests.lock().unwrap().is_empty());
+        ssh().unwrap();
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec![CredentialRequest::Passphrase {
+                url: URL.to_string(),
+                key: key.clone()
+            }]
+        );
+    }
+
+    #[test]
+    fn key_files_come_from_ssh_command_and_config() {
+        let home = Path::new("/home/gpig");
+        let ssh_config = "\
+# work keys
+Host *.example.com !private.example.com
+    IdentityFile ~/.ssh/work_%r
+Host = github.com
+    IdentityFile=\"/keys/git hub\"
+Match host example.com
+    IdentityFile ~/.ssh/ignored
+Host *
+    IdentityFile %d/.ssh/%h
+";
+
+        assert_eq!(
+            credentials::configured_key_files(
+                Some("ssh -i ~/deploy -o IdentityFile=/keys/other"),
+                ssh_config,
+                "ssh://deploy@git.example.com:2222/repo.git",
+                home,
+            ),
+            vec![
+                home.join("deploy"),
+                Path::new("/keys/other").to_path_buf(),
+                home.join(".ssh/work_deploy"),
+                home.join(".ssh/git.example.com"),
+            ]
+        );
+        assert_eq!(
+            credentials::configured_key_files(None, ssh_config, "git@github.com:o/r.git", home),
+            vec![
+                Path::new("/keys/git hub").to_path_buf(),
+                home.join(".ssh/github.com"),
+            ]
+        );
+        assert_eq!(
+            credentials::configured_key_files(None, "", "git@private.example.com:r", home).first(),
+            Some(&home.join(".ssh/id_rsa"))
+        );
+    }
+
+    /// An http remote that rejects every request, recording the credentials it was sent.
+    fn reject_all() -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/repo.git", listener.local_addr().unwrap());
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorded = seen.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    break;
+                };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap_or(0) > 0 {
+                    if line == "\r\n" {
+                        break;
+                    }
+                    if let Some(auth) = line.strip_prefix("Authorization: Basic ") {
+                        recorded.lock().unwrap().push(auth.trim().to_string());
+                    }
+                    line.clear();
+                }
+                let _ = stream.write_all(
+                    b"HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: Basic realm=\"gpig\"\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                );
+            }
+        });
+        (url, seen)
+    }
+
+    #[test]
+    fn rejected_http_credentials_prompt_until_the_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let (url, seen) = reject_all();
+        let mut remote = repo.remote("origin", &url).unwrap();
+        let config = empty_config(dir.path());
+        let prompts = Arc::new(Mutex::new(0));
+        let counter = prompts.clone();
+        let mut chain = CredentialChain::new().with_prompt(move |request| {
+            assert!(matches!(request, CredentialRequest::UserPass { .. }));
+            *counter.lock().unwrap() += 1;
+            Some(answer("bob", "wrong", false))
+        });
+
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.credentials(|url, username, allowed| {
+            chain.credentials(&config, url, username, allowed)
+        });
+        let mut options = FetchOptions::new();
+        options.remote_callbacks(callbacks);
+        let result = remote.fetch(&[] as &[&str], Some(&mut options), None);
+
+        assert!(result.is_err());
+        assert_eq!(*prompts.lock().unwrap(), 3);
+        // "bob:wrong"
+        assert!(
+            seen.lock()
+                .unwrap()
+                .iter()
+                .any(|auth| auth == "Ym9iOndyb25n")
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use gpig::error::GpigError;
+use gpig::fixture::Fixture;
+use gpig::garph::Garph;
+
+#[cfg(test)]
+mod test_error {
+    use git2::{ErrorClass, ErrorCode, Repository};
+
+    use super::*;
+
+    fn numbered_lines(count: usize) -> Vec<u8> {
+        (0..count)
+            .map(|n| format!("line {}\n", n))
+            .collect::<String>()
+            .into_bytes()
+    }
+
+    #[test]
+    fn git_errors_keep_class_and_code() {
+        let error = GpigError::from(git2::Error::new(
+            ErrorCode::NotFound,
+            ErrorClass::Reference,
+            "reference not found",
+        ));
+        assert_eq!(
+            error,
+            GpigError::Git {
+                class: ErrorClass::Reference,
+                code: ErrorCode::NotFound,
+                message: "reference not found".to_string(),
+            }
+        );
+        assert_eq!(error.code(), Some(ErrorCode::NotFound));
+
+        let error = GpigError::from(git2::Error::new(
+            ErrorCode::User,
+            ErrorClass::None,
+            "no error",
+        ));
+        // only the diff walks treat a callback stop as hitting the limits
+        assert_eq!(error.code(), Some(ErrorCode::User));
+    }
+
+    #[test]
+    fn no_repository_is_its_own_variant() {
+        let garph = Garph::new(None);
+        assert_eq!(garph.list_refs().err(), Some(GpigError::NoRepository));
+        assert_eq!(garph.delete_tag("v1").err(), Some(GpigError::NoRepository));
+    }
+
+    #[test]
+    fn file_diff_reports_binary_and_missing_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let oid = Fixture::commit_files(
+            &repo,
+            &[("image.bin", &[0, 1, 2, 0, 3]), ("a.txt", b"a\n")],
+            "first",
+        );
+        let garph = Garph::new(Some(Repository::open(dir.path()).unwrap()));
+
+        assert_eq!(
+            garph.compute_file_diff(&oid, "image.bin").err(),
+            Some(GpigError::BinaryFile {
+                path: "image.bin".to_string(),
+                size: 5,
+            })
+        );
+        assert_eq!(
+            garph.compute_file_diff(&oid, "missing.txt").err(),
+            Some(GpigError::PathNotInDiff("missing.txt".to_string()))
+        );
+    }
+
+    #[test]
+    fn diffs_over_the_limits_are_truncated_not_failed() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        Fixture::commit_files(&repo, &[("big.txt", b"start\n")], "first");
+        let files: Vec<(String, Vec<u8>)> = (0..12)
+            .map(|n| (format!("f{}.txt", n), numbered_lines(5)))
+            .chain([("big.txt".to_string(), numbered_lines(400))])
+            .collect();
+        let files: Vec<(&str, &[u8])> = files
+            .iter()
+            .map(|(path, content)| (path.as_str(), content.as_slice()))
+            .collect();
+        let oid = Fixture::commit_files(&repo, &files, "many changes");
+        let garph = Garph::new(Some(Repository::open(dir.path()).unwrap()));
+
+        let file_diff = garph.compute_file_diff(&oid, "big.txt").unwrap();
+        assert!(file_diff.contains("diff truncated"), "{}", file_diff);
+
+        let commit_diff = garph.compute_commit_diff(&oid).unwrap();
+        assert!(commit_diff.contains("diff truncated"), "{}", commit_diff);
+    }
+}
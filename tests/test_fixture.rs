@@ -0,0 +1,154 @@
+use gpig::error::GpigError;
+use gpig::fixture::Fixture;
+use gpig::garph::Garph;
+
+#[cfg(test)]
+mod test_fixture {
+    use git2::{Delta, Oid};
+
+    use super::*;
+
+    fn graph(fixture: &Fixture) -> Garph {
+        let mut garph = Garph::new(Some(fixture.open()));
+        garph.recompute();
+        garph
+    }
+
+    fn rows(garph: &Garph) -> Vec<Oid> {
+        garph.nodes().iter().map(|node| node.oid).collect()
+    }
+
+    fn changes(garph: &Garph, oid: Oid) -> Vec<(String, Delta)> {
+        garph
+            .get_changed_files(&oid)
+            .unwrap()
+            .into_iter()
+            .map(|file| (file.path, file.status))
+            .collect()
+    }
+
+    #[test]
+    fn branch_and_merge_take_two_lanes() {
+        let dir = tempfile::tempdir().unwrap();
+        let fixture = Fixture::new(dir.path())
+            .commit("first", &[("a.txt", "a\n")])
+            .branch("feature")
+            .commit("feature work", &[("b.txt", "b\n")])
+            .checkout("main")
+            .commit("main work", &[("a.txt", "aa\n")])
+            .merge("merge feature", &["feature"])
+            .tag("v1");
+
+        let garph = graph(&fixture);
+
+        assert_eq!(
+            rows(&garph),
+            vec![
+                fixture.oid("merge feature"),
+                fixture.oid("main work"),
+                fixture.oid("feature work"),
+                fixture.oid("first"),
+            ]
+        );
+        let x: Vec<_> = garph.nodes().iter().map(|node| node.position.x).collect();
+        assert_eq!(x[0], x[1]);
+        assert!(x[2] > x[1], "the feature commit sits in a second lane");
+        assert_eq!(x[3], x[0]);
+        assert_eq!(
+            garph.tags_for(&fixture.oid("merge feature")),
+            vec!["v1".to_string()]
+        );
+        assert_eq!(
+            changes(&garph, fixture.oid("merge feature")),
+            vec![("b.txt".to_string(), Delta::Added)]
+        );
+    }
+
+    #[test]
+    fn octopus_merge_has_every_branch_as_parent() {
+        let dir = tempfile::tempdir().unwrap();
+        let fixture = Fixture::new(dir.path())
+            .commit("first", &[("a.txt", "a\n")])
+            .branch("one")
+            .commit("one", &[("one.txt", "1\n")])
+            .checkout("main")
+            .branch("two")
+            .commit("two", &[("two.txt", "2\n")])
+            .checkout("main")
+            .branch("three")
+            .commit("three", &[("three.txt", "3\n")])
+            .checkout("main")
+            .merge("octopus", &["one", "two", "three"]);
+
+        let garph = graph(&fixture);
+
+        let octopus = &garph.nodes()[0];
+        assert_eq!(octopus.oid, fixture.oid("octopus"));
+        assert_eq!(
+            octopus.parents,
+            vec![
+                fixture.oid("first"),
+                fixture.oid("one"),
+                fixture.oid("two"),
+                fixture.oid("three"),
+            ]
+        );
+        assert_eq!(garph.nodes().len(), 5);
+        for path in ["one.txt", "two.txt", "three.txt"] {
+            assert!(dir.path().join(path).exists(), "{} was checked out", path);
+        }
+    }
+
+    #[test]
+    fn renames_binaries_and_empty_commits() {
+        let dir = tempfile::tempdir().unwrap();
+        let fixture = Fixture::new(dir.path())
+            .commit("first", &[("old.txt", "same\n"), ("gone.txt", "x\n")])
+            .rename("rename", "old.txt", "new.txt")
+            .commit_bytes("binary", &[("image.bin", &[0, 1, 2, 0, 3])])
+            .remove("remove", &["gone.txt"])
+            .empty_commit("empty");
+
+        let garph = graph(&fixture);
+
+        assert_eq!(garph.nodes().len(), 5);
+        assert_eq!(
+            changes(&garph, fixture.oid("rename")),
+            vec![
+                ("new.txt".to_string(), Delta::Added),
+                ("old.txt".to_string(), Delta::Deleted),
+            ]
+        );
+        assert_eq!(
+            garph
+                .compute_file_diff(&fixture.oid("binary"), "image.bin")
+                .err(),
+            Some(GpigError::BinaryFile {
+                path: "image.bin".to_string(),
+                size: 5,
+            })
+        );
+        assert_eq!(
+            changes(&garph, fixture.oid("remove")),
+            vec![("gone.txt".to_string(), Delta::Deleted)]
+        );
+        assert!(changes(&garph, fixture.oid("empty")).is_empty());
+    }
+
+    #[test]
+    fn file_diff_shows_the_changed_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let fixture = Fixture::new(dir.path())
+            .commit("first", &[("src/lib.rs", "fn a() {}\nfn b() {}\n")])
+            .commit("second", &[("src/lib.rs", "fn a() {}\nfn c() {}\n")]);
+
+        let garph = graph(&fixture);
+        let diff = garph
+            .compute_file_diff(&fixture.oid("second"), "src/lib.rs")
+            .unwrap();
+
+        assert!(diff.contains("--- a/src/lib.rs"), "{}", diff);
+        assert!(diff.contains("-fn b() {}"), "{}", diff);
+        assert!(diff.contains("+fn c() {}"), "{}", diff);
+    }
+}
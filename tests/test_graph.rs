@@ -0,0 +1,126 @@
+use gpig::fixture::Fixture;
+use gpig::garph::{Garph, LayoutError};
+
+#[cfg(test)]
+mod test_graph {
+    use std::path::Path;
+
+    use git2::{Oid, Repository};
+
+    use super::*;
+
+    fn object_path(dir: &Path, oid: Oid) -> std::path::PathBuf {
+        let hex = oid.to_string();
+        dir.join(".git/objects").join(&hex[..2]).join(&hex[2..])
+    }
+
+    fn graph(dir: &Path) -> Garph {
+        let mut garph = Garph::new(Some(Repository::open(dir).unwrap()));
+        garph.recompute();
+        garph
+    }
+
+    #[test]
+    fn empty_repository_has_no_commits_and_no_error() {
+        let dir = tempfile::tempdir().unwrap();
+        Fixture::init(dir.path());
+
+        let garph = graph(dir.path());
+
+        assert!(garph.nodes().is_empty());
+        assert_eq!(garph.layout_error(), None);
+        assert_eq!(garph.unborn_branch(), Some("main"));
+    }
+
+    #[test]
+    fn broken_head_is_an_error_and_retry_recovers() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let oid = Fixture::commit_file(&repo, "a.txt", "a\n", "first");
+        let head = dir.path().join(".git/refs/heads/main");
+        std::fs::write(&head, format!("{}\n", "1".repeat(40))).unwrap();
+
+        let mut garph = graph(dir.path());
+        assert!(matches!(
+            garph.layout_error(),
+            Some(LayoutError::BrokenHead(_))
+        ));
+        assert!(garph.nodes().is_empty());
+
+        std::fs::write(&head, format!("{}\n", oid)).unwrap();
+        garph.retry();
+        assert_eq!(garph.layout_error(), None);
+        assert_eq!(garph.nodes().len(), 1);
+    }
+
+    #[test]
+    fn detached_head_is_laid_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let first = Fixture::commit_file(&repo, "a.txt", "a\n", "first");
+        Fixture::commit_file(&repo, "a.txt", "b\n", "second");
+        repo.set_head_detached(first).unwrap();
+
+        let garph = graph(dir.path());
+
+        assert_eq!(garph.layout_error(), None);
+        assert_eq!(garph.nodes().len(), 1);
+    }
+
+    #[test]
+    fn shallow_boundary_is_truncated() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        Fixture::commit_file(&repo, "a.txt", "a\n", "first");
+        let second = Fixture::commit_file(&repo, "a.txt", "b\n", "second");
+        let third = Fixture::commit_file(&repo, "a.txt", "c\n", "third");
+        std::fs::write(dir.path().join(".git/shallow"), format!("{}\n", second)).unwrap();
+
+        let garph = graph(dir.path());
+
+        assert_eq!(garph.layout_error(), None);
+        let rows: Vec<_> = garph.nodes().iter().map(|n| (n.oid, n.truncated)).collect();
+        assert_eq!(rows, vec![(third, false), (second, true)]);
+    }
+
+    #[test]
+    fn missing_parent_object_is_truncated() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let first = Fixture::commit_file(&repo, "a.txt", "a\n", "first");
+        let second = Fixture::commit_file(&repo, "a.txt", "b\n", "second");
+        std::fs::remove_file(object_path(dir.path(), first)).unwrap();
+
+        let garph = graph(dir.path());
+
+        assert_eq!(garph.layout_error(), None);
+        let rows: Vec<_> = garph.nodes().iter().map(|n| (n.oid, n.truncated)).collect();
+        assert_eq!(rows, vec![(second, true)]);
+    }
+
+    #[test]
+    fn stashes_have_a_color_no_lane_gets() {
+        let dir = tempfile::tempdir().unwrap();
+        let names = ["one", "two", "three", "four", "five", "six"];
+        let mut fixture = Fixture::new(dir.path()).commit("base", &[("a.txt", "a\n")]);
+        for name in names {
+            fixture = fixture
+                .branch(name)
+                .commit(name, &[(&format!("{}.txt", name), "x\n")])
+                .checkout("main");
+        }
+        let fixture = fixture.merge("merge everything", &names);
+        std::fs::write(dir.path().join("a.txt"), "changed\n").unwrap();
+        gpig::stash::save(&mut fixture.open(), Some("wip"), false).unwrap();
+
+        let garph = graph(dir.path());
+
+        let (stashes, commits): (Vec<_>, Vec<_>) =
+            garph.nodes().iter().partition(|node| node.stash.is_some());
+        assert_eq!(stashes.len(), 1);
+        assert!(
+            commits.iter().all(|node| node.color != stashes[0].color),
+            "a lane shares the stash color"
+        );
+    }
+}
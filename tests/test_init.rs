@@ -0,0 +1,73 @@
+use gpig::init::{self, GitignoreTemplate, InitOptions};
+
+#[cfg(test)]
+mod test_init {
+    use std::path::Path;
+
+    use super::*;
+
+    fn options(path: &Path) -> InitOptions {
+        InitOptions {
+            path: path.to_path_buf(),
+            initial_branch: "trunk".to_string(),
+            bare: false,
+            gitignore: None,
+        }
+    }
+
+    #[test]
+    fn init_creates_folder_with_unborn_branch_and_gitignore() {
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().join("nested/project");
+        let repo = init::init(&InitOptions {
+            gitignore: Some(GitignoreTemplate::Rust),
+            ..options(&path)
+        })
+        .unwrap();
+
+        let head = repo.head().err().unwrap();
+        assert_eq!(head.code(), git2::ErrorCode::UnbornBranch);
+        assert_eq!(
+            repo.find_reference("HEAD").unwrap().symbolic_target(),
+            Some("refs/heads/trunk")
+        );
+        let gitignore = std::fs::read_to_string(path.join(".gitignore")).unwrap();
+        assert!(gitignore.contains("/target/"));
+        assert!(gitignore.contains(".DS_Store"));
+    }
+
+    #[test]
+    fn init_bare_has_no_working_tree() {
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().join("server.git");
+        let repo = init::init(&InitOptions {
+            bare: true,
+            ..options(&path)
+        })
+        .unwrap();
+        assert!(repo.is_bare());
+        assert!(repo.workdir().is_none());
+
+        let error = init::init(&InitOptions {
+            bare: true,
+            gitignore: Some(GitignoreTemplate::General),
+            ..options(&root.path().join("other.git"))
+        });
+        assert!(error.is_err());
+    }
+
+    #[test]
+    fn init_rejects_bad_branch_and_existing_repository() {
+        let root = tempfile::tempdir().unwrap();
+        let error = init::init(&InitOptions {
+            initial_branch: "bad..name".to_string(),
+            ..options(root.path())
+        })
+        .err()
+        .unwrap();
+        assert!(error.message().contains("bad..name"));
+
+        init::init(&options(root.path())).unwrap();
+        assert!(init::init(&options(root.path())).is_err());
+    }
+}
@@ -0,0 +1,448 @@
+use gpig::fixture::Fixture;
+use gpig::operations::{self, MergeMode, OperationKind, OperationOutcome, ResetMode};
+
+#[cfg(test)]
+mod test_operations {
+    use std::path::Path;
+
+    use git2::Signature;
+
+    use super::*;
+
+    #[test]
+    fn cherry_pick_applies_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        Fixture::checkout_branch(&repo, "feature", base);
+        let picked = Fixture::commit_file(&repo, "b.txt", "b\n", "add b");
+        Fixture::checkout_branch(&repo, "main", base);
+
+        let outcome = operations::cherry_pick(&repo, picked, None).unwrap();
+
+        let OperationOutcome::Committed(oid) = outcome else {
+            panic!("expected a clean cherry-pick");
+        };
+        let commit = repo.find_commit(oid).unwrap();
+        assert_eq!(commit.message(), Some("add b"));
+        assert_eq!(commit.parent_id(0).unwrap(), base);
+        assert_eq!(repo.state(), git2::RepositoryState::Clean);
+        assert!(dir.path().join("b.txt").exists());
+    }
+
+    #[test]
+    fn cherry_pick_reports_conflicts_and_aborts() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        Fixture::checkout_branch(&repo, "feature", base);
+        let picked = Fixture::commit_file(&repo, "a.txt", "feature\n", "feature change");
+        Fixture::checkout_branch(&repo, "main", base);
+        let head = Fixture::commit_file(&repo, "a.txt", "main\n", "main change");
+
+        let outcome = operations::cherry_pick(&repo, picked, None).unwrap();
+
+        assert_eq!(
+            outcome,
+            OperationOutcome::Conflicts(vec!["a.txt".to_string()])
+        );
+        let state = operations::conflict_state(&repo).unwrap().unwrap();
+        assert_eq!(state.paths, vec!["a.txt".to_string()]);
+
+        operations::abort_operation(&repo).unwrap();
+        assert_eq!(repo.state(), git2::RepositoryState::Clean);
+        assert_eq!(repo.head().unwrap().target(), Some(head));
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+            "main\n"
+        );
+    }
+
+    #[test]
+    fn abort_keeps_unrelated_local_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        Fixture::commit_file(&repo, "notes.txt", "notes\n", "notes");
+        let base = Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        Fixture::checkout_branch(&repo, "feature", base);
+        std::fs::write(dir.path().join("new.txt"), "new\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("new.txt")).unwrap();
+        index.write().unwrap();
+        let picked = Fixture::commit_file(&repo, "a.txt", "feature\n", "feature change");
+        Fixture::checkout_branch(&repo, "main", base);
+        Fixture::commit_file(&repo, "a.txt", "main\n", "main change");
+        std::fs::write(dir.path().join("notes.txt"), "work in progress\n").unwrap();
+        std::fs::write(dir.path().join("scratch.txt"), "scratch\n").unwrap();
+
+        let outcome = operations::cherry_pick(&repo, picked, None).unwrap();
+        assert!(matches!(outcome, OperationOutcome::Conflicts(_)));
+        assert!(
+            operations::revert(&repo, base, None).is_err(),
+            "no second operation while one is stopped"
+        );
+
+        operations::abort_operation(&repo).unwrap();
+        assert_eq!(repo.state(), git2::RepositoryState::Clean);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+            "main\n"
+        );
+        assert!(!dir.path().join("new.txt").exists());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("notes.txt")).unwrap(),
+            "work in progress\n"
+        );
+        assert!(dir.path().join("scratch.txt").exists());
+        assert!(!repo.index().unwrap().has_conflicts());
+    }
+
+    #[test]
+    fn revert_creates_inverse_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        let change = Fixture::commit_file(&repo, "a.txt", "changed\n", "change a");
+
+        let outcome = operations::revert(&repo, change, None).unwrap();
+
+        let OperationOutcome::Committed(oid) = outcome else {
+            panic!("expected a clean revert");
+        };
+        let commit = repo.find_commit(oid).unwrap();
+        assert!(commit.message().unwrap().starts_with("Revert \"change a\""));
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+            "a\n"
+        );
+    }
+
+    /// Stage `staged.txt` so it would end up in the next commit.
+    fn stage_unrelated_file(repo: &git2::Repository, dir: &Path) {
+        std::fs::write(dir.join("staged.txt"), "half done\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("staged.txt")).unwrap();
+        index.write().unwrap();
+    }
+
+    #[test]
+    fn cherry_pick_refuses_staged_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        Fixture::checkout_branch(&repo, "feature", base);
+        let picked = Fixture::commit_file(&repo, "b.txt", "b\n", "add b");
+        Fixture::checkout_branch(&repo, "main", base);
+        stage_unrelated_file(&repo, dir.path());
+
+        let error = operations::cherry_pick(&repo, picked, None).unwrap_err();
+
+        assert!(error.message().contains("staged"), "{}", error);
+        assert_eq!(repo.head().unwrap().target(), Some(base));
+        assert_eq!(repo.state(), git2::RepositoryState::Clean);
+        assert!(!dir.path().join("b.txt").exists());
+    }
+
+    #[test]
+    fn revert_refuses_staged_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        let change = Fixture::commit_file(&repo, "a.txt", "changed\n", "change a");
+        stage_unrelated_file(&repo, dir.path());
+
+        let error = operations::revert(&repo, change, None).unwrap_err();
+
+        assert!(error.message().contains("staged"), "{}", error);
+        assert_eq!(repo.head().unwrap().target(), Some(change));
+        assert_eq!(repo.state(), git2::RepositoryState::Clean);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+            "changed\n"
+        );
+    }
+
+    #[test]
+    fn merge_commit_requires_mainline() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        Fixture::checkout_branch(&repo, "feature", base);
+        let feature = Fixture::commit_file(&repo, "b.txt", "b\n", "add b");
+        Fixture::checkout_branch(&repo, "main", base);
+        let main = Fixture::commit_file(&repo, "c.txt", "c\n", "add c");
+
+        let sig = Signature::now("gpig", "gpig@example.com").unwrap();
+        let mut index = repo
+            .merge_commits(
+                &repo.find_commit(main).unwrap(),
+                &repo.find_commit(feature).unwrap(),
+                None,
+            )
+            .unwrap();
+        let tree = repo.find_tree(index.write_tree_to(&repo).unwrap()).unwrap();
+        let merge = repo
+            .commit(
+                None,
+                &sig,
+                &sig,
+                "merge",
+                &tree,
+                &[
+                    &repo.find_commit(main).unwrap(),
+                    &repo.find_commit(feature).unwrap(),
+                ],
+            )
+            .unwrap();
+
+        assert!(operations::revert(&repo, merge, None).is_err());
+        assert!(operations::revert(&repo, merge, Some(3)).is_err());
+    }
+
+    #[test]
+    fn merge_fast_forward_only_moves_head() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        Fixture::checkout_branch(&repo, "feature", base);
+        let tip = Fixture::commit_file(&repo, "b.txt", "b\n", "add b");
+        Fixture::checkout_branch(&repo, "main", base);
+
+        let preview = operations::preview_merge(&repo, tip).unwrap();
+        assert!(preview.can_fast_forward);
+        assert!(preview.conflicts.is_empty());
+
+        let outcome = operations::merge(&repo, tip, MergeMode::FastForwardOnly).unwrap();
+
+        assert_eq!(outcome, OperationOutcome::Committed(tip));
+        assert_eq!(repo.head().unwrap().target(), Some(tip));
+        assert!(dir.path().join("b.txt").exists());
+    }
+
+    #[test]
+    fn merge_no_fast_forward_creates_merge_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        Fixture::checkout_branch(&repo, "feature", base);
+        let tip = Fixture::commit_file(&repo, "b.txt", "b\n", "add b");
+        Fixture::checkout_branch(&repo, "main", base);
+
+        let outcome = operations::merge(&repo, tip, MergeMode::NoFastForward).unwrap();
+
+        let OperationOutcome::Committed(oid) = outcome else {
+            panic!("expected a clean merge");
+        };
+        let commit = repo.find_commit(oid).unwrap();
+        assert_eq!(commit.parent_ids().collect::<Vec<_>>(), vec![base, tip]);
+        assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    }
+
+    #[test]
+    fn merge_squash_creates_single_parent_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        Fixture::checkout_branch(&repo, "feature", base);
+        let tip = Fixture::commit_file(&repo, "b.txt", "b\n", "add b");
+        Fixture::checkout_branch(&repo, "main", base);
+        let head = Fixture::commit_file(&repo, "c.txt", "c\n", "add c");
+
+        let outcome = operations::merge(&repo, tip, MergeMode::Squash).unwrap();
+
+        let OperationOutcome::Committed(oid) = outcome else {
+            panic!("expected a clean squash");
+        };
+        let commit = repo.find_commit(oid).unwrap();
+        assert_eq!(commit.parent_ids().collect::<Vec<_>>(), vec![head]);
+        assert!(dir.path().join("b.txt").exists());
+        assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    }
+
+    #[test]
+    fn merge_refuses_staged_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        Fixture::checkout_branch(&repo, "feature", base);
+        let tip = Fixture::commit_file(&repo, "b.txt", "b\n", "add b");
+        Fixture::checkout_branch(&repo, "main", base);
+        let head = Fixture::commit_file(&repo, "c.txt", "c\n", "add c");
+        stage_unrelated_file(&repo, dir.path());
+
+        for mode in [MergeMode::NoFastForward, MergeMode::Squash] {
+            let error = operations::merge(&repo, tip, mode).unwrap_err();
+            assert!(error.message().contains("staged"), "{}", error);
+            assert_eq!(repo.head().unwrap().target(), Some(head));
+            assert_eq!(repo.state(), git2::RepositoryState::Clean);
+            assert!(!dir.path().join("b.txt").exists());
+        }
+    }
+
+    #[test]
+    fn leftover_squash_message_or_conflicts_are_not_an_operation() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        // what a clean `git merge --squash` leaves until the user commits
+        std::fs::write(repo.path().join("SQUASH_MSG"), "Squashed commit\n").unwrap();
+
+        assert!(operations::conflict_state(&repo).unwrap().is_none());
+
+        std::fs::remove_file(repo.path().join("SQUASH_MSG")).unwrap();
+        Fixture::checkout_branch(&repo, "feature", base);
+        let tip = Fixture::commit_file(&repo, "a.txt", "feature\n", "feature change");
+        Fixture::checkout_branch(&repo, "main", base);
+        Fixture::commit_file(&repo, "a.txt", "main\n", "main change");
+        operations::cherry_pick(&repo, tip, None).unwrap();
+        // conflicts with no operation state, as after `rm .git/CHERRY_PICK_HEAD`
+        repo.cleanup_state().unwrap();
+
+        assert!(
+            operations::conflict_state(&repo).unwrap().is_none(),
+            "conflicts gpig did not create are not a stash apply"
+        );
+    }
+
+    #[test]
+    fn conflicted_squash_stays_in_progress_once_resolved() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        Fixture::checkout_branch(&repo, "feature", base);
+        let tip = Fixture::commit_file(&repo, "a.txt", "feature\n", "feature change");
+        Fixture::checkout_branch(&repo, "main", base);
+        Fixture::commit_file(&repo, "a.txt", "main\n", "main change");
+
+        let outcome = operations::merge(&repo, tip, MergeMode::Squash).unwrap();
+        assert_eq!(
+            outcome,
+            OperationOutcome::Conflicts(vec!["a.txt".to_string()])
+        );
+        std::fs::write(dir.path().join("a.txt"), "both\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(std::path::Path::new("a.txt")).unwrap();
+        index.write().unwrap();
+
+        let state = operations::conflict_state(&repo).unwrap().unwrap();
+        assert_eq!(state.kind, OperationKind::Squash);
+        operations::continue_operation(&repo).unwrap();
+        assert!(operations::conflict_state(&repo).unwrap().is_none());
+    }
+
+    #[test]
+    fn merge_conflicts_are_previewed_and_leave_merge_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        Fixture::checkout_branch(&repo, "feature", base);
+        let tip = Fixture::commit_file(&repo, "a.txt", "feature\n", "feature change");
+        Fixture::checkout_branch(&repo, "main", base);
+        Fixture::commit_file(&repo, "a.txt", "main\n", "main change");
+
+        let preview = operations::preview_merge(&repo, tip).unwrap();
+        assert!(!preview.can_fast_forward);
+        assert_eq!(preview.conflicts, vec!["a.txt".to_string()]);
+
+        let outcome = operations::merge(&repo, tip, MergeMode::NoFastForward).unwrap();
+
+        assert_eq!(
+            outcome,
+            OperationOutcome::Conflicts(vec!["a.txt".to_string()])
+        );
+        assert_eq!(repo.state(), git2::RepositoryState::Merge);
+        let state = operations::conflict_state(&repo).unwrap().unwrap();
+        assert_eq!(state.kind, OperationKind::Merge);
+    }
+
+    #[test]
+    fn reset_soft_keeps_changes_staged() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        let old_head = Fixture::commit_file(&repo, "b.txt", "b\n", "add b");
+
+        operations::reset(&repo, base, ResetMode::Soft).unwrap();
+
+        assert_eq!(repo.head().unwrap().target(), Some(base));
+        assert_eq!(
+            operations::uncommitted_changes(&repo).unwrap(),
+            vec!["A b.txt"]
+        );
+        let orig_head = repo.find_reference("ORIG_HEAD").unwrap();
+        assert_eq!(orig_head.target(), Some(old_head));
+        let reflog = repo.reflog("HEAD").unwrap();
+        assert_eq!(reflog.get(0).unwrap().id_old(), old_head);
+        assert_eq!(reflog.get(0).unwrap().id_new(), base);
+    }
+
+    #[test]
+    fn reset_mixed_unstages_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        Fixture::commit_file(&repo, "a.txt", "changed\n", "change a");
+
+        operations::reset(&repo, base, ResetMode::Mixed).unwrap();
+
+        assert_eq!(
+            operations::uncommitted_changes(&repo).unwrap(),
+            vec!["M a.txt"]
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+            "changed\n"
+        );
+    }
+
+    #[test]
+    fn reset_is_refused_mid_operation_and_logged_like_git() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        Fixture::checkout_branch(&repo, "feature", base);
+        let tip = Fixture::commit_file(&repo, "a.txt", "feature\n", "feature change");
+        Fixture::checkout_branch(&repo, "main", base);
+        let head = Fixture::commit_file(&repo, "a.txt", "main\n", "main change");
+        operations::merge(&repo, tip, MergeMode::NoFastForward).unwrap();
+
+        assert!(operations::reset(&repo, base, ResetMode::Hard).is_err());
+        assert_eq!(repo.head().unwrap().target(), Some(head));
+
+        operations::abort_operation(&repo).unwrap();
+        operations::reset(&repo, base, ResetMode::Soft).unwrap();
+
+        let orig_head = repo.find_reference("ORIG_HEAD").unwrap();
+        assert_eq!(orig_head.target(), Some(head));
+        let reflog = repo.reflog("HEAD").unwrap();
+        assert_eq!(
+            reflog.get(0).unwrap().message(),
+            Some(format!("reset: moving to {}", base).as_str())
+        );
+    }
+
+    #[test]
+    fn reset_hard_discards_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        Fixture::commit_file(&repo, "b.txt", "b\n", "add b");
+        std::fs::write(dir.path().join("a.txt"), "dirty\n").unwrap();
+        std::fs::write(dir.path().join("untracked.txt"), "u\n").unwrap();
+
+        assert_eq!(
+            operations::uncommitted_changes(&repo).unwrap(),
+            vec!["M a.txt"]
+        );
+        operations::reset(&repo, base, ResetMode::Hard).unwrap();
+
+        assert_eq!(repo.head().unwrap().target(), Some(base));
+        assert!(operations::uncommitted_changes(&repo).unwrap().is_empty());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+            "a\n"
+        );
+        assert!(!dir.path().join("b.txt").exists());
+        assert!(dir.path().join("untracked.txt").exists());
+    }
+}
@@ -0,0 +1,411 @@
+use gpig::fixture::Fixture;
+use gpig::operations::{self, OperationKind};
+use gpig::rebase::{self, RebaseAction, RebaseOutcome, RebaseProgress};
+
+#[cfg(test)]
+mod test_rebase {
+    use std::path::Path;
+
+    use git2::{Oid, Repository};
+
+    use super::*;
+
+    fn head_oid(repo: &Repository) -> Oid {
+        repo.head().unwrap().target().unwrap()
+    }
+
+    /// `feature` adds b.txt, then edits a.txt; `main` also edits a.txt.
+    /// Returns the tip of main; HEAD is left on feature.
+    fn diverged_repo(repo: &Repository) -> Oid {
+        let base = Fixture::commit_file(repo, "a.txt", "a\n", "base");
+        Fixture::checkout_branch(repo, "main", base);
+        let main = Fixture::commit_file(repo, "a.txt", "main\n", "main change");
+        Fixture::checkout_branch(repo, "feature", base);
+        Fixture::commit_file(repo, "b.txt", "b\n", "add b");
+        Fixture::commit_file(repo, "a.txt", "feature\n", "feature change");
+        main
+    }
+
+    #[test]
+    fn rebase_replays_commits_onto_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        Fixture::checkout_branch(&repo, "main", base);
+        let main = Fixture::commit_file(&repo, "c.txt", "c\n", "add c");
+        Fixture::checkout_branch(&repo, "feature", base);
+        Fixture::commit_file(&repo, "b.txt", "b\n", "add b");
+        Fixture::commit_file(&repo, "d.txt", "d\n", "add d");
+
+        let outcome = rebase::rebase_onto(&repo, main, &mut |_| {}).unwrap();
+
+        let RebaseOutcome::Finished { head, applied } = outcome else {
+            panic!("expected a clean rebase");
+        };
+        assert_eq!(applied, 2);
+        assert_eq!(head_oid(&repo), head);
+        assert_eq!(repo.head().unwrap().shorthand(), Some("feature"));
+        let tip = repo.find_commit(head).unwrap();
+        assert_eq!(tip.message(), Some("add d"));
+        assert_eq!(tip.parent(0).unwrap().parent_id(0).unwrap(), main);
+        assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    }
+
+    #[test]
+    fn rebase_reports_progress_and_counts_new_commits() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        Fixture::checkout_branch(&repo, "main", base);
+        let main = Fixture::commit_file(&repo, "c.txt", "c\n", "add c");
+        Fixture::checkout_branch(&repo, "feature", base);
+        Fixture::commit_file(&repo, "c.txt", "c\n", "add c again");
+        Fixture::commit_file(&repo, "b.txt", "b\n", "add b");
+
+        let mut reported = Vec::new();
+        let outcome =
+            rebase::rebase_onto(&repo, main, &mut |progress| reported.push(progress.label()))
+                .unwrap();
+
+        assert_eq!(reported, vec!["applying 1/2", "applying 2/2"]);
+        let RebaseOutcome::Finished { applied, .. } = outcome else {
+            panic!("expected a clean rebase");
+        };
+        assert_eq!(applied, 1, "the change already on main is not counted");
+    }
+
+    #[test]
+    fn rebase_stops_on_conflict_and_continues() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let main = diverged_repo(&repo);
+
+        let outcome = rebase::rebase_onto(&repo, main, &mut |_| {}).unwrap();
+
+        assert_eq!(
+            outcome,
+            RebaseOutcome::Stopped {
+                progress: RebaseProgress {
+                    current: 2,
+                    total: 2
+                },
+                paths: vec!["a.txt".to_string()],
+            }
+        );
+        let state = operations::conflict_state(&repo).unwrap().unwrap();
+        assert_eq!(state.kind, OperationKind::Rebase);
+        assert_eq!(state.progress.unwrap().label(), "applying 2/2");
+
+        std::fs::write(dir.path().join("a.txt"), "resolved\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.txt")).unwrap();
+        index.write().unwrap();
+
+        let RebaseOutcome::Finished { head, .. } =
+            rebase::continue_rebase(&repo, &mut |_| {}).unwrap()
+        else {
+            panic!("expected the rebase to finish");
+        };
+        let tip = repo.find_commit(head).unwrap();
+        assert_eq!(tip.message(), Some("feature change"));
+        assert_eq!(tip.parent(0).unwrap().parent_id(0).unwrap(), main);
+        assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    }
+
+    #[test]
+    fn rebase_skip_drops_conflicting_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let main = diverged_repo(&repo);
+        rebase::rebase_onto(&repo, main, &mut |_| {}).unwrap();
+
+        let RebaseOutcome::Finished { head, .. } = rebase::skip_rebase(&repo, &mut |_| {}).unwrap()
+        else {
+            panic!("expected the rebase to finish");
+        };
+
+        let tip = repo.find_commit(head).unwrap();
+        assert_eq!(tip.message(), Some("add b"));
+        assert_eq!(tip.parent_id(0).unwrap(), main);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+            "main\n"
+        );
+    }
+
+    #[test]
+    fn rebase_abort_restores_branch() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let main = diverged_repo(&repo);
+        let original = head_oid(&repo);
+        rebase::rebase_onto(&repo, main, &mut |_| {}).unwrap();
+
+        operations::abort_operation(&repo).unwrap();
+
+        assert_eq!(head_oid(&repo), original);
+        assert_eq!(repo.head().unwrap().shorthand(), Some("feature"));
+        assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    }
+
+    /// Three commits on top of `base`, one file each.
+    fn linear_repo(repo: &Repository) -> Oid {
+        let base = Fixture::commit_file(repo, "a.txt", "a\n", "base");
+        Fixture::commit_file(repo, "b.txt", "b\n", "add b");
+        Fixture::commit_file(repo, "c.txt", "c\n", "add c");
+        Fixture::commit_file(repo, "d.txt", "d\n", "add d");
+        base
+    }
+
+    fn summaries(repo: &Repository, head: Oid, count: usize) -> Vec<String> {
+        let mut commit = repo.find_commit(head).unwrap();
+        let mut summaries = Vec::new();
+        summaries.push(commit.summary().unwrap().to_string());
+        for _ in 1..count {
+            commit = commit.parent(0).unwrap();
+            summaries.push(commit.summary().unwrap().to_string());
+        }
+        summaries
+    }
+
+    #[test]
+    fn plan_lists_commits_oldest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = linear_repo(&repo);
+
+        let steps = rebase::plan_rebase(&repo, base).unwrap();
+
+        let summaries: Vec<_> = steps.iter().map(|step| step.summary.as_str()).collect();
+        assert_eq!(summaries, vec!["add b", "add c", "add d"]);
+        assert!(steps.iter().all(|step| step.action == RebaseAction::Pick));
+    }
+
+    #[test]
+    fn interactive_rebase_reorders_rewords_and_drops() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = linear_repo(&repo);
+        let mut steps = rebase::plan_rebase(&repo, base).unwrap();
+        steps.swap(0, 2);
+        steps[0].action = RebaseAction::Reword;
+        steps[0].message = "add d, reworded".to_string();
+        steps[1].action = RebaseAction::Drop;
+
+        let outcome = rebase::start_interactive_rebase(&repo, base, &steps, &mut |_| {}).unwrap();
+
+        let RebaseOutcome::Finished { head, applied } = outcome else {
+            panic!("expected the rebase to finish");
+        };
+        assert_eq!(applied, 2);
+        assert_eq!(
+            summaries(&repo, head, 3),
+            vec!["add b", "add d, reworded", "base"]
+        );
+        assert_eq!(repo.head().unwrap().shorthand(), Some("main"));
+        assert!(!dir.path().join("c.txt").exists());
+        assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    }
+
+    #[test]
+    fn reword_replaces_the_summary_and_keeps_the_body() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        Fixture::commit_file(
+            &repo,
+            "b.txt",
+            "b\n",
+            "add b\n\nb is needed because\nof reasons.\n",
+        );
+        let mut steps = rebase::plan_rebase(&repo, base).unwrap();
+        assert_eq!(steps[0].summary, "add b");
+
+        steps[0].action = RebaseAction::Reword;
+        steps[0].reword(" add b, reworded ");
+        let RebaseOutcome::Finished { head, .. } =
+            rebase::start_interactive_rebase(&repo, base, &steps, &mut |_| {}).unwrap()
+        else {
+            panic!("expected the rebase to finish");
+        };
+
+        assert_eq!(
+            repo.find_commit(head).unwrap().message(),
+            Some("add b, reworded\n\nb is needed because\nof reasons.\n")
+        );
+
+        steps[0].reword("");
+        assert!(rebase::start_interactive_rebase(&repo, base, &steps, &mut |_| {}).is_err());
+    }
+
+    #[test]
+    fn interactive_rebase_squashes_and_fixes_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = linear_repo(&repo);
+        let mut steps = rebase::plan_rebase(&repo, base).unwrap();
+        steps[1].action = RebaseAction::Squash;
+        steps[2].action = RebaseAction::Fixup;
+
+        let RebaseOutcome::Finished { head, .. } =
+            rebase::start_interactive_rebase(&repo, base, &steps, &mut |_| {}).unwrap()
+        else {
+            panic!("expected the rebase to finish");
+        };
+
+        let tip = repo.find_commit(head).unwrap();
+        assert_eq!(tip.parent_id(0).unwrap(), base);
+        assert_eq!(tip.message(), Some("add b\n\nadd c"));
+        assert!(tip.tree().unwrap().get_name("d.txt").is_some());
+
+        steps[0].action = RebaseAction::Fixup;
+        assert!(rebase::start_interactive_rebase(&repo, base, &steps, &mut |_| {}).is_err());
+    }
+
+    #[test]
+    fn interactive_rebase_stops_on_conflict_skips_and_aborts() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        Fixture::commit_file(&repo, "a.txt", "one\n", "first");
+        let original = Fixture::commit_file(&repo, "a.txt", "two\n", "second");
+        let mut steps = rebase::plan_rebase(&repo, base).unwrap();
+        steps.swap(0, 1);
+
+        let outcome = rebase::start_interactive_rebase(&repo, base, &steps, &mut |_| {}).unwrap();
+
+        let RebaseOutcome::Stopped { progress, paths } = outcome else {
+            panic!("expected a conflict");
+        };
+        // "second" no longer applies once "first" is moved after it
+        assert_eq!(progress.label(), "applying 1/2");
+        assert_eq!(paths, vec!["a.txt".to_string()]);
+        let state = operations::conflict_state(&repo).unwrap().unwrap();
+        assert_eq!(state.kind, OperationKind::Rebase);
+        assert_eq!(state.progress, Some(progress));
+        // laid out like `git rebase -i`: the stopped step is already done
+        let state_dir = repo.path().join("rebase-merge");
+        let done = std::fs::read_to_string(state_dir.join("done")).unwrap();
+        assert!(done.starts_with(&format!("pick {} second", original)));
+        assert_eq!(
+            std::fs::read_to_string(repo.path().join("REBASE_HEAD")).unwrap(),
+            format!("{}\n", original)
+        );
+        assert!(state_dir.join("author-script").exists());
+
+        operations::abort_operation(&repo).unwrap();
+
+        assert_eq!(head_oid(&repo), original);
+        assert_eq!(repo.head().unwrap().shorthand(), Some("main"));
+        assert_eq!(repo.state(), git2::RepositoryState::Clean);
+
+        rebase::start_interactive_rebase(&repo, base, &steps, &mut |_| {}).unwrap();
+        let RebaseOutcome::Finished { head, applied } =
+            rebase::skip_rebase(&repo, &mut |_| {}).unwrap()
+        else {
+            panic!("expected the rebase to finish after skipping");
+        };
+        assert_eq!(applied, 1);
+        assert_eq!(summaries(&repo, head, 2), vec!["first", "base"]);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+            "one\n"
+        );
+    }
+
+    /// `first` and `second` both edit a.txt, with `add b` between them and `add d` after,
+    /// so `second` conflicts once `first` is dropped.
+    fn repo_with_conflicting_second(repo: &Repository) -> Oid {
+        let base = Fixture::commit_file(repo, "a.txt", "a\n", "base");
+        Fixture::commit_file(repo, "a.txt", "one\n", "first");
+        Fixture::commit_file(repo, "b.txt", "b\n", "add b");
+        Fixture::commit_file(repo, "a.txt", "two\n", "second");
+        Fixture::commit_file(repo, "d.txt", "d\n", "add d");
+        base
+    }
+
+    #[test]
+    fn skipped_squash_leaves_the_group_message_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = repo_with_conflicting_second(&repo);
+        let mut steps = rebase::plan_rebase(&repo, base).unwrap();
+        steps[0].action = RebaseAction::Drop;
+        steps[2].action = RebaseAction::Squash;
+        steps[3].action = RebaseAction::Fixup;
+
+        let outcome = rebase::start_interactive_rebase(&repo, base, &steps, &mut |_| {}).unwrap();
+        assert!(matches!(outcome, RebaseOutcome::Stopped { .. }));
+        let RebaseOutcome::Finished { head, .. } = rebase::skip_rebase(&repo, &mut |_| {}).unwrap()
+        else {
+            panic!("expected the rebase to finish after skipping");
+        };
+
+        let tip = repo.find_commit(head).unwrap();
+        assert_eq!(tip.parent_id(0).unwrap(), base);
+        // the fixup rewrites the group's commit, without the skipped squash's message
+        assert_eq!(tip.message(), Some("add b"));
+        assert!(tip.tree().unwrap().get_name("d.txt").is_some());
+    }
+
+    #[test]
+    fn squash_after_a_skipped_pick_folds_into_the_commit_before() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = repo_with_conflicting_second(&repo);
+        let mut steps = rebase::plan_rebase(&repo, base).unwrap();
+        steps[0].action = RebaseAction::Drop;
+        steps[3].action = RebaseAction::Squash;
+
+        let outcome = rebase::start_interactive_rebase(&repo, base, &steps, &mut |_| {}).unwrap();
+        assert!(matches!(outcome, RebaseOutcome::Stopped { .. }));
+        let RebaseOutcome::Finished { head, applied } =
+            rebase::skip_rebase(&repo, &mut |_| {}).unwrap()
+        else {
+            panic!("expected the rebase to finish after skipping");
+        };
+
+        assert_eq!(applied, 1);
+        let tip = repo.find_commit(head).unwrap();
+        assert_eq!(tip.parent_id(0).unwrap(), base);
+        assert_eq!(tip.message(), Some("add b\n\nadd d"));
+        let tree = tip.tree().unwrap();
+        assert!(tree.get_name("b.txt").is_some());
+        assert!(tree.get_name("d.txt").is_some());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+            "a\n"
+        );
+    }
+
+    #[test]
+    fn squash_after_an_upstream_pick_still_folds() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let base = Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        Fixture::checkout_branch(&repo, "main", base);
+        let main = Fixture::commit_file(&repo, "c.txt", "c\n", "add c");
+        Fixture::checkout_branch(&repo, "feature", base);
+        Fixture::commit_file(&repo, "c.txt", "c\n", "add c again");
+        Fixture::commit_file(&repo, "b.txt", "b\n", "add b");
+        let mut steps = rebase::plan_rebase(&repo, base).unwrap();
+        steps[1].action = RebaseAction::Squash;
+
+        let mut reported = Vec::new();
+        let outcome = rebase::start_interactive_rebase(&repo, main, &steps, &mut |progress| {
+            reported.push(progress.label())
+        })
+        .unwrap();
+
+        let RebaseOutcome::Finished { head, applied } = outcome else {
+            panic!("expected the rebase to finish");
+        };
+        assert_eq!(reported, vec!["applying 1/2", "applying 2/2"]);
+        assert_eq!(applied, 1);
+        let tip = repo.find_commit(head).unwrap();
+        assert_eq!(tip.parent_id(0).unwrap(), main);
+        // the pick was already on main, but the squash still folds into its message
+        assert_eq!(tip.message(), Some("add c again\n\nadd b"));
+        assert!(tip.tree().unwrap().get_name("b.txt").is_some());
+    }
+}
@@ -0,0 +1,127 @@
+use gpig::fixture::Fixture;
+use gpig::recent::RecentRepos;
+use gpig::tabs::{Tabs, UserFiles};
+
+#[cfg(test)]
+mod test_recent {
+    use std::path::{Path, PathBuf};
+
+    use gpui::{AppContext, TestAppContext};
+
+    use super::*;
+
+    fn paths(recent: &RecentRepos) -> Vec<PathBuf> {
+        recent
+            .entries()
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect()
+    }
+
+    #[test]
+    fn newest_first_with_pinned_on_top_and_a_cap() {
+        let mut recent = RecentRepos::default();
+        for i in 0..12 {
+            recent.record(Path::new(&format!("/repos/{}", i)), i);
+        }
+        assert_eq!(recent.entries().len(), 10);
+        assert_eq!(paths(&recent)[0], PathBuf::from("/repos/11"));
+        assert!(!paths(&recent).contains(&PathBuf::from("/repos/1")));
+
+        recent.set_pinned(Path::new("/repos/2"), true);
+        recent.record(Path::new("/repos/5"), 20);
+        for i in 30..40 {
+            recent.record(Path::new(&format!("/other/{}", i)), i);
+        }
+        let entries = paths(&recent);
+        assert_eq!(
+            entries[0],
+            PathBuf::from("/repos/2"),
+            "pins survive the cap"
+        );
+        assert_eq!(entries[1], PathBuf::from("/other/39"));
+        assert_eq!(entries.len(), 11);
+
+        recent.remove(Path::new("/repos/2"));
+        assert!(!paths(&recent).contains(&PathBuf::from("/repos/2")));
+    }
+
+    #[test]
+    fn saves_loads_and_prunes_missing_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let kept = dir.path().join("kept");
+        std::fs::create_dir(&kept).unwrap();
+        let file = dir.path().join("state/recent.toml");
+
+        let mut recent = RecentRepos::default();
+        recent.record(&kept, 1);
+        recent.record(&dir.path().join("gone"), 2);
+        recent.set_pinned(&kept, true);
+        recent.save(&file).unwrap();
+
+        let mut loaded = RecentRepos::load(&file);
+        assert_eq!(loaded, recent);
+        assert_eq!(loaded.prune_missing(), 1);
+        assert_eq!(paths(&loaded), vec![kept]);
+        assert!(loaded.entries()[0].pinned);
+
+        assert_eq!(
+            RecentRepos::load(&dir.path().join("missing.toml")),
+            RecentRepos::default()
+        );
+    }
+
+    #[gpui::test]
+    fn workspace_records_opened_repositories(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first");
+        let second = dir.path().join("second");
+        let _first = Fixture::new(&first).commit("a", &[("a.txt", "a\n")]);
+        let _second = Fixture::new(&second).commit("b", &[("b.txt", "b\n")]);
+        let file = dir.path().join("recent.toml");
+
+        let files = UserFiles {
+            recent: Some(file.clone()),
+            settings: None,
+        };
+        let tabs = cx.new(|cx| Tabs::new(Some(git2::Repository::open(&first).unwrap()), files, cx));
+        let first_tab = tabs.read_with(cx, |tabs, _| tabs.active().clone());
+        first_tab.update(cx, |workspace, cx| workspace.open_recent(&second, cx));
+        cx.run_until_parked();
+        // the recent repository gets its own tab
+        let workspace = tabs.read_with(cx, |tabs, _| {
+            assert_eq!(tabs.len(), 2);
+            tabs.active().clone()
+        });
+
+        let names: Vec<String> = workspace.read_with(cx, |workspace, _| {
+            workspace
+                .recent()
+                .iter()
+                .map(|entry| entry.name())
+                .collect()
+        });
+        assert_eq!(names.len(), 2);
+        assert!(names.contains(&"first".to_string()));
+        assert_eq!(RecentRepos::load(&file).entries().len(), 2);
+
+        std::fs::remove_dir_all(&first).unwrap();
+        let first_entry = workspace.read_with(cx, |workspace, _| {
+            workspace
+                .recent()
+                .into_iter()
+                .find(|entry| entry.name() == "first")
+                .unwrap()
+                .path
+        });
+        workspace.update(cx, |workspace, cx| workspace.open_recent(&first_entry, cx));
+        workspace.read_with(cx, |workspace, _| {
+            let names: Vec<String> = workspace
+                .recent()
+                .iter()
+                .map(|entry| entry.name())
+                .collect();
+            assert_eq!(names, vec!["second"]);
+        });
+    }
+}
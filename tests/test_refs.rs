@@ -0,0 +1,108 @@
+use gpig::fixture::Fixture;
+use gpig::garph::Garph;
+use gpig::refs::RepoRefs;
+use gpig::settings::Settings;
+use gpig::workspace::Workspace;
+
+#[cfg(test)]
+mod test_refs {
+    use git2::{Oid, Repository, Signature};
+    use gpui::{AppContext, TestAppContext};
+
+    use super::*;
+
+    fn commit(repo: &Repository, message: &str, parents: &[Oid]) -> Oid {
+        let sig = Signature::now("gpig", "gpig@example.com").unwrap();
+        let tree_oid = repo.index().unwrap().write_tree().unwrap();
+        let tree = repo.find_tree(tree_oid).unwrap();
+        let parents: Vec<_> = parents
+            .iter()
+            .map(|p| repo.find_commit(*p).unwrap())
+            .collect();
+        let parents: Vec<_> = parents.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .unwrap()
+    }
+
+    #[test]
+    fn lists_branches_and_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut repo = Repository::init(dir.path()).unwrap();
+        let first = commit(&repo, "first", &[]);
+        let second = commit(&repo, "second", &[first]);
+
+        repo.branch("feature", &repo.find_commit(first).unwrap(), false)
+            .unwrap();
+        repo.tag_lightweight("v1", &repo.find_object(first, None).unwrap(), false)
+            .unwrap();
+
+        let refs = RepoRefs::load(&mut repo).unwrap();
+
+        assert_eq!(refs.local_branches.len(), 2);
+        let head = refs.local_branches.iter().find(|b| b.is_head).unwrap();
+        assert_eq!(head.oid, second);
+        let feature = refs
+            .local_branches
+            .iter()
+            .find(|b| b.name == "feature")
+            .unwrap();
+        assert_eq!(feature.oid, first);
+        assert_eq!(refs.tags.len(), 1);
+        assert_eq!(refs.tags[0].oid, first);
+        assert!(refs.stashes.is_empty());
+    }
+
+    #[test]
+    fn counts_ahead_behind_upstream() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut repo = Repository::init(dir.path()).unwrap();
+        let first = commit(&repo, "first", &[]);
+        let second = commit(&repo, "second", &[first]);
+
+        repo.reference("refs/remotes/origin/main", first, true, "test")
+            .unwrap();
+        repo.remote("origin", "https://example.com/repo.git")
+            .unwrap();
+        {
+            let head = repo.head().unwrap();
+            let branch_name = head.shorthand().unwrap().to_string();
+            let mut branch = repo
+                .find_branch(&branch_name, git2::BranchType::Local)
+                .unwrap();
+            branch.set_upstream(Some("origin/main")).unwrap();
+        }
+
+        let refs = RepoRefs::load(&mut repo).unwrap();
+        let local = &refs.local_branches[0];
+        assert_eq!(local.oid, second);
+        assert_eq!((local.ahead, local.behind), (1, 0));
+        assert_eq!(refs.remotes.len(), 1);
+        assert_eq!(refs.remotes[0].branches[0].name, "main");
+    }
+
+    #[gpui::test]
+    fn selecting_a_ref_beyond_the_walk_says_so(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let fixture = Fixture::new(dir.path())
+            .commit("old", &[("a.txt", "1\n")])
+            .tag("v1")
+            .commit("new", &[("a.txt", "2\n")]);
+        let garph = cx.new(|_| Garph::new(Some(fixture.open())));
+        let workspace = cx.new(|cx| Workspace::new(Some(garph.clone()), cx));
+        garph.update(cx, |garph, cx| {
+            let mut settings = Settings::default();
+            settings.history.limit_rows = 1;
+            garph.set_settings(settings, cx);
+        });
+        cx.run_until_parked();
+
+        garph.update(cx, |garph, cx| garph.select_commit(fixture.oid("new"), cx));
+        workspace.read_with(cx, |workspace, _| assert_eq!(workspace.notice(), None));
+
+        garph.update(cx, |garph, cx| garph.select_commit(fixture.oid("old"), cx));
+        workspace.read_with(cx, |workspace, _| {
+            let notice = workspace.notice().unwrap();
+            assert!(notice.contains("older than the 1 commits"), "{}", notice);
+        });
+    }
+}
@@ -0,0 +1,445 @@
+use gpig::credentials::CredentialChain;
+use gpig::operations::OperationOutcome;
+use gpig::rebase::RebaseOutcome;
+use gpig::refs::RepoRefs;
+use gpig::remote::{self, CloneOptions, PullMode, PullOutcome};
+
+#[cfg(test)]
+mod test_remote {
+    use std::path::Path;
+
+    use git2::{Oid, Repository, Signature};
+
+    use super::*;
+
+    fn init_repo(dir: &Path) -> Repository {
+        let repo = Repository::init(dir).unwrap();
+        configure(&repo);
+        repo
+    }
+
+    fn configure(repo: &Repository) {
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "gpig").unwrap();
+        config.set_str("user.email", "gpig@example.com").unwrap();
+    }
+
+    fn commit_file(repo: &Repository, path: &str, content: &str, message: &str) -> Oid {
+        let workdir = repo.workdir().unwrap();
+        std::fs::write(workdir.join(path), content).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(path)).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = Signature::now("gpig", "gpig@example.com").unwrap();
+        let parents = match repo.head() {
+            Ok(head) => vec![head.peel_to_commit().unwrap()],
+            Err(_) => Vec::new(),
+        };
+        let parents: Vec<_> = parents.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .unwrap()
+    }
+
+    fn head_oid(repo: &Repository) -> Oid {
+        repo.head().unwrap().target().unwrap()
+    }
+
+    /// A bare remote seeded from `local`, whose `master` now tracks `origin/master`.
+    fn with_remote(root: &Path, local: &Repository) -> Repository {
+        let bare = Repository::init_bare(root.join("remote.git")).unwrap();
+        local
+            .remote("origin", root.join("remote.git").to_str().unwrap())
+            .unwrap();
+        remote::push(local, &mut |_| {}, &mut CredentialChain::new()).unwrap();
+        bare
+    }
+
+    fn clone(root: &Path, name: &str) -> Repository {
+        let url = root.join("remote.git");
+        let repo = Repository::clone(url.to_str().unwrap(), root.join(name)).unwrap();
+        configure(&repo);
+        repo
+    }
+
+    #[test]
+    fn push_sets_upstream_and_reports_progress() {
+        let root = tempfile::tempdir().unwrap();
+        let local = init_repo(&root.path().join("local"));
+        let first = commit_file(&local, "a.txt", "a\n", "first");
+        local
+            .remote("origin", root.path().join("remote.git").to_str().unwrap())
+            .unwrap();
+        let bare = Repository::init_bare(root.path().join("remote.git")).unwrap();
+
+        let mut progress = Vec::new();
+        let outcome = remote::push(
+            &local,
+            &mut |p| progress.push(p),
+            &mut CredentialChain::new(),
+        )
+        .unwrap();
+
+        assert_eq!(outcome.remote, "origin");
+        assert_eq!(outcome.branch, "master");
+        assert!(outcome.set_upstream);
+        assert_eq!(bare.refname_to_id("refs/heads/master").unwrap(), first);
+        assert_eq!(remote::upstream_remote(&local).unwrap(), "origin");
+
+        commit_file(&local, "a.txt", "b\n", "second");
+        let outcome = remote::push(&local, &mut |_| {}, &mut CredentialChain::new()).unwrap();
+        assert!(!outcome.set_upstream);
+        assert_eq!(
+            bare.refname_to_id("refs/heads/master").unwrap(),
+            head_oid(&local)
+        );
+    }
+
+    #[test]
+    fn push_rejects_non_fast_forward_with_explanation() {
+        let root = tempfile::tempdir().unwrap();
+        let local = init_repo(&root.path().join("local"));
+        commit_file(&local, "a.txt", "a\n", "first");
+        with_remote(root.path(), &local);
+        let other = clone(root.path(), "other");
+        commit_file(&other, "b.txt", "b\n", "from other");
+        remote::push(&other, &mut |_| {}, &mut CredentialChain::new()).unwrap();
+        commit_file(&local, "c.txt", "c\n", "from local");
+
+        let error = remote::push(&local, &mut |_| {}, &mut CredentialChain::new()).unwrap_err();
+
+        assert!(error.message().contains("rejected"), "{}", error.message());
+        assert!(error.message().contains("origin/master"));
+    }
+
+    #[test]
+    fn fetch_all_and_pull_fast_forwards() {
+        let root = tempfile::tempdir().unwrap();
+        let local = init_repo(&root.path().join("local"));
+        commit_file(&local, "a.txt", "a\n", "first");
+        with_remote(root.path(), &local);
+        let other = clone(root.path(), "other");
+        let tip = commit_file(&other, "b.txt", "b\n", "from other");
+        remote::push(&other, &mut |_| {}, &mut CredentialChain::new()).unwrap();
+
+        let mut progress = Vec::new();
+        let fetched = remote::fetch(
+            &local,
+            None,
+            &mut |p| progress.push(p),
+            &mut CredentialChain::new(),
+        )
+        .unwrap();
+        assert_eq!(fetched, vec!["origin".to_string()]);
+        assert_eq!(
+            local.refname_to_id("refs/remotes/origin/master").unwrap(),
+            tip
+        );
+        assert!(progress.iter().any(|p| p.total_objects > 0));
+
+        assert_eq!(
+            remote::pull(
+                &local,
+                &mut |_| {},
+                &mut |_| {},
+                &mut CredentialChain::new()
+            )
+            .unwrap(),
+            PullOutcome::FastForwarded(tip)
+        );
+        assert_eq!(head_oid(&local), tip);
+        assert!(root.path().join("local/b.txt").exists());
+        assert_eq!(
+            remote::pull(
+                &local,
+                &mut |_| {},
+                &mut |_| {},
+                &mut CredentialChain::new()
+            )
+            .unwrap(),
+            PullOutcome::UpToDate
+        );
+    }
+
+    #[test]
+    fn pull_merges_or_rebases_diverged_branches() {
+        let root = tempfile::tempdir().unwrap();
+        let local = init_repo(&root.path().join("local"));
+        commit_file(&local, "a.txt", "a\n", "first");
+        with_remote(root.path(), &local);
+        let other = clone(root.path(), "other");
+        let theirs = commit_file(&other, "b.txt", "b\n", "from other");
+        remote::push(&other, &mut |_| {}, &mut CredentialChain::new()).unwrap();
+        commit_file(&local, "c.txt", "c\n", "from local");
+
+        local.config().unwrap().set_str("pull.ff", "only").unwrap();
+        assert_eq!(
+            remote::pull_mode(&local, "master").unwrap(),
+            PullMode::FastForwardOnly
+        );
+        assert!(
+            remote::pull(
+                &local,
+                &mut |_| {},
+                &mut |_| {},
+                &mut CredentialChain::new()
+            )
+            .is_err()
+        );
+
+        local
+            .config()
+            .unwrap()
+            .set_bool("pull.rebase", true)
+            .unwrap();
+        let mut replayed = Vec::new();
+        let PullOutcome::Rebased(RebaseOutcome::Finished { head, .. }) = remote::pull(
+            &local,
+            &mut |_| {},
+            &mut |progress| replayed.push(progress.label()),
+            &mut CredentialChain::new(),
+        )
+        .unwrap() else {
+            panic!("expected a rebase");
+        };
+        assert_eq!(replayed, vec!["applying 1/1"]);
+        let tip = local.find_commit(head).unwrap();
+        assert_eq!(tip.message(), Some("from local"));
+        assert_eq!(tip.parent_id(0).unwrap(), theirs);
+
+        let ours = commit_file(&local, "d.txt", "d\n", "another local");
+        commit_file(&other, "e.txt", "e\n", "another other");
+        remote::push(&other, &mut |_| {}, &mut CredentialChain::new()).unwrap();
+        local.config().unwrap().remove("pull.rebase").unwrap();
+        local.config().unwrap().remove("pull.ff").unwrap();
+
+        let PullOutcome::Merged(OperationOutcome::Committed(merge)) = remote::pull(
+            &local,
+            &mut |_| {},
+            &mut |_| {},
+            &mut CredentialChain::new(),
+        )
+        .unwrap() else {
+            panic!("expected a merge commit");
+        };
+        let merge = local.find_commit(merge).unwrap();
+        assert_eq!(merge.parent_count(), 2);
+        assert_eq!(merge.parent_id(0).unwrap(), ours);
+    }
+
+    #[test]
+    fn pull_mode_reads_settings_as_git_does() {
+        let root = tempfile::tempdir().unwrap();
+        let local = init_repo(&root.path().join("local"));
+        let mut config = local.config().unwrap();
+
+        for value in ["no", "off", "0", "false"] {
+            config.set_str("pull.rebase", value).unwrap();
+            assert_eq!(
+                remote::pull_mode(&local, "master").unwrap(),
+                PullMode::Merge
+            );
+        }
+        for value in ["yes", "on", "1", "interactive"] {
+            config.set_str("pull.rebase", value).unwrap();
+            assert_eq!(
+                remote::pull_mode(&local, "master").unwrap(),
+                PullMode::Rebase
+            );
+        }
+        config.set_str("pull.rebase", "merges").unwrap();
+        assert!(remote::pull_mode(&local, "master").is_err());
+
+        config.set_str("branch.master.rebase", "false").unwrap();
+        config.set_str("pull.ff", "false").unwrap();
+        assert_eq!(
+            remote::pull_mode(&local, "master").unwrap(),
+            PullMode::NoFastForward
+        );
+    }
+
+    #[test]
+    fn pull_with_ff_false_merges_instead_of_fast_forwarding() {
+        let root = tempfile::tempdir().unwrap();
+        let local = init_repo(&root.path().join("local"));
+        let first = commit_file(&local, "a.txt", "a\n", "first");
+        with_remote(root.path(), &local);
+        let other = clone(root.path(), "other");
+        let theirs = commit_file(&other, "b.txt", "b\n", "from other");
+        remote::push(&other, &mut |_| {}, &mut CredentialChain::new()).unwrap();
+
+        local.config().unwrap().set_bool("pull.ff", false).unwrap();
+        let PullOutcome::Merged(OperationOutcome::Committed(merge)) = remote::pull(
+            &local,
+            &mut |_| {},
+            &mut |_| {},
+            &mut CredentialChain::new(),
+        )
+        .unwrap() else {
+            panic!("expected a merge commit");
+        };
+        let merge = local.find_commit(merge).unwrap();
+        assert_eq!(merge.parent_id(0).unwrap(), first);
+        assert_eq!(merge.parent_id(1).unwrap(), theirs);
+    }
+
+    #[test]
+    fn push_goes_to_the_upstream_branch() {
+        let root = tempfile::tempdir().unwrap();
+        let local = init_repo(&root.path().join("local"));
+        let first = commit_file(&local, "a.txt", "a\n", "first");
+        let bare = with_remote(root.path(), &local);
+        let mut config = local.config().unwrap();
+        config
+            .set_str("branch.master.merge", "refs/heads/main")
+            .unwrap();
+
+        let outcome = remote::push(&local, &mut |_| {}, &mut CredentialChain::new()).unwrap();
+
+        assert_eq!(outcome.branch, "main");
+        assert!(!outcome.set_upstream);
+        assert_eq!(bare.refname_to_id("refs/heads/main").unwrap(), first);
+    }
+
+    #[test]
+    fn add_edit_rename_and_remove_remotes() {
+        let root = tempfile::tempdir().unwrap();
+        let mut local = init_repo(&root.path().join("local"));
+        commit_file(&local, "a.txt", "a\n", "first");
+        with_remote(root.path(), &local);
+
+        remote::add_remote(&local, "upstream", "https://example.com/up.git").unwrap();
+        assert!(remote::add_remote(&local, "bad name", "https://example.com").is_err());
+        remote::set_remote_urls(
+            &local,
+            "upstream",
+            "https://example.com/new.git",
+            Some("ssh://example.com/new.git"),
+        )
+        .unwrap();
+
+        let refs = RepoRefs::load(&mut local).unwrap();
+        let upstream = refs.remotes.iter().find(|r| r.name == "upstream").unwrap();
+        assert_eq!(upstream.url, "https://example.com/new.git");
+        assert_eq!(
+            upstream.push_url.as_deref(),
+            Some("ssh://example.com/new.git")
+        );
+
+        remote::rename_remote(&local, "origin", "home").unwrap();
+        assert!(local.find_remote("origin").is_err());
+        assert!(local.find_reference("refs/remotes/home/master").is_ok());
+        assert!(local.find_reference("refs/remotes/origin/master").is_err());
+        assert_eq!(remote::upstream_remote(&local).unwrap(), "home");
+
+        remote::remove_remote(&local, "upstream").unwrap();
+        let names: Vec<_> = RepoRefs::load(&mut local)
+            .unwrap()
+            .remotes
+            .into_iter()
+            .map(|r| r.name)
+            .collect();
+        assert_eq!(names, vec!["home".to_string()]);
+    }
+
+    #[test]
+    fn prune_removes_stale_tracking_branches() {
+        let root = tempfile::tempdir().unwrap();
+        let local = init_repo(&root.path().join("local"));
+        let first = commit_file(&local, "a.txt", "a\n", "first");
+        let bare = with_remote(root.path(), &local);
+        bare.reference("refs/heads/topic", first, false, "topic")
+            .unwrap();
+        remote::fetch(
+            &local,
+            Some("origin"),
+            &mut |_| {},
+            &mut CredentialChain::new(),
+        )
+        .unwrap();
+        assert!(local.find_reference("refs/remotes/origin/topic").is_ok());
+
+        bare.find_reference("refs/heads/topic")
+            .unwrap()
+            .delete()
+            .unwrap();
+        let pruned =
+            remote::prune_remote(&local, "origin", &mut |_| {}, &mut CredentialChain::new())
+                .unwrap();
+
+        assert_eq!(pruned, vec!["origin/topic".to_string()]);
+        assert!(local.find_reference("refs/remotes/origin/topic").is_err());
+        assert!(local.find_reference("refs/remotes/origin/master").is_ok());
+    }
+
+    #[test]
+    fn clone_name_follows_git() {
+        assert_eq!(
+            remote::clone_name("https://example.com/org/gpig.git"),
+            "gpig"
+        );
+        assert_eq!(remote::clone_name("git@example.com:org/gpig.git/"), "gpig");
+        assert_eq!(remote::clone_name("/srv/repos/tools"), "tools");
+        assert_eq!(remote::clone_name("git@example.com:gpig"), "gpig");
+    }
+
+    #[test]
+    fn clone_checks_out_branch_and_reports_progress() {
+        let root = tempfile::tempdir().unwrap();
+        let local = init_repo(&root.path().join("local"));
+        let first = commit_file(&local, "a.txt", "a\n", "first");
+        let bare = with_remote(root.path(), &local);
+        bare.reference("refs/heads/topic", first, false, "topic")
+            .unwrap();
+
+        let mut progress = Vec::new();
+        let options = CloneOptions {
+            // a plain path is copied locally without transfer progress
+            url: format!("file://{}", root.path().join("remote.git").display()),
+            destination: root.path().join("copy"),
+            branch: Some("topic".to_string()),
+            ..Default::default()
+        };
+        let copy = remote::clone(
+            &options,
+            &mut |p| progress.push(p),
+            &mut CredentialChain::new(),
+        )
+        .unwrap();
+
+        assert_eq!(copy.head().unwrap().shorthand(), Some("topic"));
+        assert_eq!(head_oid(&copy), first);
+        assert!(root.path().join("copy/a.txt").exists());
+        assert!(!progress.is_empty());
+
+        let error = remote::clone(&options, &mut |_| {}, &mut CredentialChain::new());
+        assert!(error.is_err(), "cloning into a non-empty folder must fail");
+    }
+
+    #[test]
+    fn clone_recursive_checks_out_submodules() {
+        let root = tempfile::tempdir().unwrap();
+        let library = init_repo(&root.path().join("library"));
+        commit_file(&library, "lib.txt", "lib\n", "library");
+
+        let app = init_repo(&root.path().join("app"));
+        commit_file(&app, "app.txt", "app\n", "app");
+        let library_url = root.path().join("library").display().to_string();
+        let mut submodule = app
+            .submodule(&library_url, Path::new("vendor/library"), true)
+            .unwrap();
+        submodule.clone(None).unwrap();
+        submodule.add_finalize().unwrap();
+        commit_file(&app, "app.txt", "app 2\n", "add submodule");
+
+        let options = CloneOptions {
+            url: root.path().join("app").display().to_string(),
+            destination: root.path().join("copy"),
+            recursive: true,
+            ..Default::default()
+        };
+        remote::clone(&options, &mut |_| {}, &mut CredentialChain::new()).unwrap();
+
+        assert!(root.path().join("copy/vendor/library/lib.txt").exists());
+    }
+}
@@ -0,0 +1,136 @@
+use gpig::fixture::Fixture;
+use gpig::restore;
+
+#[cfg(test)]
+mod test_restore {
+    use std::path::Path;
+
+    use git2::{Oid, Repository};
+
+    use super::*;
+
+    fn blob_at(repo: &Repository, commit: Oid, path: &str) -> Oid {
+        let tree = repo.find_commit(commit).unwrap().tree().unwrap();
+        tree.get_path(Path::new(path)).unwrap().id()
+    }
+
+    fn head_commit(repo: &Repository) -> Oid {
+        repo.head().unwrap().target().unwrap()
+    }
+
+    fn read(dir: &Path, path: &str) -> String {
+        std::fs::read_to_string(dir.join(path)).unwrap()
+    }
+
+    const ORIGINAL: &str = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+
+    #[test]
+    fn lists_and_discards_single_hunks() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        Fixture::commit_file(&repo, "a.txt", ORIGINAL, "base");
+        let edited = ORIGINAL.replace("1\n", "one\n").replace("10\n", "ten\n");
+        std::fs::write(dir.path().join("a.txt"), &edited).unwrap();
+
+        let hunks = restore::workdir_hunks(&repo, "a.txt").unwrap();
+        assert_eq!(hunks.len(), 2);
+        assert!(hunks[0].lines.contains(&"+one".to_string()));
+        assert!(hunks[1].lines.contains(&"-10".to_string()));
+
+        restore::discard_hunk(&repo, "a.txt", 1).unwrap();
+
+        assert_eq!(read(dir.path(), "a.txt"), ORIGINAL.replace("1\n", "one\n"));
+        assert_eq!(restore::workdir_hunks(&repo, "a.txt").unwrap().len(), 1);
+        assert!(restore::discard_hunk(&repo, "a.txt", 1).is_err());
+    }
+
+    #[test]
+    fn discard_file_restores_head_and_removes_new_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        Fixture::commit_file(&repo, "a.txt", ORIGINAL, "base");
+        std::fs::write(dir.path().join("a.txt"), "changed\n").unwrap();
+        std::fs::write(dir.path().join("new.txt"), "new\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.txt")).unwrap();
+        index.add_path(Path::new("new.txt")).unwrap();
+        index.write().unwrap();
+
+        restore::discard_file(&repo, "a.txt").unwrap();
+        restore::discard_file(&repo, "new.txt").unwrap();
+
+        assert_eq!(read(dir.path(), "a.txt"), ORIGINAL);
+        assert!(!dir.path().join("new.txt").exists());
+        let statuses = repo.statuses(None).unwrap();
+        assert!(statuses.is_empty());
+    }
+
+    #[test]
+    fn restore_file_checks_out_old_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        let first = Fixture::commit_file(&repo, "a.txt", "first\n", "first");
+        Fixture::commit_file(&repo, "a.txt", "second\n", "second");
+
+        let second = head_commit(&repo);
+
+        restore::restore_file(&repo, second, true, "a.txt").unwrap();
+
+        assert_eq!(read(dir.path(), "a.txt"), "first\n");
+        let entry = repo
+            .index()
+            .unwrap()
+            .get_path(Path::new("a.txt"), 0)
+            .unwrap();
+        assert_eq!(entry.id, blob_at(&repo, first, "a.txt"));
+
+        // before the root commit there was no file
+        restore::restore_file(&repo, first, true, "a.txt").unwrap();
+        assert!(!dir.path().join("a.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn restore_file_keeps_the_executable_bit_and_symlinks() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Fixture::init(dir.path());
+        std::fs::write(dir.path().join("run.sh"), "echo hi\n").unwrap();
+        std::fs::set_permissions(
+            dir.path().join("run.sh"),
+            std::fs::Permissions::from_mode(0o755),
+        )
+        .unwrap();
+        std::os::unix::fs::symlink("run.sh", dir.path().join("link")).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("link")).unwrap();
+        index.write().unwrap();
+        let first = Fixture::commit_file(&repo, "run.sh", "echo hi\n", "first");
+        std::fs::remove_file(dir.path().join("link")).unwrap();
+        Fixture::commit_file(&repo, "link", "plain\n", "replace link");
+        Fixture::commit_file(&repo, "run.sh", "echo bye\n", "second");
+        std::fs::set_permissions(
+            dir.path().join("run.sh"),
+            std::fs::Permissions::from_mode(0o644),
+        )
+        .unwrap();
+
+        restore::restore_file(&repo, first, false, "run.sh").unwrap();
+        restore::restore_file(&repo, first, false, "link").unwrap();
+
+        let mode = std::fs::metadata(dir.path().join("run.sh"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o111, 0o111);
+        assert_eq!(read(dir.path(), "run.sh"), "echo hi\n");
+        let link = std::fs::symlink_metadata(dir.path().join("link")).unwrap();
+        assert!(link.file_type().is_symlink());
+        let index = repo.index().unwrap();
+        let staged = index.get_path(Path::new("run.sh"), 0).unwrap();
+        assert_eq!(staged.mode, 0o100755);
+        assert_eq!(staged.id, blob_at(&repo, first, "run.sh"));
+        assert_eq!(index.get_path(Path::new("link"), 0).unwrap().mode, 0o120000);
+    }
+}
@@ -0,0 +1,175 @@
+use gpig::fixture::Fixture;
+use gpig::garph::Garph;
+use gpig::settings::{SettingField, Settings, SortOrder};
+use gpig::watcher::DEBOUNCE;
+use gpig::workspace::Workspace;
+
+#[cfg(test)]
+mod test_settings {
+    use std::time::Duration;
+
+    use gpui::{AppContext, TestAppContext};
+
+    use super::*;
+
+    /// Give the watcher's file events, which arrive in real time, a chance to land and run
+    /// out the debounce until `done` holds.
+    fn settle(cx: &mut TestAppContext, done: impl Fn(&mut TestAppContext) -> bool) {
+        for _ in 0..50 {
+            std::thread::sleep(Duration::from_millis(20));
+            cx.executor().advance_clock(DEBOUNCE);
+            cx.run_until_parked();
+            if done(cx) {
+                return;
+            }
+        }
+    }
+
+    #[test]
+    fn repository_keys_override_user_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let user = dir.path().join("settings.toml");
+        let repo = Settings::repo_file(dir.path());
+        std::fs::write(
+            &user,
+            "[history]\nlimit_rows = 500\nsort = \"date\"\n[layout]\nlane_width = 20.0\n",
+        )
+        .unwrap();
+        std::fs::write(&repo, "[history]\nlimit_rows = 5000\n").unwrap();
+
+        let settings = Settings::load(&[user.clone(), repo, dir.path().join("none.toml")]).unwrap();
+        assert_eq!(settings.history.limit_rows, 5000);
+        assert_eq!(settings.history.sort, SortOrder::Date);
+        assert_eq!(settings.layout.lane_width, 20.0);
+        assert_eq!(
+            settings.layout.row_height, 20.0,
+            "missing keys keep defaults"
+        );
+        assert_eq!(settings.diff, Default::default());
+
+        assert_eq!(Settings::load(&[]).unwrap(), Settings::default());
+    }
+
+    #[test]
+    fn broken_files_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("settings.toml");
+
+        std::fs::write(&file, "[history\n").unwrap();
+        let error = Settings::load(std::slice::from_ref(&file)).unwrap_err();
+        assert!(error.contains("settings.toml"), "{}", error);
+
+        std::fs::write(&file, "[history]\nlimit_rows = 0\n").unwrap();
+        assert!(Settings::load(std::slice::from_ref(&file)).is_err());
+
+        std::fs::write(&file, "[layout]\nlane_width = inf\n").unwrap();
+        let error = Settings::load(std::slice::from_ref(&file)).unwrap_err();
+        assert!(error.contains("layout.lane_width"), "{}", error);
+        let mut settings = Settings::default();
+        SettingField::RowHeight.apply(&mut settings, "inf").unwrap();
+        assert!(
+            settings.validate().is_err(),
+            "the form cannot set it either"
+        );
+
+        std::fs::write(&file, "[diff]\nmax_files = 0\n").unwrap();
+        let error = Settings::load(std::slice::from_ref(&file)).unwrap_err();
+        assert!(error.contains("diff.max_files"), "{}", error);
+
+        std::fs::write(&file, "[history]\nlimit_row = 10\n").unwrap();
+        assert!(
+            Settings::load(std::slice::from_ref(&file)).is_err(),
+            "typos are not silently ignored"
+        );
+    }
+
+    #[test]
+    fn overrides_keep_only_the_differences() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = Settings::repo_file(dir.path());
+        let base = Settings::default();
+        let mut settings = base.clone();
+        settings.diff.max_file_diff_lines = 2000;
+        settings.history.sort = SortOrder::Topo;
+
+        settings.save_overrides(&base, &file).unwrap();
+        let text = std::fs::read_to_string(&file).unwrap();
+        assert!(text.contains("max_file_diff_lines = 2000"), "{}", text);
+        assert!(!text.contains("limit_rows"), "{}", text);
+        assert_eq!(
+            Settings::load(std::slice::from_ref(&file)).unwrap(),
+            settings
+        );
+
+        settings.save(&file).unwrap();
+        assert!(
+            std::fs::read_to_string(&file)
+                .unwrap()
+                .contains("limit_rows")
+        );
+    }
+
+    #[test]
+    fn fields_round_trip_through_text() {
+        let mut settings = Settings::default();
+        for field in SettingField::ALL {
+            let value = field.value(&settings);
+            field.apply(&mut settings, &value).unwrap();
+        }
+        assert_eq!(settings, Settings::default());
+
+        SettingField::RowHeight
+            .apply(&mut settings, " 24.5 ")
+            .unwrap();
+        assert_eq!(settings.layout.row_height, 24.5);
+        assert!(
+            SettingField::LimitRows
+                .apply(&mut settings, "many")
+                .is_err()
+        );
+    }
+
+    #[gpui::test]
+    fn settings_apply_and_reload_on_change(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let repo_dir = dir.path().join("repo");
+        let fixture = Fixture::new(&repo_dir)
+            .commit("first", &[("a.txt", "1\n")])
+            .commit("second", &[("a.txt", "2\n")])
+            .commit("third", &[("a.txt", "3\n")]);
+        let user = dir.path().join("config/settings.toml");
+        std::fs::create_dir_all(user.parent().unwrap()).unwrap();
+        std::fs::write(&user, "[history]\nlimit_rows = 1\n").unwrap();
+
+        let garph = cx.new(|_| Garph::new(Some(fixture.open())));
+        let workspace = cx.new(|cx| Workspace::new(Some(garph.clone()), cx));
+        workspace.update(cx, |workspace, cx| {
+            workspace.set_settings_file(user.clone(), cx)
+        });
+        cx.run_until_parked();
+        garph.read_with(cx, |garph, _| {
+            assert_eq!(garph.settings().history.limit_rows, 1);
+            assert_eq!(garph.nodes().len(), 1);
+        });
+
+        std::fs::write(
+            Settings::repo_file(&repo_dir.join(".git")),
+            "[history]\nlimit_rows = 2\n",
+        )
+        .unwrap();
+        settle(cx, |cx| {
+            garph.read_with(cx, |garph, _| garph.nodes().len() == 2)
+        });
+        garph.read_with(cx, |garph, _| assert_eq!(garph.nodes().len(), 2));
+
+        // a broken edit keeps the settings in use
+        std::fs::write(&user, "[history\n").unwrap();
+        settle(cx, |cx| {
+            workspace.read_with(cx, |workspace, _| workspace.notice().is_some())
+        });
+        workspace.read_with(cx, |workspace, _| assert!(workspace.notice().is_some()));
+        workspace.read_with(cx, |workspace, _| {
+            assert_eq!(workspace.settings().history.limit_rows, 2)
+        });
+    }
+}
@@ -0,0 +1,114 @@
+use gpig::fixture::Fixture;
+use gpig::operations::{self, OperationKind};
+use gpig::stash::{self, StashOutcome};
+
+#[cfg(test)]
+mod test_stash {
+    use std::path::Path;
+
+    use git2::Repository;
+
+    use super::*;
+
+    fn stash_count(repo: &mut Repository) -> usize {
+        let mut count = 0;
+        repo.stash_foreach(|_, _, _| {
+            count += 1;
+            true
+        })
+        .unwrap();
+        count
+    }
+
+    fn read(dir: &Path, path: &str) -> String {
+        std::fs::read_to_string(dir.join(path)).unwrap()
+    }
+
+    #[test]
+    fn save_stashes_changes_and_cleans_worktree() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut repo = Fixture::init(dir.path());
+        Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        std::fs::write(dir.path().join("a.txt"), "changed\n").unwrap();
+
+        let oid = stash::save(&mut repo, Some("wip"), false).unwrap();
+
+        assert_eq!(read(dir.path(), "a.txt"), "a\n");
+        assert_eq!(stash::index_of(&mut repo, oid).unwrap(), Some(0));
+        let message = repo
+            .find_commit(oid)
+            .unwrap()
+            .message()
+            .unwrap()
+            .to_string();
+        assert!(message.contains("wip"));
+        assert!(stash::save(&mut repo, None, false).is_err());
+    }
+
+    #[test]
+    fn save_includes_untracked_files_when_asked() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut repo = Fixture::init(dir.path());
+        Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        std::fs::write(dir.path().join("new.txt"), "new\n").unwrap();
+
+        assert!(stash::save(&mut repo, None, false).is_err());
+        stash::save(&mut repo, None, true).unwrap();
+
+        assert!(!dir.path().join("new.txt").exists());
+        stash::pop(&mut repo, 0).unwrap();
+        assert_eq!(read(dir.path(), "new.txt"), "new\n");
+    }
+
+    #[test]
+    fn apply_keeps_stash_and_pop_drops_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut repo = Fixture::init(dir.path());
+        Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        std::fs::write(dir.path().join("a.txt"), "changed\n").unwrap();
+        stash::save(&mut repo, None, false).unwrap();
+
+        assert_eq!(stash::apply(&mut repo, 0).unwrap(), StashOutcome::Applied);
+        assert_eq!(read(dir.path(), "a.txt"), "changed\n");
+        assert_eq!(stash_count(&mut repo), 1);
+
+        std::fs::write(dir.path().join("a.txt"), "a\n").unwrap();
+        assert_eq!(stash::pop(&mut repo, 0).unwrap(), StashOutcome::Applied);
+        assert_eq!(read(dir.path(), "a.txt"), "changed\n");
+        assert_eq!(stash_count(&mut repo), 0);
+    }
+
+    #[test]
+    fn drop_removes_only_that_stash() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut repo = Fixture::init(dir.path());
+        Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        std::fs::write(dir.path().join("a.txt"), "first\n").unwrap();
+        let first = stash::save(&mut repo, Some("first"), false).unwrap();
+        std::fs::write(dir.path().join("a.txt"), "second\n").unwrap();
+        stash::save(&mut repo, Some("second"), false).unwrap();
+
+        stash::drop(&mut repo, 0).unwrap();
+
+        assert_eq!(stash_count(&mut repo), 1);
+        assert_eq!(stash::index_of(&mut repo, first).unwrap(), Some(0));
+    }
+
+    #[test]
+    fn pop_with_conflicts_keeps_stash() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut repo = Fixture::init(dir.path());
+        Fixture::commit_file(&repo, "a.txt", "a\n", "base");
+        std::fs::write(dir.path().join("a.txt"), "stashed\n").unwrap();
+        stash::save(&mut repo, Some("wip"), false).unwrap();
+        Fixture::commit_file(&repo, "a.txt", "committed\n", "change a");
+
+        let outcome = stash::pop(&mut repo, 0).unwrap();
+
+        assert_eq!(outcome, StashOutcome::Conflicts(vec!["a.txt".to_string()]));
+        assert_eq!(stash_count(&mut repo), 1);
+        assert!(read(dir.path(), "a.txt").contains("<<<<<<<"));
+        let state = operations::conflict_state(&repo).unwrap().unwrap();
+        assert_eq!(state.kind, OperationKind::StashApply);
+    }
+}
@@ -0,0 +1,158 @@
+use gpig::fixture::Fixture;
+use gpig::tabs::{Tabs, UserFiles};
+
+#[cfg(test)]
+mod test_tabs {
+    use std::path::Path;
+
+    use gpui::{AppContext, Entity, TestAppContext};
+
+    use super::*;
+
+    fn names(tabs: &Entity<Tabs>, cx: &mut TestAppContext) -> Vec<Option<String>> {
+        tabs.read_with(cx, |tabs, cx| {
+            tabs.repo_paths(cx)
+                .into_iter()
+                .map(|path| path.map(|path| path.file_name().unwrap().to_string_lossy().into()))
+                .collect()
+        })
+    }
+
+    fn repo(dir: &Path, name: &str) -> Fixture {
+        Fixture::new(&dir.join(name)).commit(name, &[("a.txt", "a\n")])
+    }
+
+    #[gpui::test]
+    fn open_fills_an_empty_tab_then_adds_tabs(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let _mono = repo(dir.path(), "mono");
+        let _service = repo(dir.path(), "service");
+        let tabs = cx.new(|cx| Tabs::new(None, UserFiles::default(), cx));
+        assert_eq!(names(&tabs, cx), vec![None]);
+
+        tabs.update(cx, |tabs, cx| tabs.open(&dir.path().join("mono"), cx))
+            .unwrap();
+        assert_eq!(names(&tabs, cx), vec![Some("mono".to_string())]);
+
+        tabs.update(cx, |tabs, cx| tabs.open(&dir.path().join("service"), cx))
+            .unwrap();
+        assert_eq!(
+            names(&tabs, cx),
+            vec![Some("mono".to_string()), Some("service".to_string())]
+        );
+        tabs.read_with(cx, |tabs, _| assert_eq!(tabs.active_index(), 1));
+
+        // a path inside an open repository switches to its tab
+        std::fs::create_dir(dir.path().join("mono/src")).unwrap();
+        tabs.update(cx, |tabs, cx| tabs.open(&dir.path().join("mono/src"), cx))
+            .unwrap();
+        tabs.read_with(cx, |tabs, _| {
+            assert_eq!(tabs.len(), 2);
+            assert_eq!(tabs.active_index(), 0);
+        });
+
+        let outside = tempfile::tempdir().unwrap();
+        assert!(
+            tabs.update(cx, |tabs, cx| tabs.open(outside.path(), cx))
+                .is_err()
+        );
+        tabs.update(cx, |tabs, cx| tabs.open_or_notify(outside.path(), cx));
+        tabs.read_with(cx, |tabs, cx| {
+            assert_eq!(tabs.len(), 2);
+            let notice = tabs.active().read(cx).notice().unwrap();
+            assert!(notice.starts_with("Failed to open"), "{}", notice);
+        });
+    }
+
+    #[gpui::test]
+    fn tabs_keep_their_own_selection(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let mono = repo(dir.path(), "mono");
+        let _service = repo(dir.path(), "service");
+        let tabs = cx.new(|cx| Tabs::new(Some(mono.open()), UserFiles::default(), cx));
+        tabs.update(cx, |tabs, cx| tabs.open(&dir.path().join("service"), cx))
+            .unwrap();
+
+        let first = tabs.read_with(cx, |tabs, _| tabs.active().clone());
+        tabs.update(cx, |tabs, cx| tabs.previous(cx));
+        let mono_tab = tabs.read_with(cx, |tabs, _| tabs.active().clone());
+        let garph = mono_tab.read_with(cx, |workspace, _| workspace.dock().unwrap().clone());
+        garph.update(cx, |garph, cx| {
+            garph.recompute();
+            garph.select_commit(mono.oid("mono"), cx);
+        });
+        cx.run_until_parked();
+
+        tabs.update(cx, |tabs, cx| tabs.next(cx));
+        tabs.read_with(cx, |tabs, _| assert_eq!(tabs.active(), &first));
+        first.read_with(cx, |workspace, _| {
+            assert!(workspace.changed_files().is_empty())
+        });
+        mono_tab.read_with(cx, |workspace, _| {
+            assert_eq!(workspace.changed_files().len(), 1)
+        });
+    }
+
+    #[gpui::test]
+    fn closing_moves_to_a_neighbour_and_keeps_one_tab(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let mono = repo(dir.path(), "mono");
+        let _service = repo(dir.path(), "service");
+        let _tools = repo(dir.path(), "tools");
+        let tabs = cx.new(|cx| Tabs::new(Some(mono.open()), UserFiles::default(), cx));
+        for name in ["service", "tools"] {
+            tabs.update(cx, |tabs, cx| tabs.open(&dir.path().join(name), cx))
+                .unwrap();
+        }
+
+        tabs.update(cx, |tabs, cx| tabs.close(2, cx));
+        tabs.read_with(cx, |tabs, _| assert_eq!(tabs.active_index(), 1));
+        tabs.update(cx, |tabs, cx| tabs.close(0, cx));
+        assert_eq!(names(&tabs, cx), vec![Some("service".to_string())]);
+        tabs.read_with(cx, |tabs, _| assert_eq!(tabs.active_index(), 0));
+
+        tabs.update(cx, |tabs, cx| tabs.close(0, cx));
+        assert_eq!(names(&tabs, cx), vec![None]);
+    }
+
+    #[gpui::test]
+    fn repositories_opened_from_a_tab_get_their_own_tab(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let mono = repo(dir.path(), "mono");
+        let _service = repo(dir.path(), "service");
+        let tabs = cx.new(|cx| Tabs::new(Some(mono.open()), UserFiles::default(), cx));
+        let mono_tab = tabs.read_with(cx, |tabs, _| tabs.active().clone());
+
+        mono_tab.update(cx, |workspace, cx| {
+            workspace.open_recent(&dir.path().join("service"), cx)
+        });
+        cx.run_until_parked();
+        assert_eq!(
+            names(&tabs, cx),
+            vec![Some("mono".to_string()), Some("service".to_string())]
+        );
+        tabs.read_with(cx, |tabs, _| assert_eq!(tabs.active_index(), 1));
+
+        // one already open is switched to instead
+        tabs.update(cx, |tabs, cx| tabs.activate(0, cx));
+        mono_tab.update(cx, |workspace, cx| {
+            workspace.open_recent(&dir.path().join("service"), cx)
+        });
+        cx.run_until_parked();
+        tabs.read_with(cx, |tabs, _| {
+            assert_eq!(tabs.len(), 2);
+            assert_eq!(tabs.active_index(), 1);
+        });
+
+        let outside = tempfile::tempdir().unwrap();
+        mono_tab.update(cx, |workspace, cx| {
+            workspace.open_recent(outside.path(), cx)
+        });
+        cx.run_until_parked();
+        assert_eq!(tabs.read_with(cx, |tabs, _| tabs.len()), 2);
+        mono_tab.read_with(cx, |workspace, _| {
+            let notice = workspace.notice().unwrap();
+            assert!(notice.starts_with("Failed to open"), "{}", notice);
+        });
+    }
+}
@@ -0,0 +1,67 @@
+use gpig::garph::Garph;
+
+#[cfg(test)]
+mod test_tags {
+    use git2::{Oid, Repository, Signature};
+
+    use super::*;
+
+    fn init_repo(dir: &std::path::Path) -> (Repository, Oid) {
+        let repo = Repository::init(dir).unwrap();
+        repo.config().unwrap().set_str("user.name", "gpig").unwrap();
+        repo.config()
+            .unwrap()
+            .set_str("user.email", "gpig@example.com")
+            .unwrap();
+        let sig = Signature::now("gpig", "gpig@example.com").unwrap();
+        let tree_oid = repo.index().unwrap().write_tree().unwrap();
+        let oid = {
+            let tree = repo.find_tree(tree_oid).unwrap();
+            repo.commit(Some("HEAD"), &sig, &sig, "first", &tree, &[])
+                .unwrap()
+        };
+        (repo, oid)
+    }
+
+    #[test]
+    fn create_lightweight_and_annotated_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, oid) = init_repo(dir.path());
+        let garph = Garph::new(Some(Repository::open(dir.path()).unwrap()));
+
+        garph.create_tag(&oid, "v1", None).unwrap();
+        garph.create_tag(&oid, "v2", Some("release 2")).unwrap();
+
+        let repo = Repository::open(dir.path()).unwrap();
+        let v1 = repo.find_reference("refs/tags/v1").unwrap();
+        assert_eq!(v1.target(), Some(oid));
+        let v2 = repo.find_reference("refs/tags/v2").unwrap();
+        let tag = v2.peel_to_tag().unwrap();
+        assert_eq!(tag.message(), Some("release 2"));
+        assert_eq!(tag.target_id(), oid);
+    }
+
+    #[test]
+    fn rejects_invalid_and_duplicate_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, oid) = init_repo(dir.path());
+        let garph = Garph::new(Some(Repository::open(dir.path()).unwrap()));
+
+        assert!(garph.create_tag(&oid, "bad name..", None).is_err());
+        garph.create_tag(&oid, "v1", None).unwrap();
+        assert!(garph.create_tag(&oid, "v1", None).is_err());
+    }
+
+    #[test]
+    fn delete_tag() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, oid) = init_repo(dir.path());
+        let garph = Garph::new(Some(Repository::open(dir.path()).unwrap()));
+
+        garph.create_tag(&oid, "v1", Some("annotated")).unwrap();
+        garph.delete_tag("v1").unwrap();
+
+        let repo = Repository::open(dir.path()).unwrap();
+        assert!(repo.find_reference("refs/tags/v1").is_err());
+    }
+}
@@ -0,0 +1,137 @@
+use gpig::fixture::Fixture;
+use gpig::garph::Garph;
+use gpig::watcher::{Classifier, DEBOUNCE, Debouncer, MAX_WAIT, WatchChange};
+use gpig::workspace::Workspace;
+
+#[cfg(test)]
+mod test_watcher {
+    use std::time::{Duration, Instant};
+
+    use gpui::{AppContext, TestAppContext};
+
+    use super::*;
+
+    #[test]
+    fn classifier_names_what_a_path_affects() {
+        let dir = tempfile::tempdir().unwrap();
+        let fixture = Fixture::new(dir.path())
+            .commit("first", &[("a.txt", "a\n"), (".gitignore", "/target/\n")])
+            .branch("feature")
+            .checkout("main");
+        let root = std::fs::canonicalize(dir.path()).unwrap();
+        let settings = root.join("settings.toml");
+        let classifier = Classifier::new(fixture.open(), std::slice::from_ref(&settings));
+        let git = root.join(".git");
+
+        let head = classifier.classify(&git.join("refs/heads/main"));
+        assert!(head.graph && head.refs && !head.remotes);
+        let other = classifier.classify(&git.join("refs/heads/feature"));
+        assert!(!other.graph && other.refs);
+        let tag = classifier.classify(&git.join("refs/tags/v1"));
+        assert!(tag.graph && tag.refs);
+        let remote = classifier.classify(&git.join("refs/remotes/origin/main"));
+        assert_eq!(
+            remote,
+            WatchChange {
+                remotes: true,
+                ..WatchChange::default()
+            }
+        );
+        assert!(classifier.classify(&git.join("MERGE_HEAD")).operation);
+        assert!(
+            classifier
+                .classify(&git.join("rebase-merge/git-rebase-todo"))
+                .operation
+        );
+        assert!(classifier.classify(&git.join("index")).worktree);
+        assert!(classifier.classify(&root.join("a.txt")).worktree);
+        assert!(classifier.classify(&settings).settings);
+
+        for quiet in [
+            git.join("refs/heads/main.lock"),
+            git.join("objects/ab/cdef"),
+            git.join("FETCH_HEAD"),
+            root.join("target/out.o"),
+        ] {
+            assert!(!classifier.classify(&quiet).any(), "{}", quiet.display());
+        }
+    }
+
+    #[test]
+    fn debouncer_waits_for_quiet_but_not_past_the_maximum() {
+        let refs = WatchChange {
+            refs: true,
+            ..WatchChange::default()
+        };
+        let worktree = WatchChange {
+            worktree: true,
+            ..WatchChange::default()
+        };
+        let start = Instant::now();
+        let mut debouncer = Debouncer::default();
+        assert_eq!(debouncer.wait(start), None);
+
+        debouncer.add(refs, start);
+        assert_eq!(debouncer.wait(start), Some(DEBOUNCE));
+        // every change starts the quiet period again
+        let later = start + MAX_WAIT - DEBOUNCE / 2;
+        debouncer.add(worktree, later);
+        assert_eq!(debouncer.wait(later), Some(DEBOUNCE / 2));
+        assert_eq!(debouncer.wait(start + MAX_WAIT), Some(Duration::ZERO));
+
+        assert_eq!(
+            debouncer.take(),
+            WatchChange {
+                refs: true,
+                worktree: true,
+                ..WatchChange::default()
+            }
+        );
+        assert_eq!(debouncer.wait(later), None);
+    }
+
+    #[gpui::test]
+    fn workspace_refreshes_after_changes_on_disk(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let fixture = Fixture::new(dir.path()).commit("first", &[("a.txt", "a\n")]);
+        let first = fixture.oid("first");
+        let garph = cx.new(|_| Garph::new(Some(fixture.open())));
+        let workspace = cx.new(|cx| Workspace::new(Some(garph.clone()), cx));
+        garph.update(cx, |garph, cx| {
+            garph.recompute();
+            garph.select_commit(first, cx);
+        });
+        cx.run_until_parked();
+        assert_eq!(
+            workspace.read_with(cx, |workspace, _| workspace.uncommitted().map(<[_]>::len)),
+            Some(0)
+        );
+
+        let _fixture = fixture.commit("second", &[("a.txt", "b\n")]);
+        std::fs::write(dir.path().join("a.txt"), "dirty\n").unwrap();
+        // the file events arrive on the watcher's own thread in real time
+        for _ in 0..50 {
+            std::thread::sleep(Duration::from_millis(20));
+            cx.executor().advance_clock(DEBOUNCE);
+            cx.run_until_parked();
+            let refreshed = garph.read_with(cx, |garph, _| garph.nodes().len() == 2)
+                && workspace.read_with(cx, |workspace, _| {
+                    workspace.uncommitted() == Some(&["M a.txt".to_string()][..])
+                });
+            if refreshed {
+                break;
+            }
+        }
+
+        garph.read_with(cx, |garph, _| assert_eq!(garph.nodes().len(), 2));
+        workspace.read_with(cx, |workspace, _| {
+            assert_eq!(workspace.uncommitted(), Some(&["M a.txt".to_string()][..]));
+            let files: Vec<_> = workspace
+                .changed_files()
+                .iter()
+                .map(|file| file.path.clone())
+                .collect();
+            assert_eq!(files, vec!["a.txt"], "the selected commit is kept");
+        });
+    }
+}